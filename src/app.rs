@@ -1,17 +1,80 @@
-use krakenrs::{AssetPairsResponse, AssetsResponse};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use crate::kraken::client::{RestAPI, RestAPIImpl};
+use krakenrs::AssetPairsResponse;
+
+use crate::config::Config;
+use crate::credentials::Credentials;
+use crate::kraken::client::RestAPIImpl;
+use crate::kraken::error::ClientError;
+use crate::kraken::messages::{
+    AddOrderResponse, AssetsResponse, BalanceResponse, Candle, History, OrderBookResponse,
+    OrderInfo, RecentSpreads, RecentTrades, TickerResponse, TradeInfo,
+};
+use crate::kraken::retry::{RetryAPI, RetryPolicy, RetryStats};
+use crate::kraken::worker::{Request, Response, Worker};
+use crate::kraken::ws_client::MarketDataImpl;
+use crate::kraken::ws_messages::{BookUpdate, Feed, MarketData, Subscription};
+use crate::kraken::ws_worker::{StreamCommand, StreamUpdate, StreamWorker};
+use crate::stm::events::Event;
+use crate::storage::{self, StorageError, Watchlist, DEFAULT_WATCHLIST};
 use crate::ui::list_stateful_widget::StatefulList;
 
+// how long a failed request is shown in the status bar
+const STATUS_ERROR_DURATION: Duration = Duration::from_secs(10);
+
 #[derive(Debug)]
 pub struct Model {
     pub assets: AssetsResponse,
     pub asset_pairs: AssetPairsResponse,
+    pub tickers: TickerResponse,
+    pub order_books: OrderBookResponse,
+    // books with a checksum mismatch, waiting for a new websocket snapshot
+    pub resyncing_books: HashSet<String>,
+    pub recent_trades: HashMap<String, RecentTrades>,
+    // keyed by asset pair and interval in minutes
+    pub candles: HashMap<(String, u32), Vec<Candle>>,
+    // API credentials available, the private API can be called
+    pub authenticated: bool,
+    // the websocket is connected, the subscribed market data is pushed without polling
+    pub streaming: bool,
+    // the last request failed on the network, the cached reference data is shown
+    pub offline: bool,
+    // unix time of the download of the assets and the asset pairs, they are cached on disk
+    pub assets_saved_at: Option<u64>,
+    pub asset_pairs_saved_at: Option<u64>,
+    pub recent_spreads: HashMap<String, RecentSpreads>,
+    pub balances: BalanceResponse,
+    // keyed by transaction id
+    pub open_orders: HashMap<String, OrderInfo>,
+    pub closed_orders: History<OrderInfo>,
+    pub trades_history: History<TradeInfo>,
+    // result of the last order submitted or validated
+    pub added_order: Option<AddOrderResponse>,
     pub debug_messages_stateful: StatefulList<String>,
     pub assets_stateful: StatefulList<String>,
     pub asset_pairs_stateful: StatefulList<String>,
+    // details of the asset selected in the Search state
+    pub asset_info_stateful: StatefulList<String>,
+    // asset pairs matching the query of the finder popup
+    pub finder_stateful: StatefulList<String>,
+    // pairs of the active watchlist, copied back to it by sync_watchlist
     pub favorites_asset_pairs_stateful: StatefulList<String>,
+    pub watchlists: Vec<Watchlist>,
+    pub active_watchlist: usize,
     pub favorites_asset_pairs_info_stateful: StatefulList<String>,
+    pub favorites_asset_pairs_ticker_stateful: StatefulList<String>,
+    pub order_book_bids_stateful: StatefulList<String>,
+    pub order_book_asks_stateful: StatefulList<String>,
+    pub trades_stateful: StatefulList<String>,
+    pub spreads_stateful: StatefulList<String>,
+    pub balances_stateful: StatefulList<String>,
+    pub open_orders_stateful: StatefulList<String>,
+    pub history_stateful: StatefulList<String>,
+    pub pending_requests: usize,
+    // the last failed request, shown in the status bar for a while
+    pub last_error: Option<(Instant, String)>,
 }
 
 impl Model {
@@ -19,33 +82,694 @@ impl Model {
         Model {
             assets: AssetsResponse::new(),
             asset_pairs: AssetPairsResponse::new(),
+            tickers: TickerResponse::new(),
+            order_books: OrderBookResponse::new(),
+            resyncing_books: HashSet::new(),
+            recent_trades: HashMap::new(),
+            candles: HashMap::new(),
+            authenticated: false,
+            streaming: false,
+            offline: false,
+            assets_saved_at: None,
+            asset_pairs_saved_at: None,
+            recent_spreads: HashMap::new(),
+            balances: BalanceResponse::new(),
+            open_orders: HashMap::new(),
+            closed_orders: History::default(),
+            trades_history: History::default(),
+            added_order: None,
             debug_messages_stateful: StatefulList::new(),
             assets_stateful: StatefulList::new(),
             asset_pairs_stateful: StatefulList::new(),
+            asset_info_stateful: StatefulList::new(),
+            finder_stateful: StatefulList::new(),
             favorites_asset_pairs_stateful: StatefulList::new(),
+            watchlists: vec![Watchlist::new(DEFAULT_WATCHLIST)],
+            active_watchlist: 0,
             favorites_asset_pairs_info_stateful: StatefulList::new(),
+            favorites_asset_pairs_ticker_stateful: StatefulList::new(),
+            order_book_bids_stateful: StatefulList::new(),
+            order_book_asks_stateful: StatefulList::new(),
+            trades_stateful: StatefulList::new(),
+            spreads_stateful: StatefulList::new(),
+            balances_stateful: StatefulList::new(),
+            open_orders_stateful: StatefulList::new(),
+            history_stateful: StatefulList::new(),
+            pending_requests: 0,
+            last_error: None,
+        }
+    }
+
+    // returns the kraken key (e.g. XETHZUSD) of the selected favorite asset pair
+    pub fn selected_favorite_key(&self) -> Option<String> {
+        let index = self.favorites_asset_pairs_stateful.state.selected()?;
+        let asset_pair = self.favorites_asset_pairs_stateful.items.get(index)?;
+
+        self.asset_pair_key(asset_pair)
+    }
+
+    // returns the kraken key (e.g. XETHZUSD) of the websocket name (e.g. ETH/USD)
+    pub fn asset_pair_key(&self, wsname: &str) -> Option<String> {
+        self.asset_pairs
+            .iter()
+            .find(|(_, pair)| pair.wsname.as_deref() == Some(wsname))
+            .map(|(key, _)| key.clone())
+    }
+
+    pub fn status_error(&self) -> Option<&str> {
+        self.last_error
+            .as_ref()
+            .filter(|(at, _)| at.elapsed() < STATUS_ERROR_DURATION)
+            .map(|(_, message)| message.as_str())
+    }
+
+    pub fn active_watchlist_name(&self) -> &str {
+        self.watchlists
+            .get(self.active_watchlist)
+            .map_or(DEFAULT_WATCHLIST, |watchlist| watchlist.name.as_str())
+    }
+
+    // copies the favorites back to the active watchlist
+    pub fn sync_watchlist(&mut self) {
+        if let Some(watchlist) = self.watchlists.get_mut(self.active_watchlist) {
+            watchlist
+                .pairs
+                .clone_from(&self.favorites_asset_pairs_stateful.items);
+        }
+    }
+
+    // replaces the favorites with the pairs of the watchlist, without syncing them back
+    pub fn show_watchlist(&mut self, index: usize) {
+        if let Some(watchlist) = self.watchlists.get(index) {
+            self.active_watchlist = index;
+            self.favorites_asset_pairs_stateful = StatefulList::with_items(watchlist.pairs.clone());
+        }
+    }
+
+    pub fn switch_watchlist(&mut self, index: usize) {
+        if index < self.watchlists.len() {
+            self.sync_watchlist();
+            self.show_watchlist(index);
+        }
+    }
+
+    // the names are unique and not empty, the new watchlist becomes the active one
+    pub fn add_watchlist(&mut self, name: &str) -> bool {
+        let name = name.trim();
+        if name.is_empty() || self.watchlists.iter().any(|w| w.name == name) {
+            return false;
+        }
+        self.watchlists.push(Watchlist::new(name));
+        self.switch_watchlist(self.watchlists.len() - 1);
+        true
+    }
+
+    pub fn rename_watchlist(&mut self, name: &str) -> bool {
+        let name = name.trim();
+        if name.is_empty() || self.watchlists.iter().any(|w| w.name == name) {
+            return false;
+        }
+        match self.watchlists.get_mut(self.active_watchlist) {
+            Some(watchlist) => {
+                name.clone_into(&mut watchlist.name);
+                true
+            }
+            None => false,
         }
     }
+
+    // the last watchlist can not be deleted, the previous one becomes the active one
+    pub fn delete_watchlist(&mut self) -> bool {
+        if self.watchlists.len() <= 1 || self.active_watchlist >= self.watchlists.len() {
+            return false;
+        }
+        self.watchlists.remove(self.active_watchlist);
+        self.show_watchlist(self.active_watchlist.saturating_sub(1));
+        true
+    }
+
+    // moves the selected favorite to the end of the other watchlist, returns the pair moved
+    pub fn move_selected_favorite(&mut self, to: usize) -> Option<String> {
+        if to == self.active_watchlist || to >= self.watchlists.len() {
+            return None;
+        }
+        let index = self.favorites_asset_pairs_stateful.state.selected()?;
+        if index >= self.favorites_asset_pairs_stateful.items.len() {
+            return None;
+        }
+
+        let pair = self.favorites_asset_pairs_stateful.items.remove(index);
+        self.favorites_asset_pairs_stateful.unselect();
+        self.sync_watchlist();
+        let pairs = &mut self.watchlists[to].pairs;
+        if !pairs.contains(&pair) {
+            pairs.push(pair.clone());
+        }
+        Some(pair)
+    }
+
+    // seconds since the download of the oldest reference data
+    pub fn data_age(&self, now: u64) -> Option<u64> {
+        [self.assets_saved_at, self.asset_pairs_saved_at]
+            .into_iter()
+            .flatten()
+            .min()
+            .map(|saved_at| now.saturating_sub(saved_at))
+    }
+
+    pub fn wsname(&self, asset_pair: &str) -> Option<String> {
+        self.asset_pairs
+            .get(asset_pair)
+            .and_then(|pair| pair.wsname.clone())
+    }
 }
 
 pub struct Context {
     app_id: String,
     app_version: String,
 
+    pub config: Config,
     pub model: Model,
-    pub kraken_api: Box<dyn RestAPI>,
+    kraken_worker: Worker,
+    // updated by the retry layer on the worker thread
+    client_stats: Arc<Mutex<RetryStats>>,
+    // None when the market data is only polled
+    stream_worker: Option<StreamWorker>,
 }
 
 impl Context {
-    pub fn new(app_id: String, app_version: String) -> Self {
-        Self {
+    pub fn new(
+        app_id: String,
+        app_version: String,
+        config: Config,
+        credentials: Option<Credentials>,
+    ) -> Self {
+        let authenticated = credentials.is_some();
+        let rest_api = RetryAPI::new(
+            Box::new(RestAPIImpl::new(config.api_url.clone(), credentials)),
+            RetryPolicy::default(),
+        );
+        let market_data_api = MarketDataImpl::new(config.ws_url.clone());
+        let mut ctx = Self {
             app_id,
             app_version,
+            config,
             model: Model::new(),
-            kraken_api: Box::new(RestAPIImpl::new(String::from("https://"))),
+            client_stats: rest_api.stats(),
+            kraken_worker: Worker::spawn(Box::new(rest_api)),
+            stream_worker: Some(StreamWorker::spawn(Box::new(market_data_api))),
+        };
+        ctx.model.authenticated = authenticated;
+        ctx.load_favorites();
+        ctx.load_reference_cache();
+
+        ctx
+    }
+
+    // the assets and the asset pairs of the previous session, shown until they are
+    // downloaded again or when kraken can not be reached.
+    pub fn load_reference_cache(&mut self) {
+        if let Some(path) = self.config.assets_cache_path() {
+            match storage::load_cache::<AssetsResponse>(&path) {
+                Ok(Some(cached)) => {
+                    self.model.assets = cached.data;
+                    self.model.assets_saved_at = Some(cached.saved_at);
+                }
+                Ok(None) => {}
+                Err(e) => self.debug(format!(
+                    "[Ctx] load_reference_cache {} failed {e}",
+                    path.display()
+                )),
+            }
+        }
+        if let Some(path) = self.config.asset_pairs_cache_path() {
+            match storage::load_cache::<AssetPairsResponse>(&path) {
+                Ok(Some(cached)) => {
+                    self.model.asset_pairs = cached.data;
+                    self.model.asset_pairs_saved_at = Some(cached.saved_at);
+                }
+                Ok(None) => {}
+                Err(e) => self.debug(format!(
+                    "[Ctx] load_reference_cache {} failed {e}",
+                    path.display()
+                )),
+            }
+        }
+    }
+
+    fn save_assets_cache(&mut self) {
+        let saved_at = unix_now();
+        self.model.assets_saved_at = Some(saved_at);
+        if let Some(path) = self.config.assets_cache_path() {
+            if let Err(e) = storage::save_cache(&path, saved_at, &self.model.assets) {
+                self.debug(format!(
+                    "[Ctx] save_assets_cache {} failed {e}",
+                    path.display()
+                ));
+            }
+        }
+    }
+
+    fn save_asset_pairs_cache(&mut self) {
+        let saved_at = unix_now();
+        self.model.asset_pairs_saved_at = Some(saved_at);
+        if let Some(path) = self.config.asset_pairs_cache_path() {
+            if let Err(e) = storage::save_cache(&path, saved_at, &self.model.asset_pairs) {
+                self.debug(format!(
+                    "[Ctx] save_asset_pairs_cache {} failed {e}",
+                    path.display()
+                ));
+            }
+        }
+    }
+
+    // reload the watchlists saved by the previous session, a corrupted file is moved aside
+    // and the session starts with an empty list.
+    pub fn load_favorites(&mut self) {
+        if let Some(path) = self.config.favorites_path() {
+            match storage::load_watchlists(&path) {
+                Ok((watchlists, active)) => {
+                    self.model.watchlists = watchlists;
+                    self.model.show_watchlist(active);
+                }
+                Err(StorageError::Json(e)) => {
+                    self.debug(format!(
+                        "[Ctx] load_favorites {} corrupted {e}",
+                        path.display()
+                    ));
+                    match storage::backup_corrupted(&path) {
+                        Ok(backup) => self.debug(format!(
+                            "[Ctx] load_favorites moved to {}",
+                            backup.display()
+                        )),
+                        Err(e) => self.debug(format!("[Ctx] load_favorites backup failed {e}")),
+                    }
+                }
+                Err(e) => self.debug(format!(
+                    "[Ctx] load_favorites {} failed {e}",
+                    path.display()
+                )),
+            }
+        }
+    }
+
+    // called every time the favorites or the watchlists are changed.
+    pub fn save_favorites(&mut self) {
+        self.model.sync_watchlist();
+        if let Some(path) = self.config.favorites_path() {
+            let watchlists = &self.model.watchlists;
+            if let Err(e) = storage::save_watchlists(&path, watchlists, self.model.active_watchlist)
+            {
+                self.debug(format!(
+                    "[Ctx] save_favorites {} failed {e}",
+                    path.display()
+                ));
+            }
+        }
+    }
+
+    // queue a request to the kraken worker, the result is posted back by poll_responses.
+    pub fn request(&mut self, request: Request) {
+        match self.kraken_worker.send(request) {
+            Ok(()) => self.model.pending_requests += 1,
+            Err(request) => self.debug(format!(
+                "[Ctx] request {request:?} failed, worker not running!!!"
+            )),
+        }
+    }
+
+    pub fn subscribe(&mut self, subscription: Subscription) {
+        self.stream_command(StreamCommand::Subscribe(subscription));
+    }
+
+    pub fn unsubscribe(&mut self, subscription: Subscription) {
+        self.stream_command(StreamCommand::Unsubscribe(subscription));
+    }
+
+    fn stream_command(&mut self, command: StreamCommand) {
+        if let Some(stream_worker) = &mut self.stream_worker {
+            if let Err(command) = stream_worker.send(command) {
+                self.debug(format!(
+                    "[Ctx] stream {command:?} failed, worker not running!!!"
+                ));
+            }
+        }
+    }
+
+    // drain the responses completed by the kraken worker and the market data pushed by the
+    // stream worker, store their payload in the model and convert them to the events
+    // consumed by the state machine.
+    pub fn poll_responses(&mut self) -> Vec<Event> {
+        let mut events = vec![];
+
+        while let Some(response) = self.kraken_worker.try_recv() {
+            self.model.pending_requests = self.model.pending_requests.saturating_sub(1);
+            events.push(self.on_response(response));
+        }
+
+        while let Some(update) = self.stream_worker.as_mut().and_then(StreamWorker::try_recv) {
+            events.extend(self.on_stream_update(update));
+        }
+
+        events
+    }
+
+    fn on_stream_update(&mut self, update: StreamUpdate) -> Option<Event> {
+        match update {
+            StreamUpdate::Connected => {
+                self.model.streaming = true;
+                self.debug("[Ctx] stream connected.".to_owned());
+                None
+            }
+            StreamUpdate::Disconnected { reason } => {
+                self.model.streaming = false;
+                self.debug(format!("[Ctx] stream disconnected {reason}"));
+                None
+            }
+            StreamUpdate::Invalid { reason } => {
+                self.debug(format!("[Ctx] stream invalid message {reason}"));
+                None
+            }
+            StreamUpdate::Data(data) => self.on_market_data(*data),
+        }
+    }
+
+    // the streamed market data is merged with the polled one and notified with the same events
+    fn on_market_data(&mut self, data: MarketData) -> Option<Event> {
+        let pair = match &data {
+            MarketData::Ticker { pair, .. }
+            | MarketData::Book { pair, .. }
+            | MarketData::Trades { pair, .. }
+            | MarketData::Ohlc { pair, .. }
+            | MarketData::Spread { pair, .. } => pair.clone(),
+            MarketData::Heartbeat => return None,
+            MarketData::Status { message } => {
+                self.debug(format!("[Ctx] stream {message}"));
+                return None;
+            }
+        };
+        let Some(asset_pair) = self.model.asset_pair_key(&pair) else {
+            self.debug(format!("[Ctx] stream unknown asset pair {pair}"));
+            return None;
+        };
+
+        match data {
+            MarketData::Ticker { ticker, .. } => {
+                self.model.tickers.insert(asset_pair.clone(), ticker);
+                Some(Event::Ticker {
+                    asset_pair,
+                    loaded: true,
+                })
+            }
+            MarketData::Book { depth, update, .. } => {
+                self.on_book_update(&pair, asset_pair, depth, update)
+            }
+            MarketData::Trades { trades, .. } => {
+                self.model
+                    .recent_trades
+                    .entry(asset_pair.clone())
+                    .or_default()
+                    .extend(trades);
+                Some(Event::RecentTrades {
+                    asset_pair,
+                    loaded: true,
+                })
+            }
+            MarketData::Ohlc {
+                interval, candle, ..
+            } => {
+                let candles = self
+                    .model
+                    .candles
+                    .entry((asset_pair.clone(), interval))
+                    .or_default();
+                // the candle of the running interval is pushed again at every trade
+                match candles.last_mut() {
+                    Some(last) if last.time == candle.time => *last = candle,
+                    Some(last) if last.time > candle.time => {}
+                    _ => candles.push(candle),
+                }
+                Some(Event::Ohlc {
+                    asset_pair,
+                    interval,
+                    loaded: true,
+                })
+            }
+            MarketData::Spread { spread, .. } => {
+                self.model
+                    .recent_spreads
+                    .entry(asset_pair.clone())
+                    .or_default()
+                    .extend(vec![spread]);
+                Some(Event::Spreads {
+                    asset_pair,
+                    loaded: true,
+                })
+            }
+            MarketData::Heartbeat | MarketData::Status { .. } => None,
+        }
+    }
+
+    // stores the payload in the model, one arm for each response of the worker
+    #[allow(clippy::too_many_lines)]
+    fn on_response(&mut self, response: Response) -> Event {
+        match response {
+            Response::Connected { result } => {
+                let error = result.as_ref().err().map(ToString::to_string);
+                self.loaded("connect", result);
+                Event::Connected {
+                    result: error.map_or(Ok(()), Err),
+                }
+            }
+            Response::Assets { assets } => {
+                let assets = self.loaded("assets", assets);
+                let loaded = assets.is_some();
+                if let Some(assets) = assets {
+                    self.model.assets = assets;
+                    self.save_assets_cache();
+                }
+                Event::Assets { loaded }
+            }
+            Response::AssetPairs { asset_pairs } => {
+                let asset_pairs = self.loaded("asset pairs", asset_pairs);
+                let loaded = asset_pairs.is_some();
+                if let Some(asset_pairs) = asset_pairs {
+                    self.model.asset_pairs = asset_pairs;
+                    self.save_asset_pairs_cache();
+                }
+                Event::AssetPairs { loaded }
+            }
+            Response::Ticker { asset_pair, ticker } => {
+                let ticker = self.loaded(&format!("ticker {asset_pair}"), ticker);
+                let loaded = ticker.is_some();
+                if let Some(ticker) = ticker {
+                    self.model.tickers.extend(ticker);
+                }
+                Event::Ticker { asset_pair, loaded }
+            }
+            Response::OrderBook {
+                asset_pair,
+                order_book,
+            } => {
+                let order_book = self.loaded(&format!("order book {asset_pair}"), order_book);
+                let loaded = order_book.is_some();
+                if let Some(order_book) = order_book {
+                    self.model.order_books.extend(order_book);
+                }
+                Event::OrderBook { asset_pair, loaded }
+            }
+            Response::RecentTrades { asset_pair, trades } => {
+                let trades = self.loaded(&format!("recent trades {asset_pair}"), trades);
+                let loaded = trades.is_some();
+                if let Some(trades) = trades {
+                    self.model
+                        .recent_trades
+                        .entry(asset_pair.clone())
+                        .or_default()
+                        .append(trades);
+                }
+                Event::RecentTrades { asset_pair, loaded }
+            }
+            Response::Ohlc {
+                asset_pair,
+                interval,
+                ohlc,
+            } => {
+                let ohlc = self.loaded(&format!("ohlc {asset_pair}"), ohlc);
+                let loaded = ohlc.is_some();
+                if let Some(ohlc) = ohlc {
+                    for candles in ohlc.candles.into_values() {
+                        self.model
+                            .candles
+                            .insert((asset_pair.clone(), interval), candles);
+                    }
+                }
+                Event::Ohlc {
+                    asset_pair,
+                    interval,
+                    loaded,
+                }
+            }
+            Response::Spreads {
+                asset_pair,
+                spreads,
+            } => {
+                let spreads = self.loaded(&format!("spreads {asset_pair}"), spreads);
+                let loaded = spreads.is_some();
+                if let Some(spreads) = spreads {
+                    self.model
+                        .recent_spreads
+                        .entry(asset_pair.clone())
+                        .or_default()
+                        .append(spreads);
+                }
+                Event::Spreads { asset_pair, loaded }
+            }
+            Response::Balances { balances } => {
+                let balances = self.loaded("balances", balances);
+                let loaded = balances.is_some();
+                if let Some(balances) = balances {
+                    self.model.balances = balances;
+                }
+                Event::Balances { loaded }
+            }
+            Response::OpenOrders { orders } => {
+                let orders = self.loaded("open orders", orders);
+                let loaded = orders.is_some();
+                if let Some(orders) = orders {
+                    self.model.open_orders = orders.open;
+                }
+                Event::OpenOrders { loaded }
+            }
+            Response::OrderCancelled { txid, result } => {
+                let error = result.as_ref().err().map(ToString::to_string);
+                let result = match self.loaded(&format!("cancel order {txid}"), result) {
+                    Some(result) if result.count > 0 || result.pending => Ok(()),
+                    Some(_) => Err("no order cancelled".to_owned()),
+                    None => Err(error.unwrap_or_default()),
+                };
+                if result.is_ok() {
+                    self.model.open_orders.remove(&txid);
+                }
+                Event::OrderCancelled { txid, result }
+            }
+            Response::ClosedOrders {
+                generation,
+                offset,
+                orders,
+            } => {
+                let orders = self.loaded("closed orders", orders);
+                let loaded = orders.is_some();
+                if let Some(orders) = orders {
+                    let mut page = orders.closed.into_iter().collect::<Vec<_>>();
+                    page.sort_by(|(_, a), (_, b)| b.closetm.total_cmp(&a.closetm));
+                    self.model
+                        .closed_orders
+                        .append(generation, offset, orders.count, page);
+                }
+                Event::ClosedOrders {
+                    generation,
+                    offset,
+                    loaded,
+                }
+            }
+            Response::TradesHistory {
+                generation,
+                offset,
+                trades,
+            } => {
+                let trades = self.loaded("trades history", trades);
+                let loaded = trades.is_some();
+                if let Some(trades) = trades {
+                    let mut page = trades.trades.into_iter().collect::<Vec<_>>();
+                    page.sort_by(|(_, a), (_, b)| b.time.total_cmp(&a.time));
+                    self.model
+                        .trades_history
+                        .append(generation, offset, trades.count, page);
+                }
+                Event::TradesHistory {
+                    generation,
+                    offset,
+                    loaded,
+                }
+            }
+            Response::OrderAdded { order, result } => {
+                let error = result.as_ref().err().map(ToString::to_string);
+                let added = self.loaded("add order", result);
+                let result = if added.is_some() {
+                    Ok(())
+                } else {
+                    Err(error.unwrap_or_default())
+                };
+                self.model.added_order = added;
+                Event::OrderAdded {
+                    validate: order.validate,
+                    result,
+                }
+            }
+        }
+    }
+
+    // the local book is verified against the checksum of every update, on mismatch the
+    // updates are ignored until the snapshot of a new subscription arrives.
+    fn on_book_update(
+        &mut self,
+        pair: &str,
+        asset_pair: String,
+        depth: u32,
+        update: BookUpdate,
+    ) -> Option<Event> {
+        if update.snapshot {
+            self.model.resyncing_books.remove(&asset_pair);
+        } else if self.model.resyncing_books.contains(&asset_pair) {
+            return None;
+        }
+
+        let checksum = update.checksum;
+        let order_book = self
+            .model
+            .order_books
+            .entry(asset_pair.clone())
+            .or_default();
+        order_book.apply(update, usize::try_from(depth).unwrap_or(usize::MAX));
+
+        if matches!(checksum, Some(checksum) if checksum != order_book.checksum()) {
+            self.debug(format!(
+                "[Ctx] stream book {pair} checksum mismatch, resubscribing."
+            ));
+            self.model.resyncing_books.insert(asset_pair.clone());
+
+            let subscription = Subscription::new(pair, Feed::Book(depth));
+            self.unsubscribe(subscription.clone());
+            self.subscribe(subscription);
+        }
+
+        Some(Event::OrderBook {
+            asset_pair,
+            loaded: true,
+        })
+    }
+
+    // logs the reason of a failed request and shows it in the status bar
+    fn loaded<T>(&mut self, request: &str, result: Result<T, ClientError>) -> Option<T> {
+        self.model.offline = matches!(result, Err(ClientError::Network(_)));
+        match result {
+            Ok(payload) => Some(payload),
+            Err(e) => {
+                self.debug(format!("[Ctx] {request} failed {e}"));
+                self.model.last_error = Some((Instant::now(), format!("{request} failed, {e}")));
+                None
+            }
         }
     }
 
+    pub fn client_stats(&self) -> RetryStats {
+        *self
+            .client_stats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
     pub fn info(&self) -> String {
         format!("AppId:{}, AppVersion:{}\n", self.app_id, self.app_version)
     }
@@ -55,19 +779,45 @@ impl Context {
     }
 }
 
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
 #[cfg(test)]
 mod mock_test {
-    use super::{Context, Model};
+    use std::sync::Arc;
+
+    use super::{Config, Context, Model};
     use crate::kraken::client::RestAPI;
+    use crate::kraken::worker::Worker;
+    use crate::kraken::ws_client::MarketDataAPI;
+    use crate::kraken::ws_worker::StreamWorker;
 
     impl Context {
         #[allow(unused)]
-        pub fn new_for_testing(kraken_api: Box<dyn RestAPI>) -> Self {
+        pub fn new_for_testing(kraken_api: Box<dyn RestAPI + Send>) -> Self {
             Self {
                 app_id: String::from("_app_id_"),
                 app_version: String::from("_app_version_"),
+                config: Config::default(),
                 model: Model::new(),
-                kraken_api,
+                client_stats: Arc::default(),
+                kraken_worker: Worker::inline(kraken_api),
+                stream_worker: None,
+            }
+        }
+
+        // the subscriptions are sent to the market data api, already connected
+        #[allow(unused)]
+        pub fn new_for_testing_with_stream(
+            kraken_api: Box<dyn RestAPI + Send>,
+            market_data_api: Box<dyn MarketDataAPI + Send>,
+        ) -> Self {
+            Self {
+                stream_worker: Some(StreamWorker::inline(market_data_api)),
+                ..Context::new_for_testing(kraken_api)
             }
         }
     }
@@ -91,6 +841,349 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_request_responses() {
+        let mut mock_client = Box::new(MockRestAPI::new());
+        mock_client
+            .expect_list_assets()
+            .once()
+            .returning(|| Ok(AssetsResponse::new()));
+        mock_client
+            .expect_list_asset_pairs()
+            .once()
+            .returning(|| Err(ClientError::RateLimit));
+        let mut ctx = Context::new_for_testing(mock_client);
+        assert_eq!(ctx.model.status_error(), None);
+
+        ctx.request(Request::ListAssets);
+        ctx.request(Request::ListAssetPairs);
+        assert_eq!(ctx.model.pending_requests, 2);
+
+        let events = ctx.poll_responses();
+        assert_eq!(ctx.model.pending_requests, 0);
+        assert_eq!(events.len(), 2);
+        assert!(matches!(events[0], Event::Assets { loaded: true }));
+        assert!(matches!(events[1], Event::AssetPairs { loaded: false }));
+        assert_eq!(
+            ctx.model.debug_messages_stateful.items,
+            vec!["[Ctx] asset pairs failed rate limit exceeded".to_owned()]
+        );
+        assert_eq!(
+            ctx.model.status_error(),
+            Some("asset pairs failed, rate limit exceeded")
+        );
+
+        assert!(ctx.poll_responses().is_empty());
+    }
+
+    #[test]
+    fn test_favorites_persistence() {
+        let dir = std::env::temp_dir().join(format!("kraken_tui_ctx_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut ctx = Context::new_for_testing(Box::new(MockRestAPI::new()));
+        ctx.config.config_dir = Some(dir.clone());
+
+        ctx.load_favorites();
+        assert!(ctx.model.favorites_asset_pairs_stateful.items.is_empty());
+
+        ctx.model
+            .favorites_asset_pairs_stateful
+            .push("XBT/EUR".to_owned());
+        ctx.model
+            .favorites_asset_pairs_stateful
+            .push("ETH/USDC".to_owned());
+        ctx.save_favorites();
+
+        let mut ctx = Context::new_for_testing(Box::new(MockRestAPI::new()));
+        ctx.config.config_dir = Some(dir.clone());
+        ctx.load_favorites();
+        assert_eq!(
+            ctx.model.favorites_asset_pairs_stateful.items,
+            vec!["XBT/EUR".to_owned(), "ETH/USDC".to_owned()]
+        );
+
+        // the active watchlist is remembered
+        assert!(ctx.model.add_watchlist("alts"));
+        ctx.model
+            .favorites_asset_pairs_stateful
+            .push("DOT/USD".to_owned());
+        ctx.save_favorites();
+        ctx.load_favorites();
+        assert_eq!(ctx.model.active_watchlist_name(), "alts");
+        assert_eq!(
+            ctx.model.favorites_asset_pairs_stateful.items,
+            vec!["DOT/USD".to_owned()]
+        );
+
+        // a corrupted file is moved aside
+        std::fs::write(dir.join("favorites.json"), "[").unwrap();
+        ctx.load_favorites();
+        assert!(dir.join("favorites.json.corrupted").exists());
+        assert!(!dir.join("favorites.json").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_watchlists() {
+        let mut model = Model::new();
+        model
+            .favorites_asset_pairs_stateful
+            .push("XBT/EUR".to_owned());
+        model
+            .favorites_asset_pairs_stateful
+            .push("ETH/USD".to_owned());
+
+        assert!(model.add_watchlist(" alts "));
+        assert!(!model.add_watchlist("alts"));
+        assert!(!model.add_watchlist(""));
+        assert_eq!(model.active_watchlist_name(), "alts");
+        assert!(model.favorites_asset_pairs_stateful.items.is_empty());
+
+        model.switch_watchlist(0);
+        model.favorites_asset_pairs_stateful.next();
+        model.favorites_asset_pairs_stateful.next();
+        assert_eq!(model.move_selected_favorite(0), None);
+        assert_eq!(model.move_selected_favorite(1), Some("ETH/USD".to_owned()));
+        assert_eq!(model.watchlists[0].pairs, vec!["XBT/EUR".to_owned()]);
+        assert_eq!(model.watchlists[1].pairs, vec!["ETH/USD".to_owned()]);
+
+        model.switch_watchlist(1);
+        assert!(!model.rename_watchlist("favorites"));
+        assert!(model.rename_watchlist("altcoins"));
+        assert_eq!(model.active_watchlist_name(), "altcoins");
+
+        // the last watchlist is kept
+        assert!(model.delete_watchlist());
+        assert_eq!(model.active_watchlist_name(), "favorites");
+        assert_eq!(
+            model.favorites_asset_pairs_stateful.items,
+            vec!["XBT/EUR".to_owned()]
+        );
+        assert!(!model.delete_watchlist());
+    }
+
+    #[test]
+    fn test_reference_cache() {
+        let dir = std::env::temp_dir().join(format!("kraken_tui_cache_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut mock_client = Box::new(MockRestAPI::new());
+        mock_client.expect_list_asset_pairs().once().returning(|| {
+            let mut asset_pairs = AssetPairsResponse::new();
+            asset_pairs.insert("XXBTZUSD".to_owned(), serde_json::from_str(
+                r#"{"altname":"XBTUSD","wsname":"XBT/USD","aclass_base":"currency","base":"XXBT",
+                    "aclass_quote":"currency","quote":"ZUSD","pair_decimals":1,"lot_decimals":8,
+                    "lot_multiplier":1,"fees":[[0,0.26]],"fees_maker":[[0,0.16]],
+                    "fee_volume_currency":"ZUSD","margin_call":80,"margin_stop":40,"ordermin":"0.0001"}"#,
+            ).unwrap());
+            Ok(asset_pairs)
+        });
+        mock_client
+            .expect_list_asset_pairs()
+            .once()
+            .returning(|| Err(ClientError::Network("timeout".to_owned())));
+        let mut ctx = Context::new_for_testing(mock_client);
+        ctx.config.config_dir = Some(dir.clone());
+
+        ctx.request(Request::ListAssetPairs);
+        ctx.poll_responses();
+        assert!(!ctx.model.offline);
+        assert!(dir.join("cache").join("asset_pairs.json").exists());
+
+        // the next session starts with the cached data, kept when kraken can not be reached
+        let saved_at = ctx.model.asset_pairs_saved_at;
+        ctx.model = Model::new();
+        ctx.load_reference_cache();
+        assert_eq!(ctx.model.asset_pairs_saved_at, saved_at);
+        assert_eq!(ctx.model.wsname("XXBTZUSD"), Some("XBT/USD".to_owned()));
+
+        ctx.request(Request::ListAssetPairs);
+        assert!(matches!(
+            ctx.poll_responses()[0],
+            Event::AssetPairs { loaded: false }
+        ));
+        assert!(ctx.model.offline);
+        assert_eq!(ctx.model.wsname("XXBTZUSD"), Some("XBT/USD".to_owned()));
+        assert_eq!(ctx.model.data_age(saved_at.unwrap() + 90), Some(90));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_stream_updates() {
+        use crate::kraken::messages::{Spread, TickerInfo};
+        use crate::kraken::ws_client::MockMarketDataAPI;
+        use crate::kraken::ws_messages::Feed;
+        use mockall::Sequence;
+
+        let mut sequence = Sequence::new();
+        let mut mock_api = Box::new(MockMarketDataAPI::new());
+        mock_api.expect_subscribe().once().returning(|_| Ok(()));
+        mock_api
+            .expect_read()
+            .once()
+            .in_sequence(&mut sequence)
+            .returning(|| {
+                Ok(Some(MarketData::Ticker {
+                    pair: "XBT/USD".to_owned(),
+                    ticker: TickerInfo {
+                        c: vec!["30000.0".to_owned()],
+                        ..TickerInfo::default()
+                    },
+                }))
+            });
+        mock_api
+            .expect_read()
+            .once()
+            .in_sequence(&mut sequence)
+            .returning(|| Ok(Some(MarketData::Heartbeat)));
+        mock_api
+            .expect_read()
+            .once()
+            .in_sequence(&mut sequence)
+            .returning(|| {
+                Ok(Some(MarketData::Spread {
+                    pair: "UNKNOWN".to_owned(),
+                    spread: Spread {
+                        time: 0,
+                        bid: "1.0".to_owned(),
+                        ask: "2.0".to_owned(),
+                    },
+                }))
+            });
+        mock_api
+            .expect_read()
+            .once()
+            .in_sequence(&mut sequence)
+            .returning(|| Ok(None));
+
+        let mut ctx = Context::new_for_testing(Box::new(MockRestAPI::new()));
+        ctx.stream_worker = Some(StreamWorker::inline(mock_api));
+        ctx.model.asset_pairs = serde_json::from_str(
+            r#"{ "XXBTZUSD": {
+              "alt_name": "XBTUSD", "wsname": "XBT/USD", "aclass_base": "currency", "base": "XXBT",
+              "aclass_quote": "currency", "quote": "ZUSD", "pair_decimals": 1, "lot_decimals": 8,
+              "lot_multiplier": 1, "fees": [], "ordermin": "0.0001"
+            } }"#,
+        )
+        .unwrap();
+
+        ctx.subscribe(Subscription::new("XBT/USD", Feed::Ticker));
+        let events = ctx.poll_responses();
+
+        assert_eq!(events.len(), 1);
+        assert!(matches!(
+            &events[0],
+            Event::Ticker { asset_pair, loaded: true } if asset_pair == "XXBTZUSD"
+        ));
+        assert_eq!(ctx.model.tickers["XXBTZUSD"].last(), Some(30000.0));
+        assert!(ctx.model.recent_spreads.is_empty());
+        assert_eq!(
+            ctx.model.debug_messages_stateful.items,
+            vec!["[Ctx] stream unknown asset pair UNKNOWN".to_owned()]
+        );
+    }
+
+    #[test]
+    fn test_stream_book_checksum_mismatch() {
+        use crate::kraken::messages::OrderBookEntry;
+        use crate::kraken::ws_client::MockMarketDataAPI;
+        use crate::kraken::ws_messages::Feed;
+        use mockall::predicate::eq;
+        use mockall::Sequence;
+
+        fn book(snapshot: bool, checksum: Option<u32>) -> MarketData {
+            MarketData::Book {
+                pair: "XBT/USD".to_owned(),
+                depth: 10,
+                update: BookUpdate {
+                    snapshot,
+                    asks: vec![OrderBookEntry(
+                        "0.05005".to_owned(),
+                        "0.00000500".to_owned(),
+                        0,
+                    )],
+                    bids: vec![],
+                    checksum,
+                },
+            }
+        }
+
+        let subscription = Subscription::new("XBT/USD", Feed::Book(10));
+        let valid = crc32fast::hash(b"5005500");
+
+        let mut sequence = Sequence::new();
+        let mut mock_api = Box::new(MockMarketDataAPI::new());
+        mock_api
+            .expect_subscribe()
+            .with(eq(subscription.clone()))
+            .once()
+            .in_sequence(&mut sequence)
+            .returning(|_| Ok(()));
+        mock_api
+            .expect_read()
+            .once()
+            .in_sequence(&mut sequence)
+            .returning(move || Ok(Some(book(false, Some(valid)))));
+        mock_api
+            .expect_read()
+            .once()
+            .in_sequence(&mut sequence)
+            .returning(|| Ok(Some(book(false, Some(1)))));
+        mock_api
+            .expect_unsubscribe()
+            .with(eq(subscription.clone()))
+            .once()
+            .in_sequence(&mut sequence)
+            .returning(|_| Ok(()));
+        mock_api
+            .expect_subscribe()
+            .with(eq(subscription.clone()))
+            .once()
+            .in_sequence(&mut sequence)
+            .returning(|_| Ok(()));
+        // ignored until the new snapshot
+        mock_api
+            .expect_read()
+            .once()
+            .in_sequence(&mut sequence)
+            .returning(|| Ok(Some(book(false, None))));
+        mock_api
+            .expect_read()
+            .once()
+            .in_sequence(&mut sequence)
+            .returning(|| Ok(Some(book(true, None))));
+        mock_api
+            .expect_read()
+            .once()
+            .in_sequence(&mut sequence)
+            .returning(|| Ok(None));
+
+        let mut ctx = Context::new_for_testing(Box::new(MockRestAPI::new()));
+        ctx.stream_worker = Some(StreamWorker::inline(mock_api));
+        ctx.model.asset_pairs = serde_json::from_str(
+            r#"{ "XXBTZUSD": {
+              "alt_name": "XBTUSD", "wsname": "XBT/USD", "aclass_base": "currency", "base": "XXBT",
+              "aclass_quote": "currency", "quote": "ZUSD", "pair_decimals": 1, "lot_decimals": 8,
+              "lot_multiplier": 1, "fees": [], "ordermin": "0.0001"
+            } }"#,
+        )
+        .unwrap();
+
+        ctx.subscribe(subscription);
+        let events = ctx.poll_responses();
+
+        assert_eq!(events.len(), 3);
+        assert!(ctx.model.resyncing_books.is_empty());
+        assert_eq!(ctx.model.order_books["XXBTZUSD"].asks.len(), 1);
+        assert_eq!(
+            ctx.model.debug_messages_stateful.items,
+            vec!["[Ctx] stream book XBT/USD checksum mismatch, resubscribing.".to_owned()]
+        );
+    }
+
     // #[test]
     // fn test_app_model_debug() {
     //   let model = AppModel::new();
@@ -0,0 +1,44 @@
+#![warn(clippy::pedantic)]
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::must_use_candidate)]
+#[path = "../mock_server.rs"]
+mod mock_server;
+
+use std::path::PathBuf;
+
+use mock_server::{default_fixtures_dir, MockServer};
+
+const DEFAULT_PORT: u16 = 8080;
+
+// Mock of the Kraken REST API serving the json fixtures on localhost, for offline
+// development and demos:
+//
+//   kraken_mock --port 8080 --fixtures ./fixtures
+//   kraken_tui --api-url http://127.0.0.1:8080
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut port = DEFAULT_PORT;
+    let mut fixtures_dir = default_fixtures_dir();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(option) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("missing value for option {option}"))?;
+        match option.as_str() {
+            "--port" => port = value.parse()?,
+            "--fixtures" => fixtures_dir = PathBuf::from(value),
+            _ => return Err(format!("unknown option {option}").into()),
+        }
+    }
+
+    let server = MockServer::bind(port, &fixtures_dir)?;
+    println!(
+        "[mock] serving {} on {}",
+        fixtures_dir.display(),
+        server.url()?
+    );
+    server
+        .spawn()
+        .join()
+        .map_err(|_| "the mock server panicked".into())
+}
@@ -0,0 +1,420 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::Deserialize;
+use thiserror::Error;
+
+const TICK_RATE_ENV: &str = "KRAKEN_TUI_TICK_RATE";
+const CONFIG_DIR_ENV: &str = "KRAKEN_TUI_CONFIG_DIR";
+const CREDENTIALS_ENV: &str = "KRAKEN_TUI_CREDENTIALS";
+const API_URL_ENV: &str = "KRAKEN_TUI_API_URL";
+const WS_URL_ENV: &str = "KRAKEN_TUI_WS_URL";
+const APP_DIR: &str = "kraken_tui";
+const FAVORITES_FILE: &str = "favorites.json";
+const CREDENTIALS_FILE: &str = "credentials.json";
+const CONFIG_FILE: &str = "config.json";
+const CACHE_DIR: &str = "cache";
+const ASSETS_CACHE_FILE: &str = "assets.json";
+const ASSET_PAIRS_CACHE_FILE: &str = "asset_pairs.json";
+const DEFAULT_API_URL: &str = "https://api.kraken.com";
+const DEFAULT_WS_URL: &str = "wss://ws.kraken.com";
+const DEFAULT_TICK_RATE: Duration = Duration::from_secs(1);
+const MIN_TICK_RATE: Duration = Duration::from_millis(100);
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ConfigError {
+    #[error("unknown option {0}")]
+    UnknownOption(String),
+    #[error("missing value for option {0}")]
+    MissingValue(String),
+    #[error("invalid value {value} for option {option}")]
+    InvalidValue { option: String, value: String },
+    #[error("invalid config file {} {reason}", path.display())]
+    InvalidFile { path: PathBuf, reason: String },
+}
+
+// config.json in the config directory, the keys mirror the command line options
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct ConfigFile {
+    tick_rate: Option<u64>,
+    api_url: Option<String>,
+    ws_url: Option<String>,
+}
+
+// the field names mirror the command line options
+#[allow(clippy::struct_field_names)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    // interval between two Event::Tick delivered to the state machine
+    pub tick_rate: Duration,
+    // directory where the user data is persisted, nothing is persisted if None
+    pub config_dir: Option<PathBuf>,
+    // overrides the credentials.json of the config directory
+    pub credentials_file: Option<PathBuf>,
+    // base url of the REST API, e.g. a local mock server or a proxy
+    pub api_url: String,
+    // url of the websocket API
+    pub ws_url: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            tick_rate: DEFAULT_TICK_RATE,
+            config_dir: None,
+            credentials_file: None,
+            api_url: DEFAULT_API_URL.to_owned(),
+            ws_url: DEFAULT_WS_URL.to_owned(),
+        }
+    }
+}
+
+impl Config {
+    // builds the configuration from the config file, overridden by the environment variables
+    // and then by the command line arguments.
+    pub fn load() -> Result<Config, ConfigError> {
+        Config::load_from(
+            |name| std::env::var(name).ok(),
+            std::env::args().skip(1).collect(),
+        )
+    }
+
+    fn load_from<F>(lookup: F, args: Vec<String>) -> Result<Config, ConfigError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let mut config = Config {
+            config_dir: default_config_dir(&lookup),
+            ..Config::default()
+        };
+
+        // the config directory itself can be moved by the environment or the arguments
+        let mut located = config.clone();
+        located.apply_env(&lookup)?;
+        located.apply_args(args.clone())?;
+        if let Some(path) = located.config_file_path() {
+            config.apply_file(&path)?;
+        }
+
+        config.apply_env(&lookup)?;
+        config.apply_args(args)?;
+
+        Ok(config)
+    }
+
+    // a missing file is not an error, all its keys are optional
+    pub fn apply_file(&mut self, path: &Path) -> Result<(), ConfigError> {
+        let invalid = |reason: String| ConfigError::InvalidFile {
+            path: path.to_owned(),
+            reason,
+        };
+
+        let file: ConfigFile = match fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text).map_err(|e| invalid(e.to_string()))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(invalid(e.to_string())),
+        };
+
+        if let Some(millis) = file.tick_rate {
+            self.tick_rate = parse_tick_rate("tick-rate", &millis.to_string())?;
+        }
+        if let Some(url) = file.api_url {
+            self.api_url = parse_url("api-url", &url, &["http://", "https://"])?;
+        }
+        if let Some(url) = file.ws_url {
+            self.ws_url = parse_url("ws-url", &url, &["ws://", "wss://"])?;
+        }
+
+        Ok(())
+    }
+
+    pub fn apply_env<F>(&mut self, lookup: F) -> Result<(), ConfigError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        if let Some(value) = lookup(TICK_RATE_ENV) {
+            self.tick_rate = parse_tick_rate(TICK_RATE_ENV, &value)?;
+        }
+        if let Some(value) = lookup(CONFIG_DIR_ENV) {
+            self.config_dir = Some(PathBuf::from(value));
+        }
+        if let Some(value) = lookup(CREDENTIALS_ENV) {
+            self.credentials_file = Some(PathBuf::from(value));
+        }
+        if let Some(value) = lookup(API_URL_ENV) {
+            self.api_url = parse_url(API_URL_ENV, &value, &["http://", "https://"])?;
+        }
+        if let Some(value) = lookup(WS_URL_ENV) {
+            self.ws_url = parse_url(WS_URL_ENV, &value, &["ws://", "wss://"])?;
+        }
+
+        Ok(())
+    }
+
+    pub fn apply_args<I>(&mut self, args: I) -> Result<(), ConfigError>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut args = args.into_iter();
+        while let Some(option) = args.next() {
+            match option.as_str() {
+                "--tick-rate" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| ConfigError::MissingValue(option.clone()))?;
+                    self.tick_rate = parse_tick_rate(&option, &value)?;
+                }
+                "--config-dir" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| ConfigError::MissingValue(option.clone()))?;
+                    self.config_dir = Some(PathBuf::from(value));
+                }
+                "--credentials" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| ConfigError::MissingValue(option.clone()))?;
+                    self.credentials_file = Some(PathBuf::from(value));
+                }
+                "--api-url" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| ConfigError::MissingValue(option.clone()))?;
+                    self.api_url = parse_url(&option, &value, &["http://", "https://"])?;
+                }
+                "--ws-url" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| ConfigError::MissingValue(option.clone()))?;
+                    self.ws_url = parse_url(&option, &value, &["ws://", "wss://"])?;
+                }
+                _ => return Err(ConfigError::UnknownOption(option)),
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn favorites_path(&self) -> Option<PathBuf> {
+        self.config_dir.as_ref().map(|dir| dir.join(FAVORITES_FILE))
+    }
+
+    pub fn config_file_path(&self) -> Option<PathBuf> {
+        self.config_dir.as_ref().map(|dir| dir.join(CONFIG_FILE))
+    }
+
+    pub fn assets_cache_path(&self) -> Option<PathBuf> {
+        self.cache_path(ASSETS_CACHE_FILE)
+    }
+
+    pub fn asset_pairs_cache_path(&self) -> Option<PathBuf> {
+        self.cache_path(ASSET_PAIRS_CACHE_FILE)
+    }
+
+    fn cache_path(&self, file: &str) -> Option<PathBuf> {
+        self.config_dir
+            .as_ref()
+            .map(|dir| dir.join(CACHE_DIR).join(file))
+    }
+
+    pub fn credentials_path(&self) -> Option<PathBuf> {
+        self.credentials_file.clone().or_else(|| {
+            self.config_dir
+                .as_ref()
+                .map(|dir| dir.join(CREDENTIALS_FILE))
+        })
+    }
+}
+
+// $XDG_CONFIG_HOME/kraken_tui or $HOME/.config/kraken_tui
+fn default_config_dir<F>(lookup: F) -> Option<PathBuf>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    lookup("XDG_CONFIG_HOME")
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| lookup("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join(APP_DIR))
+}
+
+// only the scheme is checked, the host is resolved when connecting
+fn parse_url(option: &str, value: &str, schemes: &[&str]) -> Result<String, ConfigError> {
+    if schemes
+        .iter()
+        .any(|scheme| value.len() > scheme.len() && value.starts_with(scheme))
+    {
+        Ok(value.to_owned())
+    } else {
+        Err(ConfigError::InvalidValue {
+            option: option.to_owned(),
+            value: value.to_owned(),
+        })
+    }
+}
+
+// the tick rate is expressed in milliseconds
+fn parse_tick_rate(option: &str, value: &str) -> Result<Duration, ConfigError> {
+    match value.parse::<u64>() {
+        Ok(millis) if Duration::from_millis(millis) >= MIN_TICK_RATE => {
+            Ok(Duration::from_millis(millis))
+        }
+        _ => Err(ConfigError::InvalidValue {
+            option: option.to_owned(),
+            value: value.to_owned(),
+        }),
+    }
+}
+
+// tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| (*value).to_owned()).collect()
+    }
+
+    #[test]
+    fn test_default() {
+        let config = Config::default();
+        assert_eq!(config.tick_rate, Duration::from_secs(1));
+        assert_eq!(config.config_dir, None);
+        assert_eq!(config.favorites_path(), None);
+    }
+
+    #[test]
+    fn test_config_dir() {
+        let dir = default_config_dir(|name| (name == "HOME").then(|| "/home/user".to_owned()));
+        assert_eq!(dir, Some(PathBuf::from("/home/user/.config/kraken_tui")));
+
+        let dir = default_config_dir(|name| (name == "XDG_CONFIG_HOME").then(|| "/xdg".to_owned()));
+        assert_eq!(dir, Some(PathBuf::from("/xdg/kraken_tui")));
+
+        assert_eq!(default_config_dir(|_| None), None);
+
+        let mut config = Config::default();
+        config
+            .apply_args(args(&["--config-dir", "/tmp/kt"]))
+            .unwrap();
+        assert_eq!(
+            config.favorites_path(),
+            Some(PathBuf::from("/tmp/kt/favorites.json"))
+        );
+        assert_eq!(
+            config.credentials_path(),
+            Some(PathBuf::from("/tmp/kt/credentials.json"))
+        );
+        assert_eq!(
+            config.asset_pairs_cache_path(),
+            Some(PathBuf::from("/tmp/kt/cache/asset_pairs.json"))
+        );
+
+        config
+            .apply_env(|name| (name == CREDENTIALS_ENV).then(|| "/secrets/kt.json".to_owned()))
+            .unwrap();
+        assert_eq!(
+            config.credentials_path(),
+            Some(PathBuf::from("/secrets/kt.json"))
+        );
+    }
+
+    #[test]
+    fn test_tick_rate() {
+        let mut config = Config::default();
+
+        config
+            .apply_env(|name| (name == TICK_RATE_ENV).then(|| "500".to_owned()))
+            .unwrap();
+        assert_eq!(config.tick_rate, Duration::from_millis(500));
+
+        config.apply_args(args(&["--tick-rate", "250"])).unwrap();
+        assert_eq!(config.tick_rate, Duration::from_millis(250));
+    }
+
+    #[test]
+    fn test_urls() {
+        let mut config = Config::default();
+        assert_eq!(config.api_url, "https://api.kraken.com");
+        assert_eq!(config.ws_url, "wss://ws.kraken.com");
+
+        config
+            .apply_env(|name| (name == API_URL_ENV).then(|| "http://127.0.0.1:8080".to_owned()))
+            .unwrap();
+        assert_eq!(config.api_url, "http://127.0.0.1:8080");
+
+        config
+            .apply_args(args(&["--ws-url", "ws://127.0.0.1:8081"]))
+            .unwrap();
+        assert_eq!(config.ws_url, "ws://127.0.0.1:8081");
+
+        assert_eq!(
+            config.apply_args(args(&["--api-url", "wss://127.0.0.1"])),
+            Err(ConfigError::InvalidValue {
+                option: "--api-url".to_owned(),
+                value: "wss://127.0.0.1".to_owned()
+            })
+        );
+    }
+
+    #[test]
+    fn test_config_file() {
+        let dir = std::env::temp_dir().join(format!("kraken_tui_config_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join(CONFIG_FILE),
+            r#"{"api-url": "http://localhost:8080", "ws-url": "ws://localhost:8081", "tick-rate": 500}"#,
+        )
+        .unwrap();
+
+        let dir_value = dir.to_string_lossy().to_string();
+        let lookup = |name: &str| match name {
+            CONFIG_DIR_ENV => Some(dir_value.clone()),
+            WS_URL_ENV => Some("ws://localhost:9091".to_owned()),
+            _ => None,
+        };
+
+        // the environment and then the arguments win over the file
+        let config = Config::load_from(lookup, args(&["--tick-rate", "250"])).unwrap();
+        assert_eq!(config.api_url, "http://localhost:8080");
+        assert_eq!(config.ws_url, "ws://localhost:9091");
+        assert_eq!(config.tick_rate, Duration::from_millis(250));
+
+        fs::write(dir.join(CONFIG_FILE), r#"{"api_url": "http://localhost"}"#).unwrap();
+        assert!(matches!(
+            Config::load_from(lookup, vec![]),
+            Err(ConfigError::InvalidFile { path, .. }) if path == dir.join(CONFIG_FILE)
+        ));
+
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            Config::load_from(lookup, vec![]).unwrap().api_url,
+            "https://api.kraken.com"
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut config = Config::default();
+
+        assert_eq!(
+            config.apply_args(args(&["--tick-rate"])),
+            Err(ConfigError::MissingValue("--tick-rate".to_owned()))
+        );
+        assert_eq!(
+            config.apply_args(args(&["--tick-rate", "10"])),
+            Err(ConfigError::InvalidValue {
+                option: "--tick-rate".to_owned(),
+                value: "10".to_owned()
+            })
+        );
+        assert_eq!(
+            config.apply_args(args(&["--verbose"])),
+            Err(ConfigError::UnknownOption("--verbose".to_owned()))
+        );
+        assert_eq!(config, Config::default());
+    }
+}
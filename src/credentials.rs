@@ -0,0 +1,170 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use thiserror::Error;
+
+const API_KEY_ENV: &str = "KRAKEN_API_KEY";
+const API_SECRET_ENV: &str = "KRAKEN_API_SECRET";
+
+#[derive(Error, Debug)]
+pub enum CredentialsError {
+    #[error("{0}")]
+    Io(#[from] io::Error),
+    #[error("{0}")]
+    Json(#[from] serde_json::Error),
+    #[error("{} is accessible by other users (mode {mode:o}), run chmod 600 on it", path.display())]
+    InsecurePermissions { path: PathBuf, mode: u32 },
+    #[error("both {API_KEY_ENV} and {API_SECRET_ENV} have to be set")]
+    Incomplete,
+}
+
+// API key and secret used to sign the private requests, the secret is base64 encoded.
+#[derive(Clone, PartialEq, Deserialize)]
+pub struct Credentials {
+    pub key: String,
+    pub secret: String,
+}
+
+// never print the secret, not even in the debug messages
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Credentials")
+            .field("key", &self.key)
+            .field("secret", &"***")
+            .finish()
+    }
+}
+
+impl Credentials {
+    fn is_empty(&self) -> bool {
+        self.key.trim().is_empty() || self.secret.trim().is_empty()
+    }
+}
+
+// the environment variables take precedence over the credentials file, None means that
+// only the public API is available. The empty values are the same as missing ones.
+pub fn load_credentials<F>(
+    lookup: F,
+    path: Option<&Path>,
+) -> Result<Option<Credentials>, CredentialsError>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let lookup = |name: &str| lookup(name).filter(|value| !value.trim().is_empty());
+    match (lookup(API_KEY_ENV), lookup(API_SECRET_ENV)) {
+        (Some(key), Some(secret)) => return Ok(Some(Credentials { key, secret })),
+        (None, None) => {}
+        _ => return Err(CredentialsError::Incomplete),
+    }
+
+    match path {
+        Some(path) if path.exists() => {
+            let credentials = load_credentials_file(path)?;
+            Ok((!credentials.is_empty()).then_some(credentials))
+        }
+        _ => Ok(None),
+    }
+}
+
+// {"key": "...", "secret": "..."}, readable only by the owner
+fn load_credentials_file(path: &Path) -> Result<Credentials, CredentialsError> {
+    check_permissions(path)?;
+
+    let content = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+#[cfg(unix)]
+fn check_permissions(path: &Path) -> Result<(), CredentialsError> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = fs::metadata(path)?.permissions().mode() & 0o777;
+    if mode & 0o077 != 0 {
+        return Err(CredentialsError::InsecurePermissions {
+            path: path.to_path_buf(),
+            mode,
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn check_permissions(_path: &Path) -> Result<(), CredentialsError> {
+    Ok(())
+}
+
+// tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("kraken_tui_{name}_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_load_from_env() {
+        let lookup = |name: &str| match name {
+            API_KEY_ENV => Some("key".to_owned()),
+            API_SECRET_ENV => Some("secret".to_owned()),
+            _ => None,
+        };
+        let credentials = load_credentials(lookup, None).unwrap().unwrap();
+        assert_eq!(credentials.key, "key");
+        assert_eq!(
+            format!("{credentials:?}"),
+            r#"Credentials { key: "key", secret: "***" }"#
+        );
+
+        let lookup = |name: &str| (name == API_KEY_ENV).then(|| "key".to_owned());
+        assert!(matches!(
+            load_credentials(lookup, None),
+            Err(CredentialsError::Incomplete)
+        ));
+
+        assert_eq!(load_credentials(|_| None, None).unwrap(), None);
+
+        // set but empty
+        let lookup = |_: &str| Some(" ".to_owned());
+        assert_eq!(load_credentials(lookup, None).unwrap(), None);
+        let lookup = |name: &str| Some(if name == API_KEY_ENV { "key" } else { "" }.to_owned());
+        assert!(matches!(
+            load_credentials(lookup, None),
+            Err(CredentialsError::Incomplete)
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_load_from_file() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = temp_dir("credentials");
+        let path = dir.join("credentials.json");
+        assert_eq!(load_credentials(|_| None, Some(&path)).unwrap(), None);
+
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&path, r#"{"key": "file_key", "secret": "file_secret"}"#).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        assert!(matches!(
+            load_credentials(|_| None, Some(&path)),
+            Err(CredentialsError::InsecurePermissions { mode: 0o644, .. })
+        ));
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        let credentials = load_credentials(|_| None, Some(&path)).unwrap().unwrap();
+        assert_eq!(credentials.key, "file_key");
+        assert_eq!(credentials.secret, "file_secret");
+
+        fs::write(&path, r#"{"key": "", "secret": ""}"#).unwrap();
+        assert_eq!(load_credentials(|_| None, Some(&path)).unwrap(), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1 +1,8 @@
 pub(crate) mod client;
+pub(crate) mod error;
+pub(crate) mod messages;
+pub(crate) mod retry;
+pub(crate) mod worker;
+pub(crate) mod ws_client;
+pub(crate) mod ws_messages;
+pub(crate) mod ws_worker;
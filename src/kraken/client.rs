@@ -1,81 +1,438 @@
-use krakenrs::{
-    AssetPairsResponse, AssetsResponse, KrakenRestAPI, KrakenRestConfig, TickerResponse,
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use hmac::{Hmac, Mac};
+use krakenrs::AssetPairsResponse;
+use reqwest::blocking::Client;
+use reqwest::header::CONTENT_TYPE;
+use serde::{de::DeserializeOwned, Serialize};
+use sha2::{Digest, Sha256, Sha512};
+
+use crate::credentials::Credentials;
+use crate::kraken::error::ClientError;
+use crate::kraken::messages::{
+    AddOrderRequest, AddOrderResponse, AssetsResponse, BalanceResponse, CancelOrderRequest,
+    CancelOrderResponse, ClosedOrdersResponse, DepthRequest, Empty, HistoryRequest, KrakenResult,
+    OhlcRequest, OhlcResponse, OpenOrdersResponse, OrderBookResponse, PairRequest, SinceRequest,
+    SpreadsResponse, TickerResponse, TradesHistoryResponse, TradesResponse,
 };
 
 #[cfg(test)]
 use mockall::{automock, predicate::*};
+
+// http status returned by kraken when the requests are throttled
+const TOO_MANY_REQUESTS: u16 = 429;
+const API_VERSION: u32 = 0;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
 #[cfg_attr(test, automock)]
 pub trait RestAPI {
-    fn connect(&mut self) -> Result<(), krakenrs::Error>;
-    fn disconnect(&self) -> Result<(), krakenrs::Error>;
-    fn list_assets(&self) -> Option<AssetsResponse>;
-    fn list_asset_pairs(&self) -> Option<AssetPairsResponse>;
-    fn ticker(&self, asset_pair: &str) -> Option<TickerResponse>;
+    fn connect(&mut self) -> Result<(), ClientError>;
+    fn disconnect(&self) -> Result<(), ClientError>;
+    fn list_assets(&self) -> Result<AssetsResponse, ClientError>;
+    fn list_asset_pairs(&self) -> Result<AssetPairsResponse, ClientError>;
+    fn ticker(&self, asset_pair: &str) -> Result<TickerResponse, ClientError>;
+    fn order_book(&self, asset_pair: &str, depth: u32) -> Result<OrderBookResponse, ClientError>;
+    fn recent_trades(
+        &self,
+        asset_pair: &str,
+        since: Option<String>,
+    ) -> Result<TradesResponse, ClientError>;
+    fn ohlc(&self, asset_pair: &str, interval: u32) -> Result<OhlcResponse, ClientError>;
+    fn spreads(&self, asset_pair: &str, since: Option<u64>)
+        -> Result<SpreadsResponse, ClientError>;
+    fn balances(&self) -> Result<BalanceResponse, ClientError>;
+    fn open_orders(&self) -> Result<OpenOrdersResponse, ClientError>;
+    fn cancel_order(&self, txid: &str) -> Result<CancelOrderResponse, ClientError>;
+    fn closed_orders(
+        &self,
+        offset: usize,
+        start: Option<u64>,
+        end: Option<u64>,
+    ) -> Result<ClosedOrdersResponse, ClientError>;
+    fn trades_history(
+        &self,
+        offset: usize,
+        start: Option<u64>,
+        end: Option<u64>,
+    ) -> Result<TradesHistoryResponse, ClientError>;
+    fn add_order(&self, order: &AddOrderRequest) -> Result<AddOrderResponse, ClientError>;
 }
 
-#[allow(unused)]
+// The requests are sent to the configured base url, e.g. https://api.kraken.com or a
+// local mock server.
 pub struct RestAPIImpl {
     url: String,
-    api: Option<KrakenRestAPI>,
+    credentials: Option<Credentials>,
+    client: Option<Client>,
 }
 
 impl RestAPIImpl {
-    pub fn new(url: String) -> RestAPIImpl {
-        RestAPIImpl { url, api: None }
+    pub fn new(url: String, credentials: Option<Credentials>) -> RestAPIImpl {
+        RestAPIImpl {
+            url,
+            credentials,
+            client: None,
+        }
+    }
+
+    fn query_public<D: Serialize, R: DeserializeOwned>(
+        &self,
+        method: &str,
+        data: D,
+    ) -> Result<R, ClientError> {
+        let path = format!("/{API_VERSION}/public/{method}");
+        self.post(&path, serde_qs::to_string(&data)?, &[])
+    }
+
+    // the credentials are required, the request is signed adding the nonce
+    fn query_private<D: Serialize, R: DeserializeOwned>(
+        &self,
+        method: &str,
+        data: D,
+    ) -> Result<R, ClientError> {
+        let credentials = self
+            .credentials
+            .as_ref()
+            .ok_or_else(|| ClientError::Auth("missing credentials".to_owned()))?;
+
+        let path = format!("/{API_VERSION}/private/{method}");
+        let nonce = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| ClientError::Auth(e.to_string()))?
+            .as_millis();
+        let query = serde_qs::to_string(&data)?;
+        let post_data = if query.is_empty() {
+            format!("nonce={nonce}")
+        } else {
+            format!("nonce={nonce}&{query}")
+        };
+        let signature = sign(&credentials.secret, &path, nonce, &post_data)?;
+
+        self.post(
+            &path,
+            post_data,
+            &[
+                ("API-Key", credentials.key.as_str()),
+                ("API-Sign", &signature),
+            ],
+        )
+    }
+
+    fn post<R: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: String,
+        headers: &[(&str, &str)],
+    ) -> Result<R, ClientError> {
+        let client = self.client.as_ref().ok_or(ClientError::NotConnected)?;
+
+        let mut request = client
+            .post(format!("{}{path}", self.url.trim_end_matches('/')))
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(body);
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+
+        let response = request.send()?;
+        let status = response.status();
+        if status.as_u16() == TOO_MANY_REQUESTS {
+            return Err(ClientError::RateLimit);
+        }
+        if !status.is_success() {
+            return Err(ClientError::Network(format!("bad status {status}")));
+        }
+
+        let text = response.text()?;
+        let result: KrakenResult<R> =
+            serde_json::from_str(&text).map_err(|e| ClientError::Parse(e.to_string()))?;
+        result.into_result()
     }
 }
 
-#[allow(unused)]
+// API-Sign is the HMAC-SHA512 of the path and of the SHA256 of nonce and post data, keyed
+// with the base64 decoded secret
+fn sign(secret: &str, path: &str, nonce: u128, post_data: &str) -> Result<String, ClientError> {
+    let key = BASE64
+        .decode(secret)
+        .map_err(|e| ClientError::Auth(format!("invalid secret {e}")))?;
+
+    let mut sha256 = Sha256::new();
+    sha256.update(nonce.to_string());
+    sha256.update(post_data);
+
+    let mut mac = Hmac::<Sha512>::new_from_slice(&key)
+        .map_err(|e| ClientError::Auth(format!("invalid secret {e}")))?;
+    mac.update(path.as_bytes());
+    mac.update(&sha256.finalize());
+
+    Ok(BASE64.encode(mac.finalize().into_bytes()))
+}
+
 impl RestAPI for RestAPIImpl {
-    fn connect(&mut self) -> Result<(), krakenrs::Error> {
-        let kc_config = KrakenRestConfig::default();
-        let api = KrakenRestAPI::try_from(kc_config);
-        match api {
-            Ok(api) => {
-                self.api = Some(api);
-                Ok(())
-            }
-            Err(e) => Err(e),
-        }
+    fn connect(&mut self) -> Result<(), ClientError> {
+        let client = Client::builder()
+            .user_agent(concat!("kraken_tui/", env!("CARGO_PKG_VERSION")))
+            .timeout(REQUEST_TIMEOUT)
+            .build()?;
+        self.client = Some(client);
+        Ok(())
     }
 
-    fn list_assets(&self) -> Option<AssetsResponse> {
-        if let Some(api) = &self.api {
-            let result = api.assets();
-            return match result {
-                Ok(assets) => return Some(assets),
-                Err(_) => return None,
-            };
-        }
+    fn list_assets(&self) -> Result<AssetsResponse, ClientError> {
+        self.query_public("Assets", Empty {})
+    }
 
-        None
+    fn list_asset_pairs(&self) -> Result<AssetPairsResponse, ClientError> {
+        self.query_public("AssetPairs", Empty {})
     }
 
-    fn list_asset_pairs(&self) -> Option<AssetPairsResponse> {
-        if let Some(api) = &self.api {
-            let result = api.asset_pairs(vec![]);
-            return match result {
-                Ok(asset_pairs) => Some(asset_pairs),
-                Err(e) => None,
-            };
-        }
+    fn ticker(&self, asset_pair: &str) -> Result<TickerResponse, ClientError> {
+        self.query_public(
+            "Ticker",
+            PairRequest {
+                pair: asset_pair.to_owned(),
+            },
+        )
+    }
 
-        None
+    fn order_book(&self, asset_pair: &str, depth: u32) -> Result<OrderBookResponse, ClientError> {
+        self.query_public(
+            "Depth",
+            DepthRequest {
+                pair: asset_pair.to_owned(),
+                count: depth,
+            },
+        )
     }
 
-    fn ticker(&self, asset_pair: &str) -> Option<TickerResponse> {
-        if let Some(api) = &self.api {
-            let result = api.ticker(vec![asset_pair.to_owned()]);
-            return match result {
-                Ok(asset_pairs) => Some(asset_pairs),
-                Err(_) => None,
-            };
-        }
+    fn recent_trades(
+        &self,
+        asset_pair: &str,
+        since: Option<String>,
+    ) -> Result<TradesResponse, ClientError> {
+        self.query_public(
+            "Trades",
+            SinceRequest {
+                pair: asset_pair.to_owned(),
+                since,
+            },
+        )
+    }
+
+    fn ohlc(&self, asset_pair: &str, interval: u32) -> Result<OhlcResponse, ClientError> {
+        self.query_public(
+            "OHLC",
+            OhlcRequest {
+                pair: asset_pair.to_owned(),
+                interval,
+            },
+        )
+    }
 
-        None
+    fn spreads(
+        &self,
+        asset_pair: &str,
+        since: Option<u64>,
+    ) -> Result<SpreadsResponse, ClientError> {
+        self.query_public(
+            "Spread",
+            SinceRequest {
+                pair: asset_pair.to_owned(),
+                since: since.map(|since| since.to_string()),
+            },
+        )
     }
 
-    fn disconnect(&self) -> Result<(), krakenrs::Error> {
+    fn balances(&self) -> Result<BalanceResponse, ClientError> {
+        self.query_private("Balance", Empty {})
+    }
+
+    fn open_orders(&self) -> Result<OpenOrdersResponse, ClientError> {
+        self.query_private("OpenOrders", Empty {})
+    }
+
+    fn cancel_order(&self, txid: &str) -> Result<CancelOrderResponse, ClientError> {
+        self.query_private(
+            "CancelOrder",
+            CancelOrderRequest {
+                txid: txid.to_owned(),
+            },
+        )
+    }
+
+    fn closed_orders(
+        &self,
+        offset: usize,
+        start: Option<u64>,
+        end: Option<u64>,
+    ) -> Result<ClosedOrdersResponse, ClientError> {
+        self.query_private(
+            "ClosedOrders",
+            HistoryRequest {
+                start,
+                end,
+                ofs: offset,
+            },
+        )
+    }
+
+    fn trades_history(
+        &self,
+        offset: usize,
+        start: Option<u64>,
+        end: Option<u64>,
+    ) -> Result<TradesHistoryResponse, ClientError> {
+        self.query_private(
+            "TradesHistory",
+            HistoryRequest {
+                start,
+                end,
+                ofs: offset,
+            },
+        )
+    }
+
+    fn add_order(&self, order: &AddOrderRequest) -> Result<AddOrderResponse, ClientError> {
+        self.query_private("AddOrder", order)
+    }
+
+    fn disconnect(&self) -> Result<(), ClientError> {
         Ok(())
     }
 }
+
+// tests
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    use super::*;
+    use crate::mock_server::{default_fixtures_dir, MockServer};
+
+    fn request_complete(request: &str) -> bool {
+        let Some((headers, body)) = request.split_once("\r\n\r\n") else {
+            return false;
+        };
+        let length = headers
+            .lines()
+            .find_map(|line| {
+                line.to_lowercase()
+                    .strip_prefix("content-length: ")?
+                    .parse()
+                    .ok()
+            })
+            .unwrap_or(0);
+        body.len() >= length
+    }
+
+    // accepts a single http request, answers it with the json body and returns it
+    fn serve(body: &'static str) -> (String, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = String::new();
+            let mut buffer = [0; 4096];
+            // the headers and then the body of the announced length
+            while !request_complete(&request) {
+                let read = stream.read(&mut buffer).unwrap();
+                request.push_str(std::str::from_utf8(&buffer[..read]).unwrap());
+            }
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+                body.len()
+            )
+            .unwrap();
+            request
+        });
+
+        (url, server)
+    }
+
+    #[test]
+    fn test_query_base_url() {
+        let (url, server) = serve(
+            r#"{ "error": [], "result": { "XXBTZUSD": {
+              "asks": [["30010.0", "1.500", 1688671834]], "bids": []
+            } } }"#,
+        );
+
+        let mut api = RestAPIImpl::new(format!("{url}/"), None);
+        assert_eq!(
+            api.order_book("XXBTZUSD", 10),
+            Err(ClientError::NotConnected)
+        );
+        api.connect().unwrap();
+
+        let order_books = api.order_book("XXBTZUSD", 10).unwrap();
+        assert_eq!(order_books["XXBTZUSD"].asks.len(), 1);
+
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST /0/public/Depth HTTP/1.1"));
+        assert!(request.ends_with("\r\n\r\npair=XXBTZUSD&count=10"));
+
+        assert!(matches!(api.balances(), Err(ClientError::Auth(_))));
+    }
+
+    #[test]
+    fn test_mock_server() {
+        let server = MockServer::bind(0, &default_fixtures_dir()).unwrap();
+        let mut api = RestAPIImpl::new(server.url().unwrap(), None);
+        server.spawn();
+        api.connect().unwrap();
+
+        assert_eq!(api.list_assets().unwrap().len(), 4);
+        assert_eq!(api.list_asset_pairs().unwrap().len(), 3);
+        assert_eq!(
+            api.ticker("XXBTZUSD").unwrap()["XXBTZUSD"].last(),
+            Some(30005.0)
+        );
+        assert_eq!(
+            api.order_book("XXBTZUSD", 10).unwrap()["XXBTZUSD"]
+                .bids
+                .len(),
+            4
+        );
+        assert_eq!(
+            api.recent_trades("XXBTZUSD", None).unwrap().trades["XXBTZUSD"].len(),
+            3
+        );
+        assert_eq!(
+            api.ohlc("XXBTZUSD", 1).unwrap().candles["XXBTZUSD"].len(),
+            3
+        );
+        assert_eq!(
+            api.ticker("XETHZEUR"),
+            Err(ClientError::Api(vec![
+                "EQuery:Unknown asset pair".to_owned()
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_sign() {
+        // example of the kraken documentation
+        let signature = sign(
+            "kQH5HW/8p1uGOVjbgWA7FunAmGO8lsSUXNsu3eow76sz84Q18fWxnyRzBHCd3pd5nE9qa99HAZtuZuj6F1huXg==",
+            "/0/private/AddOrder",
+            1_616_492_376_594,
+            "nonce=1616492376594&ordertype=limit&pair=XBTUSD&price=37500&type=buy&volume=1.25",
+        )
+        .unwrap();
+
+        assert_eq!(
+            signature,
+            "4/dpxb3iT4tp/ZCVEwSnEsLxx0bqyhLpdfOpc6fn7OR8+UClSV5n9E6aSS8MPtnRfp32bAb0nmbRn6H8ndwLUQ=="
+        );
+        assert!(matches!(
+            sign("not base64!", "/", 0, ""),
+            Err(ClientError::Auth(_))
+        ));
+    }
+}
@@ -0,0 +1,91 @@
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ClientError {
+    #[error("not connected")]
+    NotConnected,
+    #[error("network error {0}")]
+    Network(String),
+    #[error("rate limit exceeded")]
+    RateLimit,
+    #[error("authentication failed {0}")]
+    Auth(String),
+    #[error("kraken error {}", .0.join(", "))]
+    Api(Vec<String>),
+    #[error("invalid response {0}")]
+    Parse(String),
+}
+
+impl ClientError {
+    // the kraken errors are strings like "EAPI:Invalid key", see
+    // https://docs.kraken.com/rest/#section/General-Usage/Requests-Responses-and-Errors
+    pub fn from_kraken_errors(errors: Vec<String>) -> ClientError {
+        let any = |patterns: &[&str]| {
+            errors
+                .iter()
+                .any(|error| patterns.iter().any(|pattern| error.contains(pattern)))
+        };
+
+        if any(&["Rate limit exceeded", "Too many requests", "Throttled"]) {
+            ClientError::RateLimit
+        } else if any(&[
+            "Invalid key",
+            "Invalid signature",
+            "Invalid nonce",
+            "Permission denied",
+        ]) {
+            ClientError::Auth(errors.join(", "))
+        } else {
+            ClientError::Api(errors)
+        }
+    }
+
+    // the request may succeed if sent again later
+    pub fn is_transient(&self) -> bool {
+        match self {
+            ClientError::Network(_) | ClientError::RateLimit => true,
+            ClientError::Api(errors) => errors.iter().any(|error| error.starts_with("EService:")),
+            _ => false,
+        }
+    }
+}
+
+impl From<reqwest::Error> for ClientError {
+    fn from(e: reqwest::Error) -> Self {
+        ClientError::Network(e.to_string())
+    }
+}
+
+impl From<serde_qs::Error> for ClientError {
+    fn from(e: serde_qs::Error) -> Self {
+        ClientError::Parse(e.to_string())
+    }
+}
+
+// tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_error_from_kraken_errors() {
+        let errors = |errors: &[&str]| errors.iter().map(|e| (*e).to_owned()).collect();
+
+        assert_eq!(
+            ClientError::from_kraken_errors(errors(&["EAPI:Rate limit exceeded"])),
+            ClientError::RateLimit
+        );
+        assert_eq!(
+            ClientError::from_kraken_errors(errors(&["EAPI:Invalid key"])),
+            ClientError::Auth("EAPI:Invalid key".to_owned())
+        );
+        let error = ClientError::from_kraken_errors(errors(&[
+            "EOrder:Insufficient funds",
+            "EGeneral:Invalid arguments",
+        ]));
+        assert_eq!(
+            error.to_string(),
+            "kraken error EOrder:Insufficient funds, EGeneral:Invalid arguments"
+        );
+    }
+}
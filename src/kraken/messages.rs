@@ -0,0 +1,1054 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::kraken::error::ClientError;
+use crate::kraken::ws_messages::BookUpdate;
+
+// Kraken responds to APIs with a json body consisting of "error:" and "result:" fields,
+// the response of every REST call is decoded through this envelope.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct KrakenResult<T> {
+    pub error: Vec<String>,
+    pub result: Option<T>,
+}
+
+impl<T> KrakenResult<T> {
+    pub fn into_result(self) -> Result<T, ClientError> {
+        if !self.error.is_empty() {
+            return Err(ClientError::from_kraken_errors(self.error));
+        }
+        self.result
+            .ok_or_else(|| ClientError::Parse("missing result".to_owned()))
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Empty {}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PairRequest {
+    pub pair: String,
+}
+
+// (Substructure within) Result of kraken public "Assets" API call.
+// The krakenrs AssetInfo misses the collateral value and the status.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AssetInfo {
+    pub altname: String,
+    pub aclass: String,
+    // scaling decimal places for record keeping
+    pub decimals: u32,
+    // scaling decimal places for output display
+    pub display_decimals: u32,
+    // valuation as margin collateral, if applicable
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collateral_value: Option<f64>,
+    // e.g. enabled, deposit_only, withdrawal_only, funding_temporarily_disabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+}
+
+pub type AssetsResponse = HashMap<String, AssetInfo>;
+
+// (Substructure within) Result of kraken public "Ticker" API call.
+// The krakenrs AssetTickerInfo only carries ask, bid and last trade.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TickerInfo {
+    // ask [price, whole lot volume, lot volume]
+    pub a: Vec<String>,
+    // bid [price, whole lot volume, lot volume]
+    pub b: Vec<String>,
+    // last trade closed [price, lot volume]
+    pub c: Vec<String>,
+    // volume [today, last 24 hours]
+    pub v: Vec<String>,
+    // volume weighted average price [today, last 24 hours]
+    pub p: Vec<String>,
+    // number of trades [today, last 24 hours]
+    pub t: Vec<u64>,
+    // low [today, last 24 hours]
+    pub l: Vec<String>,
+    // high [today, last 24 hours]
+    pub h: Vec<String>,
+    // today's opening price
+    pub o: String,
+}
+
+pub type TickerResponse = HashMap<String, TickerInfo>;
+
+impl TickerInfo {
+    pub fn last(&self) -> Option<f64> {
+        parse_at(&self.c, 0)
+    }
+
+    pub fn ask(&self) -> Option<f64> {
+        parse_at(&self.a, 0)
+    }
+
+    pub fn bid(&self) -> Option<f64> {
+        parse_at(&self.b, 0)
+    }
+
+    pub fn volume_24h(&self) -> Option<f64> {
+        parse_at(&self.v, 1)
+    }
+
+    pub fn vwap_24h(&self) -> Option<f64> {
+        parse_at(&self.p, 1)
+    }
+
+    pub fn low_24h(&self) -> Option<f64> {
+        parse_at(&self.l, 1)
+    }
+
+    pub fn high_24h(&self) -> Option<f64> {
+        parse_at(&self.h, 1)
+    }
+
+    pub fn open(&self) -> Option<f64> {
+        self.o.parse().ok()
+    }
+
+    // percentage change of the last price against the opening price
+    pub fn change_pct(&self) -> Option<f64> {
+        match (self.last(), self.open()) {
+            (Some(last), Some(open)) if open != 0.0 => Some((last - open) / open * 100.0),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DepthRequest {
+    pub pair: String,
+    pub count: u32,
+}
+
+// (Substructure within) Result of kraken public "Depth" API call: [price, volume, timestamp]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OrderBookEntry(pub String, pub String, pub u64);
+
+impl OrderBookEntry {
+    pub fn price(&self) -> Option<f64> {
+        self.0.parse().ok()
+    }
+
+    pub fn volume(&self) -> Option<f64> {
+        self.1.parse().ok()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OrderBook {
+    // sorted by ascending price
+    pub asks: Vec<OrderBookEntry>,
+    // sorted by descending price
+    pub bids: Vec<OrderBookEntry>,
+}
+
+pub type OrderBookResponse = HashMap<String, OrderBook>;
+
+impl OrderBook {
+    // difference between the best ask and the best bid
+    pub fn spread(&self) -> Option<f64> {
+        let ask = self.asks.first()?.price()?;
+        let bid = self.bids.first()?.price()?;
+        Some(ask - bid)
+    }
+
+    // spread as a percentage of the best ask
+    pub fn spread_pct(&self) -> Option<f64> {
+        let ask = self.asks.first()?.price()?;
+        let spread = self.spread()?;
+        (ask != 0.0).then(|| spread / ask * 100.0)
+    }
+
+    // applies a websocket snapshot or update keeping at most depth levels for each side
+    pub fn apply(&mut self, update: BookUpdate, depth: usize) {
+        if update.snapshot {
+            self.asks.clear();
+            self.bids.clear();
+        }
+        apply_levels(&mut self.asks, update.asks, depth, false);
+        apply_levels(&mut self.bids, update.bids, depth, true);
+    }
+
+    // CRC32 of the top 10 asks followed by the top 10 bids, each level as its price and
+    // volume without the decimal point and the leading zeros.
+    pub fn checksum(&self) -> u32 {
+        let digits = |value: &str| value.replace('.', "").trim_start_matches('0').to_owned();
+
+        let mut hasher = crc32fast::Hasher::new();
+        for level in self.asks.iter().take(10).chain(self.bids.iter().take(10)) {
+            hasher.update(digits(&level.0).as_bytes());
+            hasher.update(digits(&level.1).as_bytes());
+        }
+        hasher.finalize()
+    }
+}
+
+// a level with a zero volume removes the price from the book
+fn apply_levels(
+    levels: &mut Vec<OrderBookEntry>,
+    updates: Vec<OrderBookEntry>,
+    depth: usize,
+    descending: bool,
+) {
+    for update in updates {
+        let price = update.price();
+        levels.retain(|level| level.price() != price);
+        if matches!(update.volume(), Some(volume) if volume > 0.0) {
+            levels.push(update);
+        }
+    }
+
+    levels.sort_by(|a, b| {
+        let (a, b) = (a.price().unwrap_or(0.0), b.price().unwrap_or(0.0));
+        if descending {
+            b.total_cmp(&a)
+        } else {
+            a.total_cmp(&b)
+        }
+    });
+    levels.truncate(depth);
+}
+
+// running sum of the volumes, starting from the best price
+pub fn cumulative_volumes(entries: &[OrderBookEntry]) -> Vec<f64> {
+    entries
+        .iter()
+        .scan(0.0, |total, entry| {
+            *total += entry.volume().unwrap_or(0.0);
+            Some(*total)
+        })
+        .collect()
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SinceRequest {
+    pub pair: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub since: Option<String>,
+}
+
+// (Substructure within) Result of kraken public "Trades" API call:
+// [price, volume, time, buy/sell, market/limit, miscellaneous, trade_id]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "Vec<serde_json::Value>")]
+pub struct Trade {
+    pub price: String,
+    pub volume: String,
+    pub time: f64,
+    // "b" buy, "s" sell
+    pub side: String,
+    // "m" market, "l" limit
+    pub order_type: String,
+    pub misc: String,
+    // missing in the older responses
+    pub id: Option<u64>,
+}
+
+impl TryFrom<Vec<serde_json::Value>> for Trade {
+    type Error = String;
+
+    fn try_from(values: Vec<serde_json::Value>) -> Result<Self, Self::Error> {
+        let text = |index: usize| {
+            values
+                .get(index)
+                .and_then(serde_json::Value::as_str)
+                .map(ToOwned::to_owned)
+                .ok_or_else(|| format!("invalid trade field {index}"))
+        };
+
+        Ok(Trade {
+            price: text(0)?,
+            volume: text(1)?,
+            time: values
+                .get(2)
+                .and_then(serde_json::Value::as_f64)
+                .ok_or_else(|| "invalid trade time".to_owned())?,
+            side: text(3)?,
+            order_type: text(4)?,
+            misc: text(5)?,
+            id: values.get(6).and_then(serde_json::Value::as_u64),
+        })
+    }
+}
+
+impl Trade {
+    pub fn is_buy(&self) -> bool {
+        self.side == "b"
+    }
+
+    pub fn is_market(&self) -> bool {
+        self.order_type == "m"
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct TradesResponse {
+    // id to be used as since when polling for new trades
+    pub last: String,
+    #[serde(flatten)]
+    pub trades: HashMap<String, Vec<Trade>>,
+}
+
+const MAX_RECENT_TRADES: usize = 1000;
+
+// The trades loaded so far for an asset pair, sorted by ascending time, and the cursor
+// used to fetch only the newer ones.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RecentTrades {
+    pub trades: Vec<Trade>,
+    pub last: Option<String>,
+}
+
+impl RecentTrades {
+    pub fn append(&mut self, response: TradesResponse) {
+        for trades in response.trades.into_values() {
+            self.extend(trades);
+        }
+        self.last = Some(response.last);
+    }
+
+    // the streamed trades do not move the polling cursor
+    pub fn extend(&mut self, trades: Vec<Trade>) {
+        self.trades.extend(trades);
+        if self.trades.len() > MAX_RECENT_TRADES {
+            self.trades.drain(..self.trades.len() - MAX_RECENT_TRADES);
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct OhlcRequest {
+    pub pair: String,
+    // minutes: 1, 5, 15, 30, 60, 240, 1440, 10080, 21600
+    pub interval: u32,
+}
+
+// (Substructure within) Result of kraken public "OHLC" API call:
+// [time, open, high, low, close, vwap, volume, count]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(try_from = "Vec<serde_json::Value>")]
+pub struct Candle {
+    pub time: u64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub vwap: f64,
+    pub volume: f64,
+    pub count: u64,
+}
+
+impl TryFrom<Vec<serde_json::Value>> for Candle {
+    type Error = String;
+
+    fn try_from(values: Vec<serde_json::Value>) -> Result<Self, Self::Error> {
+        let number = |index: usize| {
+            values
+                .get(index)
+                .and_then(serde_json::Value::as_str)
+                .and_then(|value| value.parse::<f64>().ok())
+                .ok_or_else(|| format!("invalid candle field {index}"))
+        };
+        let integer = |index: usize| {
+            values
+                .get(index)
+                .and_then(serde_json::Value::as_u64)
+                .ok_or_else(|| format!("invalid candle field {index}"))
+        };
+
+        Ok(Candle {
+            time: integer(0)?,
+            open: number(1)?,
+            high: number(2)?,
+            low: number(3)?,
+            close: number(4)?,
+            vwap: number(5)?,
+            volume: number(6)?,
+            count: integer(7)?,
+        })
+    }
+}
+
+impl Candle {
+    pub fn is_bullish(&self) -> bool {
+        self.close >= self.open
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct OhlcResponse {
+    // id to be used as since when polling for new, committed, candles
+    pub last: u64,
+    #[serde(flatten)]
+    pub candles: HashMap<String, Vec<Candle>>,
+}
+
+// (Substructure within) Result of kraken public "Spread" API call: [time, bid, ask]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "Vec<serde_json::Value>")]
+pub struct Spread {
+    pub time: u64,
+    pub bid: String,
+    pub ask: String,
+}
+
+impl TryFrom<Vec<serde_json::Value>> for Spread {
+    type Error = String;
+
+    fn try_from(values: Vec<serde_json::Value>) -> Result<Self, Self::Error> {
+        let text = |index: usize| {
+            values
+                .get(index)
+                .and_then(serde_json::Value::as_str)
+                .map(ToOwned::to_owned)
+                .ok_or_else(|| format!("invalid spread field {index}"))
+        };
+
+        Ok(Spread {
+            time: values
+                .first()
+                .and_then(serde_json::Value::as_u64)
+                .ok_or_else(|| "invalid spread time".to_owned())?,
+            bid: text(1)?,
+            ask: text(2)?,
+        })
+    }
+}
+
+impl Spread {
+    pub fn spread(&self) -> Option<f64> {
+        let bid = self.bid.parse::<f64>().ok()?;
+        let ask = self.ask.parse::<f64>().ok()?;
+        Some(ask - bid)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct SpreadsResponse {
+    // id to be used as since when polling for new spreads
+    pub last: u64,
+    #[serde(flatten)]
+    pub spreads: HashMap<String, Vec<Spread>>,
+}
+
+const MAX_RECENT_SPREADS: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpreadStats {
+    pub min: f64,
+    pub avg: f64,
+    pub max: f64,
+}
+
+// The spreads loaded so far for an asset pair, sorted by ascending time, and the cursor
+// used to fetch only the newer ones.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RecentSpreads {
+    pub spreads: Vec<Spread>,
+    pub last: Option<u64>,
+}
+
+impl RecentSpreads {
+    pub fn append(&mut self, response: SpreadsResponse) {
+        for spreads in response.spreads.into_values() {
+            self.extend(spreads);
+        }
+        self.last = Some(response.last);
+    }
+
+    // the streamed spreads do not move the polling cursor
+    pub fn extend(&mut self, spreads: Vec<Spread>) {
+        self.spreads.extend(spreads);
+        if self.spreads.len() > MAX_RECENT_SPREADS {
+            self.spreads
+                .drain(..self.spreads.len() - MAX_RECENT_SPREADS);
+        }
+    }
+
+    // min, average and max spread over the loaded window
+    #[allow(clippy::cast_precision_loss)]
+    pub fn stats(&self) -> Option<SpreadStats> {
+        let spreads = self
+            .spreads
+            .iter()
+            .filter_map(Spread::spread)
+            .collect::<Vec<_>>();
+        if spreads.is_empty() {
+            return None;
+        }
+
+        Some(SpreadStats {
+            min: spreads.iter().copied().fold(f64::INFINITY, f64::min),
+            avg: spreads.iter().sum::<f64>() / spreads.len() as f64,
+            max: spreads.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        })
+    }
+}
+
+// Result of kraken private "Balance" API call: amount held for each asset
+pub type BalanceResponse = HashMap<String, String>;
+
+// (Substructure within) Result of kraken private "OpenOrders" API call
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct OrderDescription {
+    pub pair: String,
+    // "buy" or "sell"
+    #[serde(rename = "type")]
+    pub side: String,
+    // "market", "limit", "stop-loss", ...
+    pub ordertype: String,
+    pub price: String,
+    #[serde(default)]
+    pub price2: String,
+    // human readable description, e.g. "buy 1.25 XBTUSD @ limit 30000.0"
+    #[serde(default)]
+    pub order: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct OrderInfo {
+    // "pending", "open", "closed", "canceled" or "expired"
+    pub status: String,
+    pub opentm: f64,
+    // set only for the closed orders
+    #[serde(default)]
+    pub closetm: f64,
+    pub descr: OrderDescription,
+    pub vol: String,
+    pub vol_exec: String,
+    // average price, set once the order is (partially) filled
+    #[serde(default)]
+    pub price: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct OpenOrdersResponse {
+    // keyed by transaction id
+    pub open: HashMap<String, OrderInfo>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CancelOrderRequest {
+    pub txid: String,
+}
+
+// Result of kraken private "CancelOrder" API call
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct CancelOrderResponse {
+    pub count: u32,
+    #[serde(default)]
+    pub pending: bool,
+}
+
+// Parameters of kraken private "AddOrder" API call, with validate the order is only
+// checked by the exchange and never submitted.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AddOrderRequest {
+    pub pair: String,
+    #[serde(rename = "type")]
+    pub side: String,
+    pub ordertype: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub price: String,
+    pub volume: String,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub validate: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct AddOrderDescription {
+    #[serde(default)]
+    pub order: String,
+}
+
+// Result of kraken private "AddOrder" API call, no txid when only validated
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct AddOrderResponse {
+    pub descr: AddOrderDescription,
+    #[serde(default)]
+    pub txid: Vec<String>,
+}
+
+// page of the private "ClosedOrders" and "TradesHistory" API calls, 50 results each
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct HistoryRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end: Option<u64>,
+    pub ofs: usize,
+}
+
+// Result of kraken private "ClosedOrders" API call
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct ClosedOrdersResponse {
+    // keyed by transaction id
+    pub closed: HashMap<String, OrderInfo>,
+    // number of orders matching the criteria, all pages included
+    pub count: usize,
+}
+
+// (Substructure within) Result of kraken private "TradesHistory" API call
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct TradeInfo {
+    pub ordertxid: String,
+    pub pair: String,
+    pub time: f64,
+    // "buy" or "sell"
+    #[serde(rename = "type")]
+    pub side: String,
+    pub ordertype: String,
+    pub price: String,
+    pub cost: String,
+    pub fee: String,
+    pub vol: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct TradesHistoryResponse {
+    // keyed by trade id
+    pub trades: HashMap<String, TradeInfo>,
+    pub count: usize,
+}
+
+// The pages loaded so far of a paginated history, newest first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct History<T> {
+    pub items: Vec<(String, T)>,
+    pub count: usize,
+    // date range the pages are requested with, the pages of a previous range are dropped
+    pub generation: u64,
+}
+
+impl<T> Default for History<T> {
+    fn default() -> Self {
+        Self {
+            items: Vec::new(),
+            count: 0,
+            generation: 0,
+        }
+    }
+}
+
+impl<T> History<T> {
+    // the page at offset 0 starts over, a page not following the loaded ones is dropped
+    pub fn append(&mut self, generation: u64, offset: usize, count: usize, page: Vec<(String, T)>) {
+        if generation != self.generation {
+            return;
+        }
+        if offset == 0 {
+            self.items.clear();
+        }
+        if offset != self.items.len() {
+            return;
+        }
+
+        // an empty page ends the history whatever the count says
+        self.count = if page.is_empty() {
+            self.items.len()
+        } else {
+            count
+        };
+        self.items.extend(page);
+    }
+
+    pub fn has_more(&self) -> bool {
+        self.items.len() < self.count
+    }
+}
+
+fn parse_at(values: &[String], index: usize) -> Option<f64> {
+    values.get(index).and_then(|value| value.parse().ok())
+}
+
+// tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TICKER_JSON: &str = r#"{
+      "error": [],
+      "result": {
+        "XETHZUSD": {
+          "a": ["1200.50000", "1", "1.000"],
+          "b": ["1200.00000", "3", "3.000"],
+          "c": ["1210.00000", "0.01000000"],
+          "v": ["1000.00000000", "2500.00000000"],
+          "p": ["1190.00000", "1180.00000"],
+          "t": [1500, 4200],
+          "l": ["1150.00000", "1100.00000"],
+          "h": ["1220.00000", "1250.00000"],
+          "o": "1100.00000"
+        }
+      }
+    }"#;
+
+    #[test]
+    fn test_asset_info() {
+        let json = r#"{
+          "error": [],
+          "result": {
+            "XXBT": {"aclass": "currency", "altname": "XBT", "decimals": 10,
+              "display_decimals": 5, "collateral_value": 1.0, "status": "enabled"},
+            "ZUSD": {"aclass": "currency", "altname": "USD", "decimals": 4, "display_decimals": 2}
+          }
+        }"#;
+        let result: KrakenResult<AssetsResponse> = serde_json::from_str(json).unwrap();
+        let assets = result.into_result().unwrap();
+
+        let xbt = assets.get("XXBT").unwrap();
+        assert_eq!(xbt.collateral_value, Some(1.0));
+        assert_eq!(xbt.status.as_deref(), Some("enabled"));
+        // optional, also in the assets cached before they were added
+        assert_eq!(assets.get("ZUSD").unwrap().status, None);
+    }
+
+    #[test]
+    fn test_ticker_info() {
+        let result: KrakenResult<TickerResponse> = serde_json::from_str(TICKER_JSON).unwrap();
+        let ticker = result.into_result().unwrap();
+        let info = ticker.get("XETHZUSD").unwrap();
+
+        assert_eq!(info.last(), Some(1210.0));
+        assert_eq!(info.ask(), Some(1200.5));
+        assert_eq!(info.bid(), Some(1200.0));
+        assert_eq!(info.volume_24h(), Some(2500.0));
+        assert_eq!(info.vwap_24h(), Some(1180.0));
+        assert_eq!(info.low_24h(), Some(1100.0));
+        assert_eq!(info.high_24h(), Some(1250.0));
+        assert_eq!(info.open(), Some(1100.0));
+        assert_eq!(info.change_pct(), Some(10.0));
+    }
+
+    #[test]
+    fn test_order_book() {
+        let json = r#"{
+          "error": [],
+          "result": {
+            "XXBTZUSD": {
+              "asks": [["30010.0", "1.500", 1688671834], ["30020.0", "2.000", 1688671835]],
+              "bids": [["30000.0", "0.500", 1688671830], ["29990.0", "1.000", 1688671831]]
+            }
+          }
+        }"#;
+        let result: KrakenResult<OrderBookResponse> = serde_json::from_str(json).unwrap();
+        let order_books = result.into_result().unwrap();
+        let order_book = order_books.get("XXBTZUSD").unwrap();
+
+        assert_eq!(order_book.asks.len(), 2);
+        assert_eq!(order_book.bids[0].price(), Some(30000.0));
+        assert_eq!(order_book.spread(), Some(10.0));
+        assert!((order_book.spread_pct().unwrap() - 0.033_322).abs() < 1e-6);
+        assert_eq!(cumulative_volumes(&order_book.asks), vec![1.5, 3.5]);
+        assert_eq!(OrderBook::default().spread(), None);
+    }
+
+    #[test]
+    fn test_order_book_apply() {
+        let entry =
+            |price: &str, volume: &str| OrderBookEntry(price.to_owned(), volume.to_owned(), 0);
+
+        let mut order_book = OrderBook::default();
+        order_book.apply(
+            BookUpdate {
+                snapshot: true,
+                asks: vec![entry("30010.0", "1.0"), entry("30020.0", "2.0")],
+                bids: vec![entry("30000.0", "0.5"), entry("29990.0", "1.0")],
+                checksum: None,
+            },
+            2,
+        );
+        order_book.apply(
+            BookUpdate {
+                snapshot: false,
+                asks: vec![entry("30005.0", "3.0"), entry("30010.0", "0.0")],
+                bids: vec![entry("29990.00", "4.0")],
+                checksum: None,
+            },
+            2,
+        );
+
+        assert_eq!(
+            order_book.asks,
+            vec![entry("30005.0", "3.0"), entry("30020.0", "2.0")]
+        );
+        assert_eq!(
+            order_book.bids,
+            vec![entry("30000.0", "0.5"), entry("29990.00", "4.0")]
+        );
+
+        // the levels beyond the depth are dropped
+        order_book.apply(
+            BookUpdate {
+                bids: vec![entry("30001.0", "1.0")],
+                ..BookUpdate::default()
+            },
+            2,
+        );
+        assert_eq!(order_book.bids.len(), 2);
+        assert_eq!(order_book.bids[1].0, "30000.0");
+    }
+
+    #[test]
+    fn test_order_book_checksum() {
+        let entry =
+            |price: &str, volume: &str| OrderBookEntry(price.to_owned(), volume.to_owned(), 0);
+
+        let order_book = OrderBook {
+            asks: vec![
+                entry("0.05005", "0.00000500"),
+                entry("0.05010", "1.50000000"),
+            ],
+            bids: vec![entry("0.05000", "2.00000000")],
+        };
+        assert_eq!(
+            order_book.checksum(),
+            crc32fast::hash(
+                concat!("5005", "500", "5010", "150000000", "5000", "200000000").as_bytes()
+            )
+        );
+        assert_eq!(OrderBook::default().checksum(), 0);
+    }
+
+    #[test]
+    fn test_recent_trades() {
+        let json = r#"{
+          "error": [],
+          "result": {
+            "XXBTZUSD": [
+              ["30000.0", "0.5", 1688671834.123, "b", "m", "", 100],
+              ["30010.0", "1.0", 1688671835.456, "s", "l", ""]
+            ],
+            "last": "1688671835456000000"
+          }
+        }"#;
+        let result: KrakenResult<TradesResponse> = serde_json::from_str(json).unwrap();
+        let response = result.into_result().unwrap();
+        assert_eq!(response.last, "1688671835456000000");
+
+        let mut recent_trades = RecentTrades::default();
+        recent_trades.append(response.clone());
+        recent_trades.append(response);
+
+        assert_eq!(recent_trades.trades.len(), 4);
+        assert_eq!(recent_trades.last, Some("1688671835456000000".to_owned()));
+
+        let trade = &recent_trades.trades[0];
+        assert!(trade.is_buy());
+        assert!(trade.is_market());
+        assert_eq!(trade.id, Some(100));
+        assert_eq!(recent_trades.trades[1].id, None);
+        assert!(!recent_trades.trades[1].is_buy());
+    }
+
+    #[test]
+    fn test_ohlc() {
+        let json = r#"{
+          "error": [],
+          "result": {
+            "XXBTZUSD": [
+              [1688671200, "30000.0", "30100.0", "29900.0", "30050.0", "30010.0", "12.5", 120],
+              [1688671260, "30050.0", "30060.0", "29950.0", "29980.0", "30000.0", "3.0", 40]
+            ],
+            "last": 1688671200
+          }
+        }"#;
+        let result: KrakenResult<OhlcResponse> = serde_json::from_str(json).unwrap();
+        let response = result.into_result().unwrap();
+        assert_eq!(response.last, 1_688_671_200);
+
+        let candles = response.candles.get("XXBTZUSD").unwrap();
+        assert_eq!(candles.len(), 2);
+        assert!((candles[0].high - 30100.0).abs() < 1e-6);
+        assert_eq!(candles[0].count, 120);
+        assert!(candles[0].is_bullish());
+        assert!(!candles[1].is_bullish());
+    }
+
+    #[test]
+    fn test_recent_spreads() {
+        let json = r#"{
+          "error": [],
+          "result": {
+            "XXBTZUSD": [
+              [1688671834, "30000.0", "30001.0"],
+              [1688671835, "30000.0", "30003.0"],
+              [1688671836, "30000.5", "30002.5"]
+            ],
+            "last": 1688671836
+          }
+        }"#;
+        let result: KrakenResult<SpreadsResponse> = serde_json::from_str(json).unwrap();
+        let response = result.into_result().unwrap();
+        assert_eq!(response.last, 1_688_671_836);
+
+        let mut recent_spreads = RecentSpreads::default();
+        assert_eq!(recent_spreads.stats(), None);
+
+        recent_spreads.append(response);
+        assert_eq!(recent_spreads.spreads.len(), 3);
+        assert_eq!(recent_spreads.last, Some(1_688_671_836));
+
+        let stats = recent_spreads.stats().unwrap();
+        assert!((stats.min - 1.0).abs() < 1e-6);
+        assert!((stats.avg - 2.0).abs() < 1e-6);
+        assert!((stats.max - 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_open_orders() {
+        let json = r#"{
+          "error": [],
+          "result": {
+            "open": {
+              "OQCLML-BW3P3-BUCMWZ": {
+                "refid": null,
+                "userref": 0,
+                "status": "open",
+                "opentm": 1688666559.8974,
+                "starttm": 0,
+                "expiretm": 0,
+                "descr": {
+                  "pair": "XBTUSD",
+                  "type": "buy",
+                  "ordertype": "limit",
+                  "price": "30010.0",
+                  "price2": "0",
+                  "leverage": "none",
+                  "order": "buy 1.25000000 XBTUSD @ limit 30010.0",
+                  "close": ""
+                },
+                "vol": "1.25000000",
+                "vol_exec": "0.37500000",
+                "cost": "11253.7",
+                "fee": "0.00000",
+                "price": "30010.0",
+                "misc": "",
+                "oflags": "fciq"
+              }
+            }
+          }
+        }"#;
+        let result: KrakenResult<OpenOrdersResponse> = serde_json::from_str(json).unwrap();
+        let response = result.into_result().unwrap();
+
+        let order = response.open.get("OQCLML-BW3P3-BUCMWZ").unwrap();
+        assert_eq!(order.status, "open");
+        assert_eq!(order.descr.side, "buy");
+        assert_eq!(order.descr.ordertype, "limit");
+        assert_eq!(order.vol_exec, "0.37500000");
+
+        let json = r#"{ "error": [], "result": { "count": 1 } }"#;
+        let result: KrakenResult<CancelOrderResponse> = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            result.into_result(),
+            Ok(CancelOrderResponse {
+                count: 1,
+                pending: false
+            })
+        );
+    }
+
+    #[test]
+    fn test_add_order() {
+        let request = AddOrderRequest {
+            pair: "XXBTZUSD".to_owned(),
+            side: "buy".to_owned(),
+            ordertype: "market".to_owned(),
+            price: String::new(),
+            volume: "0.5".to_owned(),
+            validate: false,
+        };
+        assert_eq!(
+            serde_json::to_string(&request).unwrap(),
+            r#"{"pair":"XXBTZUSD","type":"buy","ordertype":"market","volume":"0.5"}"#
+        );
+
+        let json = r#"{
+          "error": [],
+          "result": { "descr": { "order": "buy 0.50000000 XBTUSD @ market" } }
+        }"#;
+        let result: KrakenResult<AddOrderResponse> = serde_json::from_str(json).unwrap();
+        let response = result.into_result().unwrap();
+        assert_eq!(response.descr.order, "buy 0.50000000 XBTUSD @ market");
+        assert!(response.txid.is_empty());
+    }
+
+    #[test]
+    fn test_history() {
+        let json = r#"{
+          "error": [],
+          "result": {
+            "trades": {
+              "THVRQM-33VKH-UCI7BS": {
+                "ordertxid": "OQCLML-BW3P3-BUCMWZ",
+                "postxid": "TKH2SE-M7IF5-CFI7LT",
+                "pair": "XXBTZUSD",
+                "time": 1688667796.8802,
+                "type": "buy",
+                "ordertype": "limit",
+                "price": "30010.00000",
+                "cost": "600.20000",
+                "fee": "0.00000",
+                "vol": "0.02000000",
+                "margin": "0.00000",
+                "misc": ""
+              }
+            },
+            "count": 3
+          }
+        }"#;
+        let result: KrakenResult<TradesHistoryResponse> = serde_json::from_str(json).unwrap();
+        let response = result.into_result().unwrap();
+        assert_eq!(response.count, 3);
+
+        let page = response.trades.into_iter().collect::<Vec<_>>();
+        assert_eq!(page[0].1.side, "buy");
+
+        let mut history = History::default();
+        history.append(0, 0, 3, page.clone());
+        assert!(history.has_more());
+
+        // not following the loaded page
+        history.append(0, 5, 3, page.clone());
+        assert_eq!(history.items.len(), 1);
+
+        history.append(0, 1, 3, page.clone());
+        assert_eq!(history.items.len(), 2);
+        history.append(0, 2, 3, vec![]);
+        assert!(!history.has_more());
+
+        // the first page of a previous date range
+        history.generation = 1;
+        history.append(0, 0, 3, page.clone());
+        assert_eq!(history.items.len(), 2);
+
+        history.append(1, 0, 3, page);
+        assert_eq!(history.items.len(), 1);
+        assert!(history.has_more());
+    }
+
+    #[test]
+    fn test_kraken_result_error() {
+        let json = r#"{ "error": ["EQuery:Unknown asset pair"] }"#;
+        let result: KrakenResult<TickerResponse> = serde_json::from_str(json).unwrap();
+
+        assert!(matches!(
+            result.into_result(),
+            Err(ClientError::Api(errors)) if errors == vec!["EQuery:Unknown asset pair"]
+        ));
+
+        let json = r#"{ "error": ["EAPI:Rate limit exceeded"] }"#;
+        let result: KrakenResult<TickerResponse> = serde_json::from_str(json).unwrap();
+        assert!(matches!(result.into_result(), Err(ClientError::RateLimit)));
+
+        let json = r#"{ "error": [] }"#;
+        let result: KrakenResult<TickerResponse> = serde_json::from_str(json).unwrap();
+        assert!(matches!(result.into_result(), Err(ClientError::Parse(_))));
+    }
+}
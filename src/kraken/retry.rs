@@ -0,0 +1,439 @@
+use std::cell::RefCell;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use krakenrs::AssetPairsResponse;
+
+use crate::kraken::client::RestAPI;
+use crate::kraken::error::ClientError;
+use crate::kraken::messages::{
+    AddOrderRequest, AddOrderResponse, AssetsResponse, BalanceResponse, CancelOrderResponse,
+    ClosedOrdersResponse, OhlcResponse, OpenOrdersResponse, OrderBookResponse, SpreadsResponse,
+    TickerResponse, TradesHistoryResponse, TradesResponse,
+};
+
+// Kraken call counter of the starter tier, see
+// https://docs.kraken.com/rest/#section/Rate-Limits/REST-API-Rate-Limits
+const MAX_CALL_COUNTER: f64 = 15.0;
+const CALL_COUNTER_DECAY_PER_SEC: f64 = 0.33;
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    // doubled at every retry
+    pub base_delay: Duration,
+    pub max_counter: f64,
+    pub decay_per_sec: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_secs(1),
+            max_counter: MAX_CALL_COUNTER,
+            decay_per_sec: CALL_COUNTER_DECAY_PER_SEC,
+        }
+    }
+}
+
+impl RetryPolicy {
+    fn delay(&self, attempt: u32) -> Duration {
+        self.base_delay.saturating_mul(2u32.saturating_pow(attempt))
+    }
+}
+
+// Failures sent again by the retry layer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Retry {
+    // the periodic refreshes, the next refresh is the retry
+    Never,
+    // the calls which are not idempotent could have been executed unless rate limited
+    RateLimit,
+    Transient,
+}
+
+// Counters of the retry layer shown in the Debug state.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RetryStats {
+    pub calls: u64,
+    pub retries: u64,
+    pub rate_limited: u64,
+    // calls delayed to stay below the call counter limit
+    pub throttled: u64,
+    pub failures: u64,
+}
+
+// Local mirror of the Kraken call counter, every private call increments it and it decays
+// over time. A call which would exceed the limit waits for the counter to decay.
+#[derive(Debug)]
+struct CallCounter {
+    value: f64,
+    updated: Instant,
+}
+
+impl CallCounter {
+    fn new(now: Instant) -> Self {
+        Self {
+            value: 0.0,
+            updated: now,
+        }
+    }
+
+    // returns how long to wait before the call can be sent
+    fn reserve(&mut self, cost: f64, now: Instant, policy: &RetryPolicy) -> Duration {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        let value = (self.value - elapsed * policy.decay_per_sec).max(0.0);
+
+        let excess = value + cost - policy.max_counter;
+        let wait = if excess > 0.0 {
+            Duration::from_secs_f64(excess / policy.decay_per_sec)
+        } else {
+            Duration::ZERO
+        };
+
+        // the counter as it will be when the call is sent
+        self.value = (value + cost).min(policy.max_counter);
+        self.updated = now + wait;
+        wait
+    }
+
+    fn saturate(&mut self, now: Instant, policy: &RetryPolicy) {
+        self.value = policy.max_counter;
+        self.updated = now;
+    }
+}
+
+// Decorates a RestAPI retrying the transient failures with an exponential backoff. The
+// worker executes one request at a time so the requests waiting for the backoff or the
+// call counter are queued instead of being sent.
+pub struct RetryAPI {
+    api: Box<dyn RestAPI + Send>,
+    policy: RetryPolicy,
+    counter: RefCell<CallCounter>,
+    stats: Arc<Mutex<RetryStats>>,
+}
+
+impl RetryAPI {
+    pub fn new(api: Box<dyn RestAPI + Send>, policy: RetryPolicy) -> RetryAPI {
+        RetryAPI {
+            api,
+            policy,
+            counter: RefCell::new(CallCounter::new(Instant::now())),
+            stats: Arc::default(),
+        }
+    }
+
+    pub fn stats(&self) -> Arc<Mutex<RetryStats>> {
+        Arc::clone(&self.stats)
+    }
+
+    fn update_stats(&self, update: impl FnOnce(&mut RetryStats)) {
+        update(&mut self.stats.lock().unwrap_or_else(PoisonError::into_inner));
+    }
+
+    // waits for the call counter to leave room for the call
+    fn reserve(&self, cost: f64) {
+        let wait = self
+            .counter
+            .borrow_mut()
+            .reserve(cost, Instant::now(), &self.policy);
+        if !wait.is_zero() {
+            self.update_stats(|stats| stats.throttled += 1);
+            thread::sleep(wait);
+        }
+    }
+
+    // cost is the increment of the call counter
+    fn call<T>(
+        &self,
+        cost: f64,
+        retry: Retry,
+        f: impl Fn(&dyn RestAPI) -> Result<T, ClientError>,
+    ) -> Result<T, ClientError> {
+        if cost > 0.0 {
+            self.reserve(cost);
+        }
+
+        let mut attempt = 0;
+        loop {
+            self.update_stats(|stats| stats.calls += 1);
+            let result = f(self.api.as_ref());
+
+            let Err(e) = &result else {
+                return result;
+            };
+            let rate_limited = *e == ClientError::RateLimit;
+            if rate_limited {
+                self.update_stats(|stats| stats.rate_limited += 1);
+                self.counter
+                    .borrow_mut()
+                    .saturate(Instant::now(), &self.policy);
+            }
+
+            let retried = match retry {
+                Retry::Never => false,
+                Retry::RateLimit => rate_limited,
+                Retry::Transient => e.is_transient(),
+            };
+            if attempt >= self.policy.max_retries || !retried {
+                self.update_stats(|stats| stats.failures += 1);
+                return result;
+            }
+
+            self.update_stats(|stats| stats.retries += 1);
+            // the counted calls wait for the saturated counter to decay
+            if rate_limited && cost > 0.0 {
+                self.reserve(cost);
+            } else {
+                thread::sleep(self.policy.delay(attempt));
+            }
+            attempt += 1;
+        }
+    }
+}
+
+impl RestAPI for RetryAPI {
+    fn connect(&mut self) -> Result<(), ClientError> {
+        self.api.connect()
+    }
+
+    fn disconnect(&self) -> Result<(), ClientError> {
+        self.api.disconnect()
+    }
+
+    // the public endpoints are not counted, they are limited per ip address
+    fn list_assets(&self) -> Result<AssetsResponse, ClientError> {
+        self.call(0.0, Retry::Transient, |api| api.list_assets())
+    }
+
+    fn list_asset_pairs(&self) -> Result<AssetPairsResponse, ClientError> {
+        self.call(0.0, Retry::Transient, |api| api.list_asset_pairs())
+    }
+
+    // the market data is refreshed periodically, a failed refresh is not retried so that
+    // the worker is free for the next requests
+    fn ticker(&self, asset_pair: &str) -> Result<TickerResponse, ClientError> {
+        self.call(0.0, Retry::Never, |api| api.ticker(asset_pair))
+    }
+
+    fn order_book(&self, asset_pair: &str, depth: u32) -> Result<OrderBookResponse, ClientError> {
+        self.call(0.0, Retry::Never, |api| api.order_book(asset_pair, depth))
+    }
+
+    fn recent_trades(
+        &self,
+        asset_pair: &str,
+        since: Option<String>,
+    ) -> Result<TradesResponse, ClientError> {
+        self.call(0.0, Retry::Never, |api| {
+            api.recent_trades(asset_pair, since.clone())
+        })
+    }
+
+    fn ohlc(&self, asset_pair: &str, interval: u32) -> Result<OhlcResponse, ClientError> {
+        self.call(0.0, Retry::Never, |api| api.ohlc(asset_pair, interval))
+    }
+
+    fn spreads(
+        &self,
+        asset_pair: &str,
+        since: Option<u64>,
+    ) -> Result<SpreadsResponse, ClientError> {
+        self.call(0.0, Retry::Never, |api| api.spreads(asset_pair, since))
+    }
+
+    fn balances(&self) -> Result<BalanceResponse, ClientError> {
+        self.call(1.0, Retry::Transient, |api| api.balances())
+    }
+
+    fn open_orders(&self) -> Result<OpenOrdersResponse, ClientError> {
+        self.call(1.0, Retry::Transient, |api| api.open_orders())
+    }
+
+    // the orders have their own counter on the kraken side
+    fn cancel_order(&self, txid: &str) -> Result<CancelOrderResponse, ClientError> {
+        self.call(0.0, Retry::RateLimit, |api| api.cancel_order(txid))
+    }
+
+    fn closed_orders(
+        &self,
+        offset: usize,
+        start: Option<u64>,
+        end: Option<u64>,
+    ) -> Result<ClosedOrdersResponse, ClientError> {
+        self.call(1.0, Retry::Transient, |api| {
+            api.closed_orders(offset, start, end)
+        })
+    }
+
+    // the history calls cost 2
+    fn trades_history(
+        &self,
+        offset: usize,
+        start: Option<u64>,
+        end: Option<u64>,
+    ) -> Result<TradesHistoryResponse, ClientError> {
+        self.call(2.0, Retry::Transient, |api| {
+            api.trades_history(offset, start, end)
+        })
+    }
+
+    fn add_order(&self, order: &AddOrderRequest) -> Result<AddOrderResponse, ClientError> {
+        self.call(0.0, Retry::RateLimit, |api| api.add_order(order))
+    }
+}
+
+// tests
+#[cfg(test)]
+mod tests {
+    use mockall::Sequence;
+
+    use super::*;
+    use crate::kraken::client::MockRestAPI;
+
+    // no backoff and a counter decaying in a few milliseconds
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            base_delay: Duration::ZERO,
+            decay_per_sec: 1000.0,
+            ..RetryPolicy::default()
+        }
+    }
+
+    #[test]
+    fn test_call_counter() {
+        let policy = RetryPolicy {
+            max_counter: 4.0,
+            decay_per_sec: 0.5,
+            ..policy()
+        };
+        let now = Instant::now();
+        let mut counter = CallCounter::new(now);
+
+        assert_eq!(counter.reserve(2.0, now, &policy), Duration::ZERO);
+        assert_eq!(counter.reserve(2.0, now, &policy), Duration::ZERO);
+        // 1 over the limit, decayed in 2 seconds
+        assert_eq!(counter.reserve(1.0, now, &policy), Duration::from_secs(2));
+        // the previous call is sent at now + 2 seconds
+        assert_eq!(
+            counter.reserve(1.0, now + Duration::from_secs(4), &policy),
+            Duration::ZERO
+        );
+
+        counter.saturate(now + Duration::from_secs(4), &policy);
+        assert_eq!(
+            counter.reserve(1.0, now + Duration::from_secs(4), &policy),
+            Duration::from_secs(2)
+        );
+    }
+
+    #[test]
+    fn test_retry_transient_failures() {
+        let mut sequence = Sequence::new();
+        let mut mock_client = Box::new(MockRestAPI::new());
+        mock_client
+            .expect_list_assets()
+            .once()
+            .in_sequence(&mut sequence)
+            .returning(|| Err(ClientError::Network("timeout".to_owned())));
+        mock_client
+            .expect_list_assets()
+            .once()
+            .in_sequence(&mut sequence)
+            .returning(|| Err(ClientError::RateLimit));
+        mock_client
+            .expect_list_assets()
+            .once()
+            .in_sequence(&mut sequence)
+            .returning(|| Ok(AssetsResponse::new()));
+        mock_client
+            .expect_balances()
+            .once()
+            .returning(|| Err(ClientError::Auth("EAPI:Invalid key".to_owned())));
+
+        let api = RetryAPI::new(mock_client, policy());
+        let stats = api.stats();
+
+        assert!(api.list_assets().is_ok());
+        assert!(api.balances().is_err());
+
+        assert_eq!(
+            *stats.lock().unwrap(),
+            RetryStats {
+                calls: 4,
+                retries: 2,
+                rate_limited: 1,
+                throttled: 1,
+                failures: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_retry_gives_up() {
+        let mut mock_client = Box::new(MockRestAPI::new());
+        mock_client
+            .expect_list_asset_pairs()
+            .times(4)
+            .returning(|| Err(ClientError::Network("timeout".to_owned())));
+        // refreshed periodically, not sent again
+        mock_client
+            .expect_ohlc()
+            .once()
+            .returning(|_, _| Err(ClientError::Network("timeout".to_owned())));
+        // the order could have been added, not sent again
+        mock_client
+            .expect_add_order()
+            .once()
+            .returning(|_| Err(ClientError::Network("timeout".to_owned())));
+
+        let api = RetryAPI::new(mock_client, policy());
+
+        assert_eq!(
+            api.list_asset_pairs().unwrap_err(),
+            ClientError::Network("timeout".to_owned())
+        );
+        assert!(api.ohlc("XXBTZUSD", 60).is_err());
+        assert!(api.add_order(&AddOrderRequest::default()).is_err());
+        assert_eq!(api.stats().lock().unwrap().failures, 3);
+    }
+
+    #[test]
+    fn test_rate_limited_waits_for_the_counter() {
+        let mut sequence = Sequence::new();
+        let mut mock_client = Box::new(MockRestAPI::new());
+        mock_client
+            .expect_open_orders()
+            .once()
+            .in_sequence(&mut sequence)
+            .returning(|| Err(ClientError::RateLimit));
+        mock_client
+            .expect_open_orders()
+            .once()
+            .in_sequence(&mut sequence)
+            .returning(|| Ok(OpenOrdersResponse::default()));
+
+        // the backoff would wait 30 seconds, the counter decays in a millisecond
+        let api = RetryAPI::new(
+            mock_client,
+            RetryPolicy {
+                base_delay: Duration::from_secs(30),
+                ..policy()
+            },
+        );
+
+        assert!(api.open_orders().is_ok());
+        assert_eq!(
+            *api.stats().lock().unwrap(),
+            RetryStats {
+                calls: 2,
+                retries: 1,
+                rate_limited: 1,
+                throttled: 1,
+                failures: 0,
+            }
+        );
+    }
+}
@@ -0,0 +1,322 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use krakenrs::AssetPairsResponse;
+
+use crate::kraken::client::RestAPI;
+use crate::kraken::error::ClientError;
+use crate::kraken::messages::{
+    AddOrderRequest, AddOrderResponse, AssetsResponse, BalanceResponse, CancelOrderResponse,
+    ClosedOrdersResponse, OhlcResponse, OpenOrdersResponse, OrderBookResponse, SpreadsResponse,
+    TickerResponse, TradesHistoryResponse, TradesResponse,
+};
+
+// Requests executed by the worker against the Kraken API endpoint.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Request {
+    Connect,
+    ListAssets,
+    ListAssetPairs,
+    Ticker {
+        asset_pair: String,
+    },
+    OrderBook {
+        asset_pair: String,
+        depth: u32,
+    },
+    RecentTrades {
+        asset_pair: String,
+        since: Option<String>,
+    },
+    Ohlc {
+        asset_pair: String,
+        interval: u32,
+    },
+    Spreads {
+        asset_pair: String,
+        since: Option<u64>,
+    },
+    Balances,
+    OpenOrders,
+    CancelOrder {
+        txid: String,
+    },
+    // the generation of the date range is echoed in the response
+    ClosedOrders {
+        generation: u64,
+        offset: usize,
+        start: Option<u64>,
+        end: Option<u64>,
+    },
+    TradesHistory {
+        generation: u64,
+        offset: usize,
+        start: Option<u64>,
+        end: Option<u64>,
+    },
+    AddOrder {
+        order: AddOrderRequest,
+    },
+}
+
+// Responses posted back by the worker, one for each request.
+#[derive(Debug)]
+pub enum Response {
+    Connected {
+        result: Result<(), ClientError>,
+    },
+    Assets {
+        assets: Result<AssetsResponse, ClientError>,
+    },
+    AssetPairs {
+        asset_pairs: Result<AssetPairsResponse, ClientError>,
+    },
+    Ticker {
+        asset_pair: String,
+        ticker: Result<TickerResponse, ClientError>,
+    },
+    OrderBook {
+        asset_pair: String,
+        order_book: Result<OrderBookResponse, ClientError>,
+    },
+    RecentTrades {
+        asset_pair: String,
+        trades: Result<TradesResponse, ClientError>,
+    },
+    Ohlc {
+        asset_pair: String,
+        interval: u32,
+        ohlc: Result<OhlcResponse, ClientError>,
+    },
+    Spreads {
+        asset_pair: String,
+        spreads: Result<SpreadsResponse, ClientError>,
+    },
+    Balances {
+        balances: Result<BalanceResponse, ClientError>,
+    },
+    OpenOrders {
+        orders: Result<OpenOrdersResponse, ClientError>,
+    },
+    OrderCancelled {
+        txid: String,
+        result: Result<CancelOrderResponse, ClientError>,
+    },
+    ClosedOrders {
+        generation: u64,
+        offset: usize,
+        orders: Result<ClosedOrdersResponse, ClientError>,
+    },
+    TradesHistory {
+        generation: u64,
+        offset: usize,
+        trades: Result<TradesHistoryResponse, ClientError>,
+    },
+    OrderAdded {
+        order: AddOrderRequest,
+        result: Result<AddOrderResponse, ClientError>,
+    },
+}
+
+pub fn handle_request(api: &mut dyn RestAPI, request: Request) -> Response {
+    match request {
+        Request::Connect => Response::Connected {
+            result: api.connect(),
+        },
+        Request::ListAssets => Response::Assets {
+            assets: api.list_assets(),
+        },
+        Request::ListAssetPairs => Response::AssetPairs {
+            asset_pairs: api.list_asset_pairs(),
+        },
+        Request::Ticker { asset_pair } => Response::Ticker {
+            ticker: api.ticker(&asset_pair),
+            asset_pair,
+        },
+        Request::OrderBook { asset_pair, depth } => Response::OrderBook {
+            order_book: api.order_book(&asset_pair, depth),
+            asset_pair,
+        },
+        Request::RecentTrades { asset_pair, since } => Response::RecentTrades {
+            trades: api.recent_trades(&asset_pair, since),
+            asset_pair,
+        },
+        Request::Ohlc {
+            asset_pair,
+            interval,
+        } => Response::Ohlc {
+            ohlc: api.ohlc(&asset_pair, interval),
+            asset_pair,
+            interval,
+        },
+        Request::Spreads { asset_pair, since } => Response::Spreads {
+            spreads: api.spreads(&asset_pair, since),
+            asset_pair,
+        },
+        Request::Balances => Response::Balances {
+            balances: api.balances(),
+        },
+        Request::OpenOrders => Response::OpenOrders {
+            orders: api.open_orders(),
+        },
+        Request::CancelOrder { txid } => Response::OrderCancelled {
+            result: api.cancel_order(&txid),
+            txid,
+        },
+        Request::ClosedOrders {
+            generation,
+            offset,
+            start,
+            end,
+        } => Response::ClosedOrders {
+            orders: api.closed_orders(offset, start, end),
+            generation,
+            offset,
+        },
+        Request::TradesHistory {
+            generation,
+            offset,
+            start,
+            end,
+        } => Response::TradesHistory {
+            trades: api.trades_history(offset, start, end),
+            generation,
+            offset,
+        },
+        Request::AddOrder { order } => Response::OrderAdded {
+            result: api.add_order(&order),
+            order,
+        },
+    }
+}
+
+enum Channel {
+    Thread {
+        requests: Sender<Request>,
+        responses: Receiver<Response>,
+    },
+    #[cfg(test)]
+    Inline {
+        api: Box<dyn RestAPI + Send>,
+        responses: std::collections::VecDeque<Response>,
+    },
+}
+
+// The Worker owns the RestAPI and executes every request on its own thread so that
+// the ui keeps drawing and accepting keys while the requests are in flight.
+pub struct Worker {
+    channel: Channel,
+}
+
+impl Worker {
+    pub fn spawn(mut api: Box<dyn RestAPI + Send>) -> Worker {
+        let (requests, requests_rx) = mpsc::channel::<Request>();
+        let (responses_tx, responses) = mpsc::channel::<Response>();
+
+        thread::Builder::new()
+            .name(String::from("kraken-worker"))
+            .spawn(move || {
+                for request in requests_rx {
+                    let response = handle_request(api.as_mut(), request);
+                    if responses_tx.send(response).is_err() {
+                        break;
+                    }
+                }
+            })
+            .expect("failed to spawn the kraken worker thread");
+
+        Worker {
+            channel: Channel::Thread {
+                requests,
+                responses,
+            },
+        }
+    }
+
+    // executes the requests synchronously on the caller thread, useful for deterministic tests.
+    #[cfg(test)]
+    pub fn inline(api: Box<dyn RestAPI + Send>) -> Worker {
+        Worker {
+            channel: Channel::Inline {
+                api,
+                responses: std::collections::VecDeque::new(),
+            },
+        }
+    }
+
+    // returns back the request if the worker is not running anymore.
+    pub fn send(&mut self, request: Request) -> Result<(), Box<Request>> {
+        match &mut self.channel {
+            Channel::Thread { requests, .. } => requests.send(request).map_err(|e| Box::new(e.0)),
+            #[cfg(test)]
+            Channel::Inline { api, responses } => {
+                responses.push_back(handle_request(api.as_mut(), request));
+                Ok(())
+            }
+        }
+    }
+
+    pub fn try_recv(&mut self) -> Option<Response> {
+        match &mut self.channel {
+            Channel::Thread { responses, .. } => responses.try_recv().ok(),
+            #[cfg(test)]
+            Channel::Inline { responses, .. } => responses.pop_front(),
+        }
+    }
+}
+
+// tests
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::kraken::client::MockRestAPI;
+
+    #[test]
+    fn test_handle_request() {
+        let mut mock_client = MockRestAPI::new();
+        mock_client.expect_connect().once().returning(|| Ok(()));
+        mock_client
+            .expect_ticker()
+            .once()
+            .returning(|_| Ok(TickerResponse::new()));
+
+        let response = handle_request(&mut mock_client, Request::Connect);
+        assert!(matches!(response, Response::Connected { result: Ok(()) }));
+
+        let response = handle_request(
+            &mut mock_client,
+            Request::Ticker {
+                asset_pair: "XETHZUSD".to_owned(),
+            },
+        );
+        assert!(matches!(
+            response,
+            Response::Ticker { asset_pair, ticker: Ok(_) } if asset_pair == "XETHZUSD"
+        ));
+    }
+
+    #[test]
+    fn test_worker_thread() {
+        let mut mock_client = Box::new(MockRestAPI::new());
+        mock_client
+            .expect_list_assets()
+            .once()
+            .returning(|| Ok(AssetsResponse::new()));
+
+        let mut worker = Worker::spawn(mock_client);
+        assert!(worker.send(Request::ListAssets).is_ok());
+
+        let mut response = None;
+        for _ in 0..100 {
+            response = worker.try_recv();
+            if response.is_some() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        assert!(matches!(response, Some(Response::Assets { assets: Ok(_) })));
+    }
+}
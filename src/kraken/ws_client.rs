@@ -0,0 +1,206 @@
+use std::io;
+use std::net::TcpStream;
+use std::time::Duration;
+
+use thiserror::Error;
+use tungstenite::{stream::MaybeTlsStream, Message, WebSocket};
+
+use crate::kraken::ws_messages::{parse_message, MarketData, Subscription};
+
+#[cfg(test)]
+use mockall::automock;
+
+// how long a read waits for a message before giving back the control to the caller
+const READ_TIMEOUT: Duration = Duration::from_millis(100);
+
+#[derive(Error, Debug)]
+pub enum StreamError {
+    #[error("websocket error {0}")]
+    WebSocket(#[from] Box<tungstenite::Error>),
+    #[error("io error {0}")]
+    Io(#[from] io::Error),
+    #[error("invalid message {0}")]
+    InvalidMessage(String),
+    #[error("not connected")]
+    NotConnected,
+    #[error("connection closed by the server")]
+    Closed,
+}
+
+impl From<tungstenite::Error> for StreamError {
+    fn from(e: tungstenite::Error) -> Self {
+        StreamError::WebSocket(Box::new(e))
+    }
+}
+
+// Public market data pushed by the Kraken websocket API, the counterpart of RestAPI.
+#[cfg_attr(test, automock)]
+pub trait MarketDataAPI {
+    fn connect(&mut self) -> Result<(), StreamError>;
+    fn subscribe(&mut self, subscription: &Subscription) -> Result<(), StreamError>;
+    fn unsubscribe(&mut self, subscription: &Subscription) -> Result<(), StreamError>;
+    // None if nothing has been received within the read timeout
+    fn read(&mut self) -> Result<Option<MarketData>, StreamError>;
+}
+
+pub struct MarketDataImpl {
+    url: String,
+    socket: Option<WebSocket<MaybeTlsStream<TcpStream>>>,
+}
+
+impl MarketDataImpl {
+    pub fn new(url: String) -> MarketDataImpl {
+        MarketDataImpl { url, socket: None }
+    }
+
+    fn send(&mut self, text: String) -> Result<(), StreamError> {
+        let socket = self.socket.as_mut().ok_or(StreamError::NotConnected)?;
+        if let Err(e) = socket.send(Message::text(text)) {
+            self.socket = None;
+            return Err(e.into());
+        }
+
+        Ok(())
+    }
+}
+
+// the timeout has to be set on the tcp stream below the tls session
+fn set_read_timeout(stream: &MaybeTlsStream<TcpStream>, timeout: Duration) -> io::Result<()> {
+    match stream {
+        MaybeTlsStream::Plain(stream) => stream.set_read_timeout(Some(timeout)),
+        MaybeTlsStream::NativeTls(stream) => stream.get_ref().set_read_timeout(Some(timeout)),
+        _ => Ok(()),
+    }
+}
+
+impl MarketDataAPI for MarketDataImpl {
+    fn connect(&mut self) -> Result<(), StreamError> {
+        let (socket, _) = tungstenite::connect(self.url.as_str())?;
+        set_read_timeout(socket.get_ref(), READ_TIMEOUT)?;
+        self.socket = Some(socket);
+
+        Ok(())
+    }
+
+    fn subscribe(&mut self, subscription: &Subscription) -> Result<(), StreamError> {
+        self.send(subscription.message("subscribe"))
+    }
+
+    fn unsubscribe(&mut self, subscription: &Subscription) -> Result<(), StreamError> {
+        self.send(subscription.message("unsubscribe"))
+    }
+
+    fn read(&mut self) -> Result<Option<MarketData>, StreamError> {
+        let socket = self.socket.as_mut().ok_or(StreamError::NotConnected)?;
+
+        match socket.read() {
+            Ok(Message::Text(text)) => parse_message(text.as_str())
+                .map(Some)
+                .map_err(StreamError::InvalidMessage),
+            Ok(Message::Close(_)) => {
+                self.socket = None;
+                Err(StreamError::Closed)
+            }
+            // the pings are answered by tungstenite itself
+            Ok(_) => Ok(None),
+            Err(tungstenite::Error::Io(e))
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                Ok(None)
+            }
+            Err(e) => {
+                self.socket = None;
+                Err(e.into())
+            }
+        }
+    }
+}
+
+// tests
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+    use std::thread;
+
+    use super::*;
+    use crate::kraken::ws_messages::Feed;
+
+    // accepts a single client, answers its first message with the given ones and closes
+    fn serve(replies: Vec<&'static str>) -> (String, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut socket = tungstenite::accept(stream).unwrap();
+            let request = socket.read().unwrap().into_text().unwrap().to_string();
+            for reply in replies {
+                socket.send(Message::text(reply)).unwrap();
+            }
+            socket.close(None).unwrap();
+            // wait for the client to acknowledge the close
+            while socket.read().is_ok() {}
+            request
+        });
+
+        (url, server)
+    }
+
+    fn read_next(client: &mut MarketDataImpl) -> Result<MarketData, StreamError> {
+        loop {
+            if let Some(data) = client.read()? {
+                return Ok(data);
+            }
+        }
+    }
+
+    #[test]
+    fn test_stream_from_local_server() {
+        let (url, server) = serve(vec![
+            r#"{"event":"heartbeat"}"#,
+            r#"[0, ["5698.40000", "5700.00000", "1542057299.545897", "1.0", "0.9"],
+                "spread", "XBT/USD"]"#,
+            "garbage",
+        ]);
+
+        let mut client = MarketDataImpl::new(url);
+        assert!(matches!(client.read(), Err(StreamError::NotConnected)));
+        client.connect().unwrap();
+        client
+            .subscribe(&Subscription::new("XBT/USD", Feed::Spread))
+            .unwrap();
+
+        assert_eq!(read_next(&mut client).unwrap(), MarketData::Heartbeat);
+        assert!(matches!(
+            read_next(&mut client),
+            Ok(MarketData::Spread { pair, .. }) if pair == "XBT/USD"
+        ));
+        assert!(matches!(
+            read_next(&mut client),
+            Err(StreamError::InvalidMessage(_))
+        ));
+        assert!(matches!(read_next(&mut client), Err(StreamError::Closed)));
+        assert!(matches!(client.read(), Err(StreamError::NotConnected)));
+
+        assert_eq!(
+            server.join().unwrap(),
+            r#"{"event":"subscribe","pair":["XBT/USD"],"subscription":{"name":"spread"}}"#
+        );
+    }
+
+    #[test]
+    fn test_connect_refused() {
+        // bind and drop to get a port with nobody listening
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+
+        let mut client = MarketDataImpl::new(format!("ws://127.0.0.1:{port}"));
+        assert!(client.connect().is_err());
+    }
+}
@@ -0,0 +1,437 @@
+use serde_json::{json, Value};
+
+use crate::kraken::messages::{Candle, OrderBookEntry, Spread, TickerInfo, Trade};
+
+// public market data channels of the Kraken websocket API
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Feed {
+    Ticker,
+    // depth: 10, 25, 100, 500, 1000
+    Book(u32),
+    Trade,
+    // interval in minutes
+    Ohlc(u32),
+    Spread,
+}
+
+impl Feed {
+    pub fn name(self) -> &'static str {
+        match self {
+            Feed::Ticker => "ticker",
+            Feed::Book(_) => "book",
+            Feed::Trade => "trade",
+            Feed::Ohlc(_) => "ohlc",
+            Feed::Spread => "spread",
+        }
+    }
+}
+
+// one channel of one asset pair, the pair is the wsname (XBT/USD) of the AssetPair
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subscription {
+    pub pair: String,
+    pub feed: Feed,
+}
+
+impl Subscription {
+    pub fn new(pair: &str, feed: Feed) -> Subscription {
+        Subscription {
+            pair: pair.to_owned(),
+            feed,
+        }
+    }
+
+    // event is "subscribe" or "unsubscribe"
+    pub fn message(&self, event: &str) -> String {
+        let mut subscription = json!({ "name": self.feed.name() });
+        match self.feed {
+            Feed::Book(depth) => subscription["depth"] = json!(depth),
+            Feed::Ohlc(interval) => subscription["interval"] = json!(interval),
+            _ => {}
+        }
+
+        json!({
+            "event": event,
+            "pair": [self.pair],
+            "subscription": subscription,
+        })
+        .to_string()
+    }
+}
+
+// Snapshot or incremental update of the "book" channel, a level with a zero volume
+// has to be removed from the book.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BookUpdate {
+    pub snapshot: bool,
+    pub asks: Vec<OrderBookEntry>,
+    pub bids: Vec<OrderBookEntry>,
+    // CRC32 of the top 10 levels after the update is applied, only sent with the updates
+    pub checksum: Option<u32>,
+}
+
+// Messages pushed by the Kraken websocket API, the pair is the wsname (XBT/USD).
+#[derive(Debug, Clone, PartialEq)]
+pub enum MarketData {
+    Ticker {
+        pair: String,
+        ticker: TickerInfo,
+    },
+    Book {
+        pair: String,
+        depth: u32,
+        update: BookUpdate,
+    },
+    Trades {
+        pair: String,
+        trades: Vec<Trade>,
+    },
+    Ohlc {
+        pair: String,
+        interval: u32,
+        candle: Candle,
+    },
+    Spread {
+        pair: String,
+        spread: Spread,
+    },
+    Heartbeat,
+    // systemStatus and subscriptionStatus events
+    Status {
+        message: String,
+    },
+}
+
+pub fn parse_message(text: &str) -> Result<MarketData, String> {
+    match serde_json::from_str::<Value>(text).map_err(|e| e.to_string())? {
+        Value::Object(object) => {
+            let field = |name: &str| object.get(name).and_then(Value::as_str).unwrap_or("");
+            match field("event") {
+                "heartbeat" => Ok(MarketData::Heartbeat),
+                "systemStatus" => Ok(MarketData::Status {
+                    message: format!("system {} {}", field("status"), field("version")),
+                }),
+                "subscriptionStatus" => Ok(MarketData::Status {
+                    message: format!(
+                        "{} {} {} {}",
+                        field("status"),
+                        field("channelName"),
+                        field("pair"),
+                        field("errorMessage")
+                    )
+                    .trim_end()
+                    .to_owned(),
+                }),
+                "" => Err(format!("unknown message {text}")),
+                event => Ok(MarketData::Status {
+                    message: event.to_owned(),
+                }),
+            }
+        }
+        Value::Array(values) => parse_channel_message(&values),
+        _ => Err(format!("unknown message {text}")),
+    }
+}
+
+// [channelID, payload, ..., channelName, pair], the book updates may carry two payloads
+fn parse_channel_message(values: &[Value]) -> Result<MarketData, String> {
+    if values.len() < 4 {
+        return Err("invalid channel message".to_owned());
+    }
+    let channel_name = text(values, values.len() - 2)?;
+    let pair = text(values, values.len() - 1)?;
+    let payloads = &values[1..values.len() - 2];
+
+    match channel_name.split_once('-') {
+        None if channel_name == "ticker" => Ok(MarketData::Ticker {
+            pair,
+            ticker: parse_ticker(&payloads[0])?,
+        }),
+        None if channel_name == "trade" => Ok(MarketData::Trades {
+            pair,
+            trades: array(&payloads[0])?
+                .iter()
+                .map(|trade| parse_trade(array(trade)?))
+                .collect::<Result<_, _>>()?,
+        }),
+        None if channel_name == "spread" => Ok(MarketData::Spread {
+            pair,
+            spread: parse_spread(array(&payloads[0])?)?,
+        }),
+        Some(("ohlc", interval)) => {
+            let interval = interval.parse::<u32>().map_err(|e| e.to_string())?;
+            Ok(MarketData::Ohlc {
+                pair,
+                interval,
+                candle: parse_candle(array(&payloads[0])?, interval)?,
+            })
+        }
+        Some(("book", depth)) => Ok(MarketData::Book {
+            pair,
+            depth: depth.parse::<u32>().map_err(|e| e.to_string())?,
+            update: parse_book(payloads)?,
+        }),
+        _ => Err(format!("unknown channel {channel_name}")),
+    }
+}
+
+// the numbers are sent either as strings or as json numbers
+fn text(values: &[Value], index: usize) -> Result<String, String> {
+    match values.get(index) {
+        Some(Value::String(value)) => Ok(value.clone()),
+        Some(Value::Number(value)) => Ok(value.to_string()),
+        _ => Err(format!("invalid field {index}")),
+    }
+}
+
+fn number(values: &[Value], index: usize) -> Result<f64, String> {
+    text(values, index)?
+        .parse()
+        .map_err(|_| format!("invalid number field {index}"))
+}
+
+fn array(value: &Value) -> Result<&Vec<Value>, String> {
+    value.as_array().ok_or_else(|| "invalid array".to_owned())
+}
+
+// same fields of the REST ticker, the volumes of a and b are integers and o is an array
+fn parse_ticker(value: &Value) -> Result<TickerInfo, String> {
+    let texts = |name: &str| -> Result<Vec<String>, String> {
+        let values = value
+            .get(name)
+            .and_then(Value::as_array)
+            .ok_or_else(|| format!("invalid ticker field {name}"))?;
+        (0..values.len()).map(|index| text(values, index)).collect()
+    };
+
+    Ok(TickerInfo {
+        a: texts("a")?,
+        b: texts("b")?,
+        c: texts("c")?,
+        v: texts("v")?,
+        p: texts("p")?,
+        t: value
+            .get("t")
+            .and_then(Value::as_array)
+            .map(|values| values.iter().filter_map(Value::as_u64).collect())
+            .unwrap_or_default(),
+        l: texts("l")?,
+        h: texts("h")?,
+        o: texts("o")?.into_iter().next().unwrap_or_default(),
+    })
+}
+
+// [price, volume, time, side, ordertype, misc]
+fn parse_trade(values: &[Value]) -> Result<Trade, String> {
+    Ok(Trade {
+        price: text(values, 0)?,
+        volume: text(values, 1)?,
+        time: number(values, 2)?,
+        side: text(values, 3)?,
+        order_type: text(values, 4)?,
+        misc: text(values, 5)?,
+        id: None,
+    })
+}
+
+// [bid, ask, timestamp, bidVolume, askVolume]
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn parse_spread(values: &[Value]) -> Result<Spread, String> {
+    Ok(Spread {
+        time: number(values, 2)? as u64,
+        bid: text(values, 0)?,
+        ask: text(values, 1)?,
+    })
+}
+
+// [time, etime, open, high, low, close, vwap, volume, count], the candle starts
+// interval minutes before etime
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn parse_candle(values: &[Value], interval: u32) -> Result<Candle, String> {
+    let end = number(values, 1)? as u64;
+
+    Ok(Candle {
+        time: end.saturating_sub(u64::from(interval) * 60),
+        open: number(values, 2)?,
+        high: number(values, 3)?,
+        low: number(values, 4)?,
+        close: number(values, 5)?,
+        vwap: number(values, 6)?,
+        volume: number(values, 7)?,
+        count: values
+            .get(8)
+            .and_then(Value::as_u64)
+            .ok_or_else(|| "invalid candle count".to_owned())?,
+    })
+}
+
+// snapshot {"as": [...], "bs": [...]}, updates {"a": [...]} {"b": [...], "c": "checksum"}
+fn parse_book(payloads: &[Value]) -> Result<BookUpdate, String> {
+    let mut update = BookUpdate::default();
+
+    for payload in payloads {
+        let object = payload
+            .as_object()
+            .ok_or_else(|| "invalid book payload".to_owned())?;
+        for (name, levels) in object {
+            match name.as_str() {
+                "as" | "bs" => update.snapshot = true,
+                "a" | "b" => {}
+                "c" => {
+                    update.checksum = levels.as_str().and_then(|checksum| checksum.parse().ok());
+                    continue;
+                }
+                _ => continue,
+            }
+
+            let levels = array(levels)?
+                .iter()
+                .map(|level| parse_book_level(array(level)?))
+                .collect::<Result<Vec<_>, _>>()?;
+            if name.starts_with('a') {
+                update.asks.extend(levels);
+            } else {
+                update.bids.extend(levels);
+            }
+        }
+    }
+
+    Ok(update)
+}
+
+// [price, volume, timestamp] and an optional "r" for the republished levels
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn parse_book_level(values: &[Value]) -> Result<OrderBookEntry, String> {
+    Ok(OrderBookEntry(
+        text(values, 0)?,
+        text(values, 1)?,
+        number(values, 2)? as u64,
+    ))
+}
+
+// tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subscription_message() {
+        let subscription = Subscription::new("XBT/USD", Feed::Ohlc(5));
+        assert_eq!(
+            subscription.message("subscribe"),
+            r#"{"event":"subscribe","pair":["XBT/USD"],"subscription":{"interval":5,"name":"ohlc"}}"#
+        );
+        let subscription = Subscription::new("XBT/USD", Feed::Ticker);
+        assert_eq!(
+            subscription.message("unsubscribe"),
+            r#"{"event":"unsubscribe","pair":["XBT/USD"],"subscription":{"name":"ticker"}}"#
+        );
+    }
+
+    #[test]
+    fn test_parse_events() {
+        assert_eq!(
+            parse_message(r#"{"event":"heartbeat"}"#),
+            Ok(MarketData::Heartbeat)
+        );
+        assert_eq!(
+            parse_message(
+                r#"{"channelName":"ticker","event":"subscriptionStatus","pair":"XBT/USD",
+                    "status":"subscribed","subscription":{"name":"ticker"}}"#
+            ),
+            Ok(MarketData::Status {
+                message: "subscribed ticker XBT/USD".to_owned()
+            })
+        );
+        assert!(parse_message(r#"{"foo":"bar"}"#).is_err());
+        assert!(parse_message("not json").is_err());
+    }
+
+    #[test]
+    fn test_parse_ticker() {
+        let json = r#"[340, {
+            "a": ["5525.40000", 1, "1.000"], "b": ["5525.10000", 1, "1.000"],
+            "c": ["5525.10000", "0.00398963"], "v": ["2634.11501494", "3591.17907851"],
+            "p": ["5631.44067", "5653.78939"], "t": [11493, 16267],
+            "l": ["5505.00000", "5505.00000"], "h": ["5783.00000", "5783.00000"],
+            "o": ["5760.70000", "5763.40000"]
+        }, "ticker", "XBT/USD"]"#;
+
+        let MarketData::Ticker { pair, ticker } = parse_message(json).unwrap() else {
+            panic!("ticker expected");
+        };
+        assert_eq!(pair, "XBT/USD");
+        assert_eq!(ticker.a, vec!["5525.40000", "1", "1.000"]);
+        assert_eq!(ticker.t, vec![11493, 16267]);
+        assert_eq!(ticker.o, "5760.70000");
+        assert!((ticker.last().unwrap() - 5525.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_parse_trade_spread_ohlc() {
+        let json = r#"[0, [["5541.20000", "0.15850568", "1534614057.321597", "s", "l", ""]],
+            "trade", "XBT/USD"]"#;
+        let MarketData::Trades { trades, .. } = parse_message(json).unwrap() else {
+            panic!("trades expected");
+        };
+        assert_eq!(trades[0].price, "5541.20000");
+        assert!(!trades[0].is_buy());
+        assert!((trades[0].time - 1_534_614_057.321_597).abs() < 1e-3);
+
+        let json = r#"[0, ["5698.40000", "5700.00000", "1542057299.545897", "1.01234567",
+            "0.98765432"], "spread", "XBT/USD"]"#;
+        let MarketData::Spread { spread, .. } = parse_message(json).unwrap() else {
+            panic!("spread expected");
+        };
+        assert_eq!(spread.time, 1_542_057_299);
+        assert!((spread.spread().unwrap() - 1.6).abs() < 1e-6);
+
+        let json = r#"[42, ["1542057314.748456", "1542057360.435743", "3586.70000",
+            "3586.70000", "3586.60000", "3586.60000", "3586.68894", "0.03373000", 2],
+            "ohlc-5", "XBT/USD"]"#;
+        let MarketData::Ohlc {
+            interval, candle, ..
+        } = parse_message(json).unwrap()
+        else {
+            panic!("ohlc expected");
+        };
+        assert_eq!(interval, 5);
+        assert_eq!(candle.time, 1_542_057_060);
+        assert_eq!(candle.count, 2);
+        assert!((candle.vwap - 3586.68894).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_parse_book() {
+        let json = r#"[0, {
+            "as": [["5541.30000", "2.50700000", "1534614248.123678"]],
+            "bs": [["5541.20000", "1.52900000", "1534614248.765567"]]
+        }, "book-10", "XBT/USD"]"#;
+        let MarketData::Book { depth, update, .. } = parse_message(json).unwrap() else {
+            panic!("book expected");
+        };
+        assert_eq!(depth, 10);
+        assert!(update.snapshot);
+        assert_eq!(
+            update.asks,
+            vec![OrderBookEntry(
+                "5541.30000".to_owned(),
+                "2.50700000".to_owned(),
+                1_534_614_248
+            )]
+        );
+        assert_eq!(update.checksum, None);
+
+        let json = r#"[1234,
+            {"a": [["5541.30000", "0.00000000", "1534614335.345903", "r"]]},
+            {"b": [["5541.10000", "0.40100000", "1534614335.345903"]], "c": "974942666"},
+            "book-10", "XBT/USD"]"#;
+        let MarketData::Book { update, .. } = parse_message(json).unwrap() else {
+            panic!("book expected");
+        };
+        assert!(!update.snapshot);
+        assert_eq!(update.asks.len(), 1);
+        assert_eq!(update.bids.len(), 1);
+        assert_eq!(update.checksum, Some(974_942_666));
+    }
+}
@@ -0,0 +1,320 @@
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+use crate::kraken::ws_client::{MarketDataAPI, StreamError};
+use crate::kraken::ws_messages::{MarketData, Subscription};
+
+// pause between two connection attempts
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamCommand {
+    Subscribe(Subscription),
+    Unsubscribe(Subscription),
+}
+
+// Updates posted back by the stream worker.
+#[derive(Debug)]
+pub enum StreamUpdate {
+    Connected,
+    Disconnected { reason: String },
+    // a message which could not be parsed, the connection is still up
+    Invalid { reason: String },
+    Data(Box<MarketData>),
+}
+
+// The subscriptions are kept so that they are restored after a reconnection.
+#[derive(Default)]
+struct Subscriptions {
+    active: Vec<Subscription>,
+    connected: bool,
+}
+
+impl Subscriptions {
+    fn handle_command(
+        &mut self,
+        api: &mut dyn MarketDataAPI,
+        command: StreamCommand,
+    ) -> Result<(), StreamError> {
+        match command {
+            StreamCommand::Subscribe(subscription) => {
+                if self.active.contains(&subscription) {
+                    return Ok(());
+                }
+                self.active.push(subscription.clone());
+                if self.connected {
+                    api.subscribe(&subscription)?;
+                }
+            }
+            StreamCommand::Unsubscribe(subscription) => {
+                if let Some(index) = self.active.iter().position(|s| *s == subscription) {
+                    self.active.remove(index);
+                    if self.connected {
+                        api.unsubscribe(&subscription)?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn connect(&mut self, api: &mut dyn MarketDataAPI) -> Result<(), StreamError> {
+        api.connect()?;
+        self.connected = true;
+        for subscription in &self.active {
+            api.subscribe(subscription)?;
+        }
+
+        Ok(())
+    }
+
+    fn read(&mut self, api: &mut dyn MarketDataAPI) -> Option<StreamUpdate> {
+        match api.read() {
+            Ok(data) => data.map(|data| StreamUpdate::Data(Box::new(data))),
+            Err(StreamError::InvalidMessage(reason)) => Some(StreamUpdate::Invalid { reason }),
+            Err(e) => Some(self.disconnected(&e)),
+        }
+    }
+
+    fn disconnected(&mut self, e: &StreamError) -> StreamUpdate {
+        self.connected = false;
+        StreamUpdate::Disconnected {
+            reason: e.to_string(),
+        }
+    }
+}
+
+// connects on the first subscription and keeps reading until the commands channel is closed
+fn run(
+    api: &mut dyn MarketDataAPI,
+    commands: &Receiver<StreamCommand>,
+    updates: &Sender<StreamUpdate>,
+) {
+    let mut subscriptions = Subscriptions::default();
+
+    loop {
+        // block while there is nothing to stream
+        let command = if subscriptions.connected || !subscriptions.active.is_empty() {
+            match commands.try_recv() {
+                Ok(command) => Some(command),
+                Err(TryRecvError::Empty) => None,
+                Err(TryRecvError::Disconnected) => return,
+            }
+        } else {
+            match commands.recv() {
+                Ok(command) => Some(command),
+                Err(_) => return,
+            }
+        };
+
+        let update = if let Some(command) = command {
+            subscriptions
+                .handle_command(api, command)
+                .err()
+                .map(|e| subscriptions.disconnected(&e))
+        } else if !subscriptions.connected {
+            match subscriptions.connect(api) {
+                Ok(()) => Some(StreamUpdate::Connected),
+                Err(e) => {
+                    let update = subscriptions.disconnected(&e);
+                    if updates.send(update).is_err() {
+                        return;
+                    }
+                    thread::sleep(RECONNECT_DELAY);
+                    continue;
+                }
+            }
+        } else {
+            subscriptions.read(api)
+        };
+
+        if let Some(update) = update {
+            if updates.send(update).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+enum Channel {
+    Thread {
+        commands: Sender<StreamCommand>,
+        updates: Receiver<StreamUpdate>,
+    },
+    #[cfg(test)]
+    Inline {
+        api: Box<dyn MarketDataAPI + Send>,
+        subscriptions: Subscriptions,
+    },
+}
+
+// The StreamWorker owns the MarketDataAPI and reads the websocket on its own thread, like
+// the Worker does for the RestAPI.
+pub struct StreamWorker {
+    channel: Channel,
+}
+
+impl StreamWorker {
+    pub fn spawn(mut api: Box<dyn MarketDataAPI + Send>) -> StreamWorker {
+        let (commands, commands_rx) = mpsc::channel::<StreamCommand>();
+        let (updates_tx, updates) = mpsc::channel::<StreamUpdate>();
+
+        thread::Builder::new()
+            .name(String::from("kraken-stream"))
+            .spawn(move || run(api.as_mut(), &commands_rx, &updates_tx))
+            .expect("failed to spawn the kraken stream thread");
+
+        StreamWorker {
+            channel: Channel::Thread { commands, updates },
+        }
+    }
+
+    // executes the commands and the reads on the caller thread, already connected.
+    #[cfg(test)]
+    pub fn inline(api: Box<dyn MarketDataAPI + Send>) -> StreamWorker {
+        StreamWorker {
+            channel: Channel::Inline {
+                api,
+                subscriptions: Subscriptions {
+                    active: vec![],
+                    connected: true,
+                },
+            },
+        }
+    }
+
+    // returns back the command if the worker is not running anymore.
+    pub fn send(&mut self, command: StreamCommand) -> Result<(), StreamCommand> {
+        match &mut self.channel {
+            Channel::Thread { commands, .. } => commands.send(command).map_err(|e| e.0),
+            #[cfg(test)]
+            Channel::Inline { api, subscriptions } => {
+                let _ = subscriptions.handle_command(api.as_mut(), command);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn try_recv(&mut self) -> Option<StreamUpdate> {
+        match &mut self.channel {
+            Channel::Thread { updates, .. } => updates.try_recv().ok(),
+            #[cfg(test)]
+            Channel::Inline { api, subscriptions } => subscriptions.read(api.as_mut()),
+        }
+    }
+}
+
+// tests
+#[cfg(test)]
+mod tests {
+    use mockall::predicate::eq;
+    use mockall::Sequence;
+
+    use super::*;
+    use crate::kraken::ws_client::MockMarketDataAPI;
+    use crate::kraken::ws_messages::Feed;
+
+    fn recv(worker: &mut StreamWorker) -> StreamUpdate {
+        for _ in 0..200 {
+            if let Some(update) = worker.try_recv() {
+                return update;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("no update received");
+    }
+
+    #[test]
+    fn test_subscriptions() {
+        let ticker = Subscription::new("XBT/USD", Feed::Ticker);
+        let spread = Subscription::new("XBT/USD", Feed::Spread);
+
+        let mut mock_api = MockMarketDataAPI::new();
+        mock_api.expect_connect().never();
+        mock_api
+            .expect_subscribe()
+            .with(eq(ticker.clone()))
+            .once()
+            .returning(|_| Ok(()));
+        mock_api
+            .expect_unsubscribe()
+            .with(eq(ticker.clone()))
+            .once()
+            .returning(|_| Ok(()));
+
+        let mut subscriptions = Subscriptions::default();
+        // not connected yet, only recorded
+        subscriptions
+            .handle_command(&mut mock_api, StreamCommand::Subscribe(spread.clone()))
+            .unwrap();
+        subscriptions.connected = true;
+        for _ in 0..2 {
+            subscriptions
+                .handle_command(&mut mock_api, StreamCommand::Subscribe(ticker.clone()))
+                .unwrap();
+        }
+        subscriptions
+            .handle_command(&mut mock_api, StreamCommand::Unsubscribe(ticker))
+            .unwrap();
+
+        assert_eq!(subscriptions.active, vec![spread]);
+    }
+
+    #[test]
+    fn test_worker_thread_reconnects() {
+        let ticker = Subscription::new("XBT/USD", Feed::Ticker);
+
+        let mut sequence = Sequence::new();
+        let mut mock_api = Box::new(MockMarketDataAPI::new());
+        mock_api
+            .expect_connect()
+            .once()
+            .in_sequence(&mut sequence)
+            .returning(|| Ok(()));
+        mock_api
+            .expect_subscribe()
+            .with(eq(ticker.clone()))
+            .once()
+            .in_sequence(&mut sequence)
+            .returning(|_| Ok(()));
+        mock_api
+            .expect_read()
+            .once()
+            .in_sequence(&mut sequence)
+            .returning(|| Ok(Some(MarketData::Heartbeat)));
+        mock_api
+            .expect_read()
+            .once()
+            .in_sequence(&mut sequence)
+            .returning(|| Err(StreamError::Closed));
+        mock_api
+            .expect_connect()
+            .once()
+            .in_sequence(&mut sequence)
+            .returning(|| Ok(()));
+        // the subscription is restored
+        mock_api
+            .expect_subscribe()
+            .with(eq(ticker.clone()))
+            .once()
+            .in_sequence(&mut sequence)
+            .returning(|_| Ok(()));
+        mock_api.expect_read().returning(|| Ok(None));
+
+        let mut worker = StreamWorker::spawn(mock_api);
+        assert!(worker.send(StreamCommand::Subscribe(ticker)).is_ok());
+
+        assert!(matches!(recv(&mut worker), StreamUpdate::Connected));
+        assert!(matches!(
+            recv(&mut worker),
+            StreamUpdate::Data(data) if *data == MarketData::Heartbeat
+        ));
+        assert!(matches!(
+            recv(&mut worker),
+            StreamUpdate::Disconnected { reason } if reason == "connection closed by the server"
+        ));
+        assert!(matches!(recv(&mut worker), StreamUpdate::Connected));
+    }
+}
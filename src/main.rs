@@ -2,22 +2,31 @@
 #![allow(clippy::missing_errors_doc)]
 #![allow(clippy::must_use_candidate)]
 mod app;
+mod config;
+mod credentials;
 mod kraken;
+#[cfg(test)]
+mod mock_server;
 mod stm;
+mod storage;
 mod terminal;
 mod ui;
 
 use std::io;
+use std::time::{Duration, Instant};
 
-use crossterm::event::{self, Event, KeyCode};
+use crossterm::event::{self, Event, KeyCode, KeyModifiers};
 use log::{error, info};
 use tui::{backend::Backend, Terminal};
 
 use crate::app::Context;
+use crate::config::Config;
+use crate::credentials::load_credentials;
 use crate::stm::{events, stm_main::MainStm, States};
 
 const APP_ID: &str = "kraken";
 const APP_VERSION: &str = "0.0.1+";
+const POLL_RATE: Duration = Duration::from_millis(50);
 
 /*
  * The role of main function is just to initialize the terminal, the app context and
@@ -29,6 +38,10 @@ const APP_VERSION: &str = "0.0.1+";
  * * See below files for respective function
  *
  * __app.rs__: Defines the AppContext and the AppModel.
+ * __config.rs__: Defines the Config loaded from the config file, the environment and the command line arguments.
+ * __credentials.rs__: Defines the loading of the Kraken API key and secret.
+ * __storage.rs__: Defines the persistence of the user data in the config directory.
+ * __mock_server.rs__: Defines the mock of the Kraken REST API serving the fixtures, shared with the kraken_mock binary.
  *
  * __stm.rs__: Defines the State trait which each state has to implement.
  * __stm/event.rs__: Defines the events consumed by the state machine.
@@ -37,14 +50,31 @@ const APP_VERSION: &str = "0.0.1+";
  * __ui.rs__: Defines the ui compoents as functions called by the current state.
  *
  * __kraken/client.rs__: Defines the client proxy for comunintation with the Kraken API enpoint.
+ * __kraken/retry.rs__: Defines the retry and rate limit layer around the client.
+ * __kraken/worker.rs__: Defines the worker thread executing the requests to the Kraken API enpoint.
+ * __kraken/ws_client.rs__: Defines the client for the market data pushed by the Kraken websocket API.
+ * __kraken/ws_worker.rs__: Defines the worker thread reading the Kraken websocket API.
  */
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // load the configuration before touching the terminal so that the errors are readable
+    let config = Config::load()?;
+    // a credentials file readable by other users is refused
+    let credentials = load_credentials(
+        |name| std::env::var(name).ok(),
+        config.credentials_path().as_deref(),
+    )?;
+
     // initialize terminal state
     let mut xterm = terminal::XTerminal::new()?;
 
     // initialize app context and state machine
-    let mut ctx = Context::new(String::from(APP_ID), String::from(APP_VERSION));
+    let mut ctx = Context::new(
+        String::from(APP_ID),
+        String::from(APP_VERSION),
+        config,
+        credentials,
+    );
     let mut stm = MainStm::new("stm", true);
     let res = run_app(&mut xterm.terminal, &mut ctx, &mut stm, true);
 
@@ -70,6 +100,7 @@ fn run_app<B: Backend>(
     // reset the state machine
     stm.switch_state(States::Home, ctx);
 
+    let mut last_tick = Instant::now();
     loop {
         terminal.draw(|f| stm.draw(f, ctx))?;
 
@@ -77,10 +108,31 @@ fn run_app<B: Backend>(
             return Ok(());
         }
 
+        // forward the responses completed by the kraken worker in the meantime
+        for event in ctx.poll_responses() {
+            stm.on_event(event, ctx);
+        }
+
+        // emit a tick every tick_rate so that the states can refresh their data
+        if last_tick.elapsed() >= ctx.config.tick_rate {
+            stm.on_event(events::Event::Tick, ctx);
+            last_tick = Instant::now();
+        }
+
+        // wake up every POLL_RATE so that the responses are drawn without waiting a key
+        if !event::poll(POLL_RATE)? {
+            continue;
+        }
+
         if let Event::Key(key) = event::read()? {
+            let captured = stm.captures_keys();
+            if key.code == KeyCode::Char('p') && key.modifiers.contains(KeyModifiers::CONTROL) {
+                stm.on_event(events::Event::OpenFinder, ctx);
+                continue;
+            }
             stm.on_event(events::Event::Key { key_code: key.code }, ctx);
 
-            if let KeyCode::Char('q') = key.code {
+            if !captured && key.code == KeyCode::Char('q') {
                 return Ok(());
             }
         }
@@ -89,10 +141,14 @@ fn run_app<B: Backend>(
 
 #[cfg(test)]
 mod tests {
-    use krakenrs::AssetsResponse;
+    use krakenrs::AssetPairsResponse;
+
+    use crate::kraken::messages::AssetsResponse;
     use tui::{backend::TestBackend, Terminal};
 
     use crate::kraken::client::MockRestAPI;
+    use crate::kraken::worker::Request;
+    use crate::mock_server::{default_fixtures_dir, MockServer};
 
     use super::*;
 
@@ -116,7 +172,11 @@ mod tests {
         mock_client
             .expect_list_assets()
             .once()
-            .returning(|| Some(AssetsResponse::new()));
+            .returning(|| Ok(AssetsResponse::new()));
+        mock_client
+            .expect_list_asset_pairs()
+            .once()
+            .returning(|| Ok(AssetPairsResponse::new()));
         let mut ctx = Context::new_for_testing(mock_client);
         let mut stm = MainStm::new("stm", false);
 
@@ -125,4 +185,44 @@ mod tests {
         assert!(result.is_ok());
         assert_eq!(stm.current_st, States::Home);
     }
+
+    // the whole app against the mock server, without network
+    #[test]
+    fn test_run_app_with_mock_server() {
+        let server = MockServer::bind(0, &default_fixtures_dir()).unwrap();
+        let config = Config {
+            api_url: server.url().unwrap(),
+            ..Config::default()
+        };
+        server.spawn();
+
+        let mut ctx = Context::new(
+            String::from(APP_ID),
+            String::from(APP_VERSION),
+            config,
+            None,
+        );
+        let mut stm = MainStm::new("stm", false);
+        stm.switch_state(States::Home, &mut ctx);
+        ctx.request(Request::ListAssetPairs);
+        ctx.request(Request::Ticker {
+            asset_pair: "XXBTZUSD".to_owned(),
+        });
+
+        for _ in 0..500 {
+            for event in ctx.poll_responses() {
+                stm.on_event(event, &mut ctx);
+            }
+            if ctx.model.pending_requests == 0 {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        assert_eq!(ctx.model.pending_requests, 0);
+        assert_eq!(ctx.model.assets.len(), 4);
+        assert_eq!(ctx.model.asset_pairs.len(), 3);
+        assert_eq!(ctx.model.tickers["XXBTZUSD"].last(), Some(30005.0));
+        assert_eq!(ctx.model.status_error(), None);
+    }
 }
@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use serde_json::{json, Value};
+
+// the public endpoints served, one fixture file for each (e.g. fixtures/Ticker.json)
+pub const METHODS: [&str; 6] = ["Assets", "AssetPairs", "Ticker", "OHLC", "Depth", "Trades"];
+
+// Serves canned Kraken REST responses on localhost, the fixtures are the json bodies
+// returned by the real API. Point kraken_tui to it with --api-url.
+pub struct MockServer {
+    listener: TcpListener,
+    fixtures: HashMap<String, Value>,
+}
+
+impl MockServer {
+    // port 0 picks a free port
+    pub fn bind(port: u16, fixtures_dir: &Path) -> io::Result<MockServer> {
+        let mut fixtures = HashMap::new();
+        for method in METHODS {
+            let path = fixtures_dir.join(format!("{method}.json"));
+            let text = fs::read_to_string(&path)?;
+            let fixture = serde_json::from_str(&text).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{} {e}", path.display()),
+                )
+            })?;
+            fixtures.insert(method.to_owned(), fixture);
+        }
+
+        Ok(MockServer {
+            listener: TcpListener::bind(("127.0.0.1", port))?,
+            fixtures,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn url(&self) -> io::Result<String> {
+        Ok(format!("http://{}", self.local_addr()?))
+    }
+
+    // one request for each connection, answered in order
+    pub fn serve(&self) {
+        for stream in self.listener.incoming().flatten() {
+            if let Err(e) = self.handle(stream) {
+                eprintln!("[mock] {e}");
+            }
+        }
+    }
+
+    pub fn spawn(self) -> thread::JoinHandle<()> {
+        thread::spawn(move || self.serve())
+    }
+
+    fn handle(&self, stream: TcpStream) -> io::Result<()> {
+        let mut reader = BufReader::new(stream);
+
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        let path = request_line
+            .split_whitespace()
+            .nth(1)
+            .unwrap_or_default()
+            .to_owned();
+
+        let mut content_length = 0;
+        loop {
+            let mut header = String::new();
+            if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap_or(0);
+                }
+            }
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body)?;
+
+        let response = self.respond(&path, &String::from_utf8_lossy(&body));
+        let text = response.to_string();
+        write!(
+            reader.get_mut(),
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{text}",
+            text.len()
+        )?;
+        reader.get_mut().flush()
+    }
+
+    // the kraken errors are returned with status 200, like the real API
+    fn respond(&self, path: &str, body: &str) -> Value {
+        let Some(method) = path.strip_prefix("/0/public/") else {
+            return json!({ "error": ["EGeneral:Permission denied"] });
+        };
+        let Some(fixture) = self.fixtures.get(method) else {
+            return json!({ "error": ["EQuery:Unknown method"] });
+        };
+
+        match pair_param(body) {
+            Some(pair) => filter_pair(fixture, &pair),
+            None => fixture.clone(),
+        }
+    }
+}
+
+fn pair_param(body: &str) -> Option<String> {
+    body.split('&')
+        .find_map(|param| param.strip_prefix("pair="))
+        .map(ToOwned::to_owned)
+}
+
+// keeps only the requested pair, and the "last" cursor of OHLC and Trades
+fn filter_pair(fixture: &Value, pair: &str) -> Value {
+    let Some(result) = fixture["result"].as_object() else {
+        return fixture.clone();
+    };
+    if !result.contains_key(pair) {
+        return json!({ "error": ["EQuery:Unknown asset pair"] });
+    }
+
+    let result = result
+        .iter()
+        .filter(|(key, _)| *key == pair || *key == "last")
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect::<serde_json::Map<_, _>>();
+    json!({ "error": [], "result": result })
+}
+
+// the fixtures shipped with the sources
+pub fn default_fixtures_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures")
+}
+
+// tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_respond() {
+        let server = MockServer::bind(0, &default_fixtures_dir()).unwrap();
+
+        let response = server.respond("/0/public/Assets", "");
+        assert_eq!(response["result"].as_object().unwrap().len(), 4);
+
+        let response = server.respond("/0/public/OHLC", "pair=XXBTZUSD&interval=1");
+        let result = response["result"].as_object().unwrap();
+        assert_eq!(result.keys().collect::<Vec<_>>(), vec!["XXBTZUSD", "last"]);
+
+        let response = server.respond("/0/public/Ticker", "pair=XETHZEUR");
+        assert_eq!(response["error"][0], "EQuery:Unknown asset pair");
+
+        let response = server.respond("/0/public/Spread", "pair=XXBTZUSD");
+        assert_eq!(response["error"][0], "EQuery:Unknown method");
+
+        let response = server.respond("/0/private/Balance", "nonce=1");
+        assert_eq!(response["error"][0], "EGeneral:Permission denied");
+    }
+
+    #[test]
+    fn test_missing_fixtures() {
+        let dir = std::env::temp_dir().join("kraken_tui_no_fixtures");
+        assert!(MockServer::bind(0, &dir).is_err());
+    }
+}
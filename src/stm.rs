@@ -14,6 +14,15 @@ pub enum States {
     Home,
     Search,
     Help,
+    Finder,
+    OrderBook,
+    Trades,
+    Chart,
+    Spreads,
+    Balances,
+    OpenOrders,
+    History,
+    OrderEntry,
 }
 
 trait State {
@@ -21,6 +30,16 @@ trait State {
     #[allow(unused_variables)]
     fn on_enter_once(&mut self, ctx: &mut Context) {}
 
+    // this method is called every time the state machine transitioning to this state,
+    // except when coming back from an overlay state like Help or Debug.
+    #[allow(unused_variables)]
+    fn on_enter(&mut self, ctx: &mut Context) {}
+
+    // this method is called every time the state machine leaves this state, except when
+    // it opens an overlay state like Help or Debug.
+    #[allow(unused_variables)]
+    fn on_exit(&mut self, ctx: &mut Context) {}
+
     // this method returns an Option<States>. If it is not NONE the optional States value
     // is the new state which the State Machine will transition
     #[allow(unused_variables)]
@@ -28,6 +47,15 @@ trait State {
         None
     }
 
+    // this method is called periodically, every Config::tick_rate, whatever the current state is.
+    #[allow(unused_variables)]
+    fn on_tick(&mut self, ctx: &mut Context) {}
+
+    // this method is called for every response posted back by the kraken worker, whatever
+    // the current state is. The payload of the response is already stored in the Model.
+    #[allow(unused_variables)]
+    fn on_response(&mut self, event: &Event, ctx: &mut Context) {}
+
     fn ui<B: Backend>(&self, f: &mut Frame<B>, _ctx: &mut Context);
 
     fn help_text(&self) -> &str {
@@ -37,10 +65,51 @@ trait State {
     }
 }
 
+// name of the asset pair selected in Home shown in the titles of the market data states,
+// its wsname (e.g. XBT/USD) when the asset pairs are loaded, its kraken key otherwise.
+fn selected_pair_name(ctx: &Context, asset_pair: Option<&String>) -> String {
+    asset_pair
+        .map(|key| ctx.model.wsname(key).unwrap_or_else(|| key.clone()))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kraken::client::MockRestAPI;
+
+    use super::*;
+
+    #[test]
+    fn test_selected_pair_name() {
+        let mut ctx = Context::new_for_testing(Box::new(MockRestAPI::new()));
+        let key = "XXBTZUSD".to_owned();
+
+        assert_eq!(selected_pair_name(&ctx, None), "");
+        assert_eq!(selected_pair_name(&ctx, Some(&key)), "XXBTZUSD");
+
+        ctx.model.asset_pairs = serde_json::from_str(
+            r#"{"XXBTZUSD": {"alt_name": "XBTUSD", "wsname": "XBT/USD", "aclass_base": "currency",
+                "base": "XXBT", "aclass_quote": "currency", "quote": "ZUSD", "pair_decimals": 1,
+                "lot_decimals": 8, "lot_multiplier": 1, "fees": [], "ordermin": "0.0001"}}"#,
+        )
+        .unwrap();
+        assert_eq!(selected_pair_name(&ctx, Some(&key)), "XBT/USD");
+    }
+}
+
 pub(crate) mod events;
+mod state_balances;
+mod state_chart;
 mod state_debug;
+mod state_finder;
 mod state_help;
+mod state_history;
 mod state_home;
+mod state_open_orders;
+mod state_order_book;
+mod state_order_entry;
 mod state_search;
+mod state_spreads;
+mod state_trades;
 mod state_unknown;
 pub(crate) mod stm_main;
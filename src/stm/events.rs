@@ -4,8 +4,93 @@ use crossterm::event::KeyCode;
 #[derive(Debug, Clone)]
 pub enum Event {
     Reset,
-    Key { key_code: KeyCode },
-    Debug { message: String },
+    Key {
+        key_code: KeyCode,
+    },
+    Debug {
+        message: String,
+    },
+    Tick,
+    // Ctrl-P, the keys are posted without modifiers
+    OpenFinder,
+
+    // posted when the kraken worker completes a request, the payload is already stored in the Model.
+    Connected {
+        result: Result<(), String>,
+    },
+    Assets {
+        loaded: bool,
+    },
+    AssetPairs {
+        loaded: bool,
+    },
+    Ticker {
+        asset_pair: String,
+        loaded: bool,
+    },
+    OrderBook {
+        asset_pair: String,
+        loaded: bool,
+    },
+    RecentTrades {
+        asset_pair: String,
+        loaded: bool,
+    },
+    Ohlc {
+        asset_pair: String,
+        interval: u32,
+        loaded: bool,
+    },
+    Spreads {
+        asset_pair: String,
+        loaded: bool,
+    },
+    Balances {
+        loaded: bool,
+    },
+    OpenOrders {
+        loaded: bool,
+    },
+    OrderCancelled {
+        txid: String,
+        result: Result<(), String>,
+    },
+    ClosedOrders {
+        generation: u64,
+        offset: usize,
+        loaded: bool,
+    },
+    TradesHistory {
+        generation: u64,
+        offset: usize,
+        loaded: bool,
+    },
+    OrderAdded {
+        validate: bool,
+        result: Result<(), String>,
+    },
+}
+
+impl Event {
+    pub fn is_response(&self) -> bool {
+        matches!(
+            self,
+            Event::Connected { .. }
+                | Event::Assets { .. }
+                | Event::AssetPairs { .. }
+                | Event::Ticker { .. }
+                | Event::OrderBook { .. }
+                | Event::RecentTrades { .. }
+                | Event::Ohlc { .. }
+                | Event::Spreads { .. }
+                | Event::Balances { .. }
+                | Event::OpenOrders { .. }
+                | Event::OrderCancelled { .. }
+                | Event::ClosedOrders { .. }
+                | Event::TradesHistory { .. }
+                | Event::OrderAdded { .. }
+        )
+    }
 }
 
 // tests
@@ -52,6 +137,10 @@ mod tests {
             message: String::from("INFO"),
         };
         assert!(matches!(event_debug, Event::Debug { message: _ }));
+        assert!(!event_debug.is_response());
+
+        let event_assets = Event::Assets { loaded: true };
+        assert!(event_assets.is_response());
 
         Ok(())
     }
@@ -0,0 +1,490 @@
+use std::cmp::Ordering;
+
+use crossterm::event::KeyCode;
+use tui::{backend::Backend, Frame};
+
+use crate::app::{Context, Model};
+use crate::kraken::worker::Request;
+use crate::stm::{events::Event, State, States};
+use crate::ui::{
+    core::{centered_rect, draw_box},
+    list_stateful_widget::draw_stateful_list,
+};
+
+// the currencies the balances can be valued in, cycled with 'c'
+const QUOTES: [&str; 3] = ["ZUSD", "ZEUR", "XXBT"];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+enum SortBy {
+    #[default]
+    Value,
+    Name,
+}
+
+// asset name, amount held and its value in the quote currency if a price is known
+struct BalanceRow {
+    name: String,
+    amount: f64,
+    value: Option<f64>,
+}
+
+// Account Balances State
+#[derive(Default)]
+pub struct BalancesState {
+    quote_index: usize,
+    sort_by: SortBy,
+    // the responses are ignored while the balances are not shown
+    visible: bool,
+    // pairs of the last ticker request, used to value the balances
+    ticker_pairs: Vec<String>,
+}
+
+impl BalancesState {
+    fn quote(&self) -> &'static str {
+        QUOTES[self.quote_index]
+    }
+
+    fn refresh(ctx: &mut Context) {
+        if ctx.model.authenticated {
+            ctx.request(Request::Balances);
+            // needed to find the pairs used to value the assets
+            if ctx.model.asset_pairs.is_empty() {
+                ctx.request(Request::ListAssetPairs);
+            }
+        }
+    }
+
+    // one ticker request for all the pairs needed to value the balances
+    fn refresh_tickers(&mut self, ctx: &mut Context) {
+        let mut pairs = ctx
+            .model
+            .balances
+            .keys()
+            .filter_map(|asset| BalancesState::find_pair(&ctx.model, asset, self.quote()))
+            .map(|(key, _)| key)
+            .collect::<Vec<_>>();
+        pairs.sort();
+
+        if !pairs.is_empty() {
+            ctx.request(Request::Ticker {
+                asset_pair: pairs.join(","),
+            });
+        }
+        self.ticker_pairs = pairs;
+    }
+
+    // the ticker requested by refresh_tickers or a streamed ticker of one of its pairs
+    fn values_with(&self, asset_pair: &str) -> bool {
+        asset_pair.split(',').any(|pair| {
+            self.ticker_pairs
+                .iter()
+                .any(|ticker_pair| ticker_pair == pair)
+        })
+    }
+
+    // kraken key of the pair trading the asset against the quote, true if the pair is
+    // quoted the other way around (e.g. XXBTZUSD to value ZUSD in XXBT). The dark pool
+    // pairs (e.g. XXBTZUSD.d) are skipped.
+    fn find_pair(model: &Model, asset: &str, quote: &str) -> Option<(String, bool)> {
+        if asset == quote {
+            return None;
+        }
+
+        let find = |base: &str, quote: &str| {
+            model
+                .asset_pairs
+                .iter()
+                .filter(|(key, pair)| {
+                    pair.base == base && pair.quote == quote && !key.contains('.')
+                })
+                .map(|(key, _)| key.clone())
+                .min()
+        };
+
+        find(asset, quote)
+            .map(|key| (key, false))
+            .or_else(|| find(quote, asset).map(|key| (key, true)))
+    }
+
+    fn value(model: &Model, asset: &str, amount: f64, quote: &str) -> Option<f64> {
+        if asset == quote {
+            return Some(amount);
+        }
+
+        let (key, inverted) = BalancesState::find_pair(model, asset, quote)?;
+        let last = model.tickers.get(&key)?.last()?;
+        if inverted {
+            (last > 0.0).then(|| amount / last)
+        } else {
+            Some(amount * last)
+        }
+    }
+
+    fn rows(&self, model: &Model) -> Vec<BalanceRow> {
+        let mut rows = model
+            .balances
+            .iter()
+            .filter_map(|(asset, amount)| {
+                let amount = amount.parse::<f64>().ok().filter(|amount| *amount > 0.0)?;
+                Some(BalanceRow {
+                    name: model
+                        .assets
+                        .get(asset)
+                        .map_or_else(|| asset.clone(), |info| info.altname.clone()),
+                    amount,
+                    value: BalancesState::value(model, asset, amount, self.quote()),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        match self.sort_by {
+            // highest value first, the assets without a price at the end
+            SortBy::Value => rows.sort_by(|a, b| match (a.value, b.value) {
+                (Some(a), Some(b)) => b.partial_cmp(&a).unwrap_or(Ordering::Equal),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => a.name.cmp(&b.name),
+            }),
+            SortBy::Name => rows.sort_by(|a, b| a.name.cmp(&b.name)),
+        }
+
+        rows
+    }
+
+    fn show_balances(&self, ctx: &mut Context) {
+        ctx.model.balances_stateful.clear();
+
+        if !ctx.model.authenticated {
+            ctx.model
+                .balances_stateful
+                .push("API credentials required, see the README".to_owned());
+            return;
+        }
+
+        let lines = self
+            .rows(&ctx.model)
+            .iter()
+            .map(|row| {
+                let value = match row.value {
+                    Some(value) => format!("{value:.2}"),
+                    None => "-".to_owned(),
+                };
+                format!("{:<10} {:>20.8} {:>16}", row.name, row.amount, value)
+            })
+            .collect::<Vec<_>>();
+
+        for line in lines {
+            ctx.model.balances_stateful.push(line);
+        }
+    }
+
+    fn title(&self, ctx: &Context) -> String {
+        let quote = ctx
+            .model
+            .assets
+            .get(self.quote())
+            .map_or(self.quote(), |info| info.altname.as_str());
+        let total = self
+            .rows(&ctx.model)
+            .iter()
+            .filter_map(|row| row.value)
+            .sum::<f64>();
+        let sort_by = match self.sort_by {
+            SortBy::Value => "value",
+            SortBy::Name => "name",
+        };
+
+        format!(" Balances total:{total:.2} {quote} sort:{sort_by} ")
+    }
+}
+
+impl State for BalancesState {
+    fn on_enter(&mut self, ctx: &mut Context) {
+        self.visible = true;
+        self.show_balances(ctx);
+        BalancesState::refresh(ctx);
+    }
+
+    fn on_exit(&mut self, _ctx: &mut Context) {
+        self.visible = false;
+    }
+
+    fn on_response(&mut self, event: &Event, ctx: &mut Context) {
+        if !self.visible {
+            return;
+        }
+
+        match event {
+            Event::Balances { loaded } => {
+                if !loaded {
+                    ctx.debug("[BalancesS] on_response kraken_api balances failed.".to_owned());
+                }
+                self.refresh_tickers(ctx);
+                self.show_balances(ctx);
+            }
+            Event::AssetPairs { loaded: true } => self.refresh_tickers(ctx),
+            Event::Ticker { asset_pair, .. } if self.values_with(asset_pair) => {
+                self.show_balances(ctx);
+            }
+            _ => {}
+        }
+    }
+
+    fn on_event(&mut self, event: Event, ctx: &mut Context) -> Option<States> {
+        match event {
+            Event::Key {
+                key_code: KeyCode::Esc,
+            } => Some(States::Home),
+            Event::Key {
+                key_code: KeyCode::Char('r'),
+            } => {
+                BalancesState::refresh(ctx);
+                None
+            }
+            Event::Key {
+                key_code: KeyCode::Char('c'),
+            } => {
+                self.quote_index = (self.quote_index + 1) % QUOTES.len();
+                self.refresh_tickers(ctx);
+                self.show_balances(ctx);
+                None
+            }
+            Event::Key {
+                key_code: KeyCode::Char('s'),
+            } => {
+                self.sort_by = match self.sort_by {
+                    SortBy::Value => SortBy::Name,
+                    SortBy::Name => SortBy::Value,
+                };
+                self.show_balances(ctx);
+                None
+            }
+            Event::Key {
+                key_code: KeyCode::Down,
+            } => {
+                ctx.model.balances_stateful.next();
+                None
+            }
+            Event::Key {
+                key_code: KeyCode::Up,
+            } => {
+                ctx.model.balances_stateful.previous();
+                None
+            }
+            Event::Key {
+                key_code: KeyCode::Left,
+            } => {
+                ctx.model.balances_stateful.unselect();
+                None
+            }
+            _ => {
+                ctx.debug(format!("[BalancesS] on_event {event:?} not match"));
+                None
+            }
+        }
+    }
+
+    fn ui<B: Backend>(&self, f: &mut Frame<B>, ctx: &mut Context) {
+        let size = f.size();
+        draw_box(f, size, &self.title(ctx));
+
+        let bbox = centered_rect(95, 90, size);
+        draw_stateful_list(
+            f,
+            bbox,
+            " asset amount value ",
+            &mut ctx.model.balances_stateful,
+            false,
+        );
+    }
+
+    fn help_text(&self) -> &str {
+        r##"
+        ESC    -> home
+        UP     -> previous
+        DOWN   -> next
+        LEFT   -> unselect
+        r      -> refresh
+        c      -> change quote currency
+        s      -> sort by value/name
+        D      -> show Debug
+        "##
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::KeyCode;
+    use mockall::predicate::eq;
+    use tui::{backend::TestBackend, buffer::Buffer, Terminal};
+
+    use crate::kraken::client::MockRestAPI;
+    use crate::kraken::error::ClientError;
+    use crate::kraken::messages::{BalanceResponse, TickerInfo, TickerResponse};
+    use crate::stm::events::Event;
+
+    use super::*;
+
+    const ASSET_PAIRS_JSON: &str = r#"{
+      "XXBTZUSD": {
+        "alt_name": "XBTUSD", "wsname": "XBT/USD", "aclass_base": "currency", "base": "XXBT",
+        "aclass_quote": "currency", "quote": "ZUSD", "pair_decimals": 1, "lot_decimals": 8,
+        "lot_multiplier": 1, "fees": [], "ordermin": "0.0001"
+      },
+      "XETHZUSD": {
+        "alt_name": "ETHUSD", "wsname": "ETH/USD", "aclass_base": "currency", "base": "XETH",
+        "aclass_quote": "currency", "quote": "ZUSD", "pair_decimals": 2, "lot_decimals": 8,
+        "lot_multiplier": 1, "fees": [], "ordermin": "0.01"
+      }
+    }"#;
+
+    fn ticker(last: &str) -> TickerInfo {
+        TickerInfo {
+            c: vec![last.to_owned(), "1".to_owned()],
+            ..TickerInfo::default()
+        }
+    }
+
+    #[test]
+    fn test_balances() {
+        let mut mock_client = Box::new(MockRestAPI::new());
+        mock_client.expect_balances().once().returning(|| {
+            let mut balances = BalanceResponse::new();
+            balances.insert("XXBT".to_owned(), "0.5".to_owned());
+            balances.insert("XETH".to_owned(), "2.0".to_owned());
+            balances.insert("ZUSD".to_owned(), "100.0".to_owned());
+            balances.insert("DOT".to_owned(), "10.0".to_owned());
+            balances.insert("XLTC".to_owned(), "0.0".to_owned());
+            Ok(balances)
+        });
+        mock_client
+            .expect_ticker()
+            .with(eq("XETHZUSD,XXBTZUSD"))
+            .once()
+            .returning(|_| {
+                let mut tickers = TickerResponse::new();
+                tickers.insert("XXBTZUSD".to_owned(), ticker("30000.0"));
+                tickers.insert("XETHZUSD".to_owned(), ticker("2000.0"));
+                Ok(tickers)
+            });
+        mock_client
+            .expect_ticker()
+            .with(eq("XXBTZUSD"))
+            .once()
+            .returning(|_| Err(ClientError::NotConnected));
+        let mut ctx = Context::new_for_testing(mock_client);
+        ctx.model.authenticated = true;
+        ctx.model.asset_pairs = serde_json::from_str(ASSET_PAIRS_JSON).unwrap();
+
+        let mut state = BalancesState::default();
+        state.on_enter(&mut ctx);
+        for _ in 0..2 {
+            for event in ctx.poll_responses() {
+                state.on_response(&event, &mut ctx);
+            }
+        }
+
+        assert_eq!(
+            ctx.model.balances_stateful.items,
+            vec![
+                "XXBT                 0.50000000         15000.00",
+                "XETH                 2.00000000          4000.00",
+                "ZUSD               100.00000000           100.00",
+                "DOT                 10.00000000                -",
+            ]
+        );
+        assert_eq!(
+            state.title(&ctx),
+            " Balances total:19100.00 ZUSD sort:value "
+        );
+
+        let event = Event::Key {
+            key_code: KeyCode::Char('s'),
+        };
+        assert_eq!(state.on_event(event, &mut ctx), None);
+        assert!(ctx.model.balances_stateful.items[0].starts_with("DOT"));
+
+        // ZUSD valued in XXBT through the inverted XXBTZUSD pair
+        assert_eq!(
+            BalancesState::value(&ctx.model, "ZUSD", 300.0, "XXBT"),
+            Some(0.01)
+        );
+        state.quote_index = 1;
+        let event = Event::Key {
+            key_code: KeyCode::Char('c'),
+        };
+        assert_eq!(state.on_event(event, &mut ctx), None);
+        assert_eq!(state.quote(), "XXBT");
+
+        let event = Event::Key {
+            key_code: KeyCode::Esc,
+        };
+        assert_eq!(state.on_event(event, &mut ctx), Some(States::Home));
+    }
+
+    #[test]
+    fn test_responses_when_not_shown() {
+        let mut ctx = Context::new_for_testing(Box::new(MockRestAPI::new()));
+        ctx.model.authenticated = true;
+        ctx.model.asset_pairs = serde_json::from_str(ASSET_PAIRS_JSON).unwrap();
+        ctx.model.balances_stateful.items = vec!["XXBT".to_owned()];
+        ctx.model.balances_stateful.next();
+
+        let mut state = BalancesState::default();
+        for event in [
+            Event::AssetPairs { loaded: true },
+            Event::Ticker {
+                asset_pair: "XXBTZUSD".to_owned(),
+                loaded: true,
+            },
+        ] {
+            state.on_response(&event, &mut ctx);
+        }
+
+        assert_eq!(ctx.model.pending_requests, 0);
+        assert_eq!(ctx.model.balances_stateful.items, vec!["XXBT"]);
+        assert_eq!(ctx.model.balances_stateful.state.selected(), Some(0));
+    }
+
+    #[test]
+    fn test_not_authenticated() {
+        let mut ctx = Context::new_for_testing(Box::new(MockRestAPI::new()));
+
+        let mut state = BalancesState::default();
+        state.on_enter(&mut ctx);
+
+        assert_eq!(ctx.model.pending_requests, 0);
+        assert_eq!(ctx.model.balances_stateful.items.len(), 1);
+    }
+
+    #[test]
+    fn test_ui() {
+        let backend = TestBackend::new(7, 4);
+        let mut terminal = Terminal::new(backend).unwrap();
+        let mut ctx = Context::new_for_testing(Box::new(MockRestAPI::new()));
+
+        let state = BalancesState::default();
+
+        terminal
+            .draw(|f| {
+                state.ui(f, &mut ctx);
+            })
+            .unwrap();
+
+        #[rustfmt::skip]
+        let expected = Buffer::with_lines(vec![
+            "┌ ass┐╮",
+            "│    ││",
+            "└────┘│",
+            "╰─────╯"
+            ]);
+
+        terminal.backend().assert_buffer(&expected);
+    }
+
+    #[test]
+    fn test_state_help() {
+        let state = BalancesState::default();
+        assert_eq!(state.help_text().len(), 241);
+    }
+}
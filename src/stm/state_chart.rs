@@ -0,0 +1,350 @@
+use std::time::{Duration, Instant};
+
+use crossterm::event::KeyCode;
+use tui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout},
+    Frame,
+};
+
+use crate::app::Context;
+use crate::kraken::worker::Request;
+use crate::kraken::ws_messages::{Feed, Subscription};
+use crate::stm::{events::Event, selected_pair_name, State, States};
+use crate::ui::{
+    chart::{draw_candlestick_chart, draw_line_chart, draw_volumes, visible_candles},
+    core::draw_box,
+};
+
+const CHART_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+// interval in minutes and its label
+const INTERVALS: [(u32, &str); 7] = [
+    (1, "1m"),
+    (5, "5m"),
+    (15, "15m"),
+    (60, "1h"),
+    (240, "4h"),
+    (1440, "1d"),
+    (10080, "1w"),
+];
+
+// OHLC Chart State
+#[derive(Default)]
+pub struct ChartState {
+    asset_pair: Option<String>,
+    interval_index: usize,
+    candlestick: bool,
+    refreshed_at: Option<Instant>,
+}
+
+impl ChartState {
+    fn interval(&self) -> u32 {
+        INTERVALS[self.interval_index].0
+    }
+
+    fn refresh(&mut self, ctx: &mut Context) {
+        if let Some(asset_pair) = &self.asset_pair {
+            ctx.request(Request::Ohlc {
+                asset_pair: asset_pair.clone(),
+                interval: self.interval(),
+            });
+            self.refreshed_at = Some(Instant::now());
+        }
+    }
+
+    // the running candle is pushed by the websocket while the chart is visible
+    fn subscription(&self, ctx: &Context) -> Option<Subscription> {
+        let pair = ctx.model.wsname(self.asset_pair.as_ref()?)?;
+        Some(Subscription::new(&pair, Feed::Ohlc(self.interval())))
+    }
+
+    fn change_interval(&mut self, interval_index: usize, ctx: &mut Context) {
+        if let Some(subscription) = self.subscription(ctx) {
+            ctx.unsubscribe(subscription);
+        }
+        self.interval_index = interval_index;
+        if let Some(subscription) = self.subscription(ctx) {
+            ctx.subscribe(subscription);
+        }
+        self.refresh(ctx);
+    }
+
+    fn title(&self, ctx: &Context) -> String {
+        let name = selected_pair_name(ctx, self.asset_pair.as_ref());
+
+        format!(
+            " Chart {name} {} {} ",
+            INTERVALS[self.interval_index].1,
+            if self.candlestick { "candles" } else { "line" }
+        )
+    }
+}
+
+impl State for ChartState {
+    fn on_enter(&mut self, ctx: &mut Context) {
+        self.asset_pair = ctx.model.selected_favorite_key();
+        if let Some(subscription) = self.subscription(ctx) {
+            ctx.subscribe(subscription);
+        }
+        self.refresh(ctx);
+    }
+
+    // stop polling and streaming once the chart is not visible anymore
+    fn on_exit(&mut self, ctx: &mut Context) {
+        self.refreshed_at = None;
+        if let Some(subscription) = self.subscription(ctx) {
+            ctx.unsubscribe(subscription);
+        }
+    }
+
+    fn on_tick(&mut self, ctx: &mut Context) {
+        if let Some(refreshed_at) = self.refreshed_at {
+            if !ctx.model.streaming && refreshed_at.elapsed() >= CHART_REFRESH_INTERVAL {
+                self.refresh(ctx);
+            }
+        }
+    }
+
+    fn on_response(&mut self, event: &Event, ctx: &mut Context) {
+        if let Event::Ohlc {
+            asset_pair,
+            interval,
+            loaded: false,
+        } = event
+        {
+            if self.asset_pair.as_ref() == Some(asset_pair) {
+                ctx.debug(format!(
+                    "[ChartS] on_response kraken_api ohlc {asset_pair} {interval} failed."
+                ));
+            }
+        }
+    }
+
+    fn on_event(&mut self, event: Event, ctx: &mut Context) -> Option<States> {
+        match event {
+            Event::Key {
+                key_code: KeyCode::Esc,
+            } => Some(States::Home),
+            Event::Key {
+                key_code: KeyCode::Char('r'),
+            } => {
+                self.refresh(ctx);
+                None
+            }
+            Event::Key {
+                key_code: KeyCode::Char('c'),
+            } => {
+                self.candlestick = !self.candlestick;
+                None
+            }
+            Event::Key {
+                key_code: KeyCode::Right,
+            } => {
+                self.change_interval((self.interval_index + 1) % INTERVALS.len(), ctx);
+                None
+            }
+            Event::Key {
+                key_code: KeyCode::Left,
+            } => {
+                let interval_index = (self.interval_index + INTERVALS.len() - 1) % INTERVALS.len();
+                self.change_interval(interval_index, ctx);
+                None
+            }
+            _ => {
+                ctx.debug(format!("[ChartS] on_event {event:?} not match"));
+                None
+            }
+        }
+    }
+
+    fn ui<B: Backend>(&self, f: &mut Frame<B>, ctx: &mut Context) {
+        let size = f.size();
+        draw_box(f, size, &self.title(ctx));
+
+        let bboxs = Layout::default()
+            .direction(Direction::Vertical)
+            .vertical_margin(1)
+            .horizontal_margin(2)
+            .constraints([Constraint::Percentage(75), Constraint::Percentage(25)].as_ref())
+            .split(size);
+
+        let pair_decimals = self
+            .asset_pair
+            .as_ref()
+            .and_then(|asset_pair| ctx.model.asset_pairs.get(asset_pair))
+            .map_or(2, |pair| usize::try_from(pair.pair_decimals).unwrap_or(2));
+
+        let candles = self
+            .asset_pair
+            .as_ref()
+            .and_then(|asset_pair| {
+                ctx.model
+                    .candles
+                    .get(&(asset_pair.clone(), self.interval()))
+            })
+            .map_or(&[][..], |candles| {
+                visible_candles(candles, bboxs[0].width.saturating_sub(2))
+            });
+
+        if self.candlestick {
+            draw_candlestick_chart(f, bboxs[0], " price ", candles);
+        } else {
+            draw_line_chart(f, bboxs[0], " price ", candles, pair_decimals);
+        }
+        draw_volumes(f, bboxs[1], " volume ", candles);
+    }
+
+    fn help_text(&self) -> &str {
+        r##"
+        ESC    -> home
+        LEFT   -> previous interval
+        RIGHT  -> next interval
+        c      -> toggle candles/line
+        r      -> refresh
+        D      -> show Debug
+        "##
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::KeyCode;
+    use mockall::predicate::eq;
+    use tui::{backend::TestBackend, buffer::Buffer, Terminal};
+
+    use crate::kraken::client::MockRestAPI;
+    use crate::kraken::error::ClientError;
+    use crate::kraken::messages::OhlcResponse;
+    use crate::stm::events::Event;
+
+    use super::*;
+
+    fn ohlc_response() -> OhlcResponse {
+        let json =
+            r#"{ "last": 60, "XXBTZUSD": [[60, "1.0", "3.0", "0.5", "2.0", "1.5", "10.0", 4]] }"#;
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_on_event() {
+        let mut mock_client = Box::new(MockRestAPI::new());
+        mock_client
+            .expect_ohlc()
+            .with(eq("XXBTZUSD"), eq(5))
+            .once()
+            .returning(|_, _| Ok(ohlc_response()));
+        mock_client
+            .expect_ohlc()
+            .with(eq("XXBTZUSD"), eq(1))
+            .once()
+            .returning(|_, _| Err(ClientError::NotConnected));
+        mock_client
+            .expect_ohlc()
+            .with(eq("XXBTZUSD"), eq(10080))
+            .once()
+            .returning(|_, _| Err(ClientError::NotConnected));
+        let mut ctx = Context::new_for_testing(mock_client);
+
+        let mut state = ChartState {
+            asset_pair: Some("XXBTZUSD".to_owned()),
+            ..ChartState::default()
+        };
+
+        let event = Event::Key {
+            key_code: KeyCode::Right,
+        };
+        assert_eq!(state.on_event(event, &mut ctx), None);
+        for event in ctx.poll_responses() {
+            state.on_response(&event, &mut ctx);
+        }
+        let candles = ctx.model.candles.get(&("XXBTZUSD".to_owned(), 5)).unwrap();
+        assert_eq!(candles.len(), 1);
+        assert_eq!(state.title(&ctx), " Chart XXBTZUSD 5m line ");
+
+        // wraps around from 5m to 1m, then to 1w
+        for _ in 0..2 {
+            let event = Event::Key {
+                key_code: KeyCode::Left,
+            };
+            state.on_event(event, &mut ctx);
+        }
+        assert_eq!(state.interval(), 10080);
+
+        let event = Event::Key {
+            key_code: KeyCode::Char('c'),
+        };
+        assert_eq!(state.on_event(event, &mut ctx), None);
+        assert_eq!(state.title(&ctx), " Chart XXBTZUSD 1w candles ");
+
+        let event = Event::Key {
+            key_code: KeyCode::Esc,
+        };
+        assert_eq!(state.on_event(event, &mut ctx), Some(States::Home));
+        state.on_exit(&mut ctx);
+        assert_eq!(state.refreshed_at, None);
+    }
+
+    #[test]
+    fn test_on_enter_without_selection() {
+        let mut ctx = Context::new_for_testing(Box::new(MockRestAPI::new()));
+
+        let mut state = ChartState::default();
+        state.on_enter(&mut ctx);
+        state.on_tick(&mut ctx);
+
+        assert_eq!(state.asset_pair, None);
+        assert_eq!(ctx.model.pending_requests, 0);
+    }
+
+    #[test]
+    fn test_ui() {
+        let backend = TestBackend::new(7, 4);
+        let mut terminal = Terminal::new(backend).unwrap();
+        let mut ctx = Context::new_for_testing(Box::new(MockRestAPI::new()));
+
+        let state = ChartState::default();
+
+        terminal
+            .draw(|f| {
+                state.ui(f, &mut ctx);
+            })
+            .unwrap();
+
+        #[rustfmt::skip]
+        let expected = Buffer::with_lines(vec![
+            " Char─╮",
+            "│ ┌ ┐ │",
+            "│ ┌ ┐ │",
+            "╰─────╯"
+            ]);
+
+        terminal.backend().assert_buffer(&expected);
+    }
+
+    #[test]
+    fn test_refresh() {
+        let mut mock_client = Box::new(MockRestAPI::new());
+        mock_client
+            .expect_ohlc()
+            .with(eq("XXBTZUSD"), eq(1))
+            .once()
+            .returning(|_, _| Ok(ohlc_response()));
+        let mut ctx = Context::new_for_testing(mock_client);
+
+        let mut state = ChartState {
+            asset_pair: Some("XXBTZUSD".to_owned()),
+            ..ChartState::default()
+        };
+
+        let event = Event::Key {
+            key_code: KeyCode::Char('r'),
+        };
+        assert_eq!(state.on_event(event, &mut ctx), None);
+        assert!(state.refreshed_at.is_some());
+
+        // polled again only once CHART_REFRESH_INTERVAL is elapsed
+        state.on_tick(&mut ctx);
+        assert_eq!(ctx.model.pending_requests, 1);
+    }
+}
@@ -40,11 +40,17 @@ impl State for DebugState {
         let size = f.size();
         draw_box(f, size, " Debug State ");
 
+        let stats = ctx.client_stats();
+        let title = format!(
+            "debug messages - calls:{} retries:{} rate limited:{} throttled:{} failures:{}",
+            stats.calls, stats.retries, stats.rate_limited, stats.throttled, stats.failures
+        );
+
         let bbox = centered_rect(95, 90, size);
         draw_stateful_list(
             f,
             bbox,
-            "debug messages",
+            &title,
             &mut ctx.model.debug_messages_stateful,
             true,
         );
@@ -0,0 +1,357 @@
+use crossterm::event::KeyCode;
+use krakenrs::AssetPair;
+use tui::{backend::Backend, Frame};
+
+use crate::app::Context;
+use crate::stm::{events::Event, State, States};
+use crate::ui::core::{centered_rect, clear_box, draw_popup};
+use crate::ui::list_stateful_widget::draw_stateful_list;
+
+// the query characters have to appear in order, the consecutive ones and the ones at the
+// start of a word (e.g. after the '/' of XBT/USD) score more. None if not matching.
+fn fuzzy_score(candidate: &str, query: &str) -> Option<i64> {
+    let candidate = candidate.to_ascii_lowercase();
+    let query = query.to_ascii_lowercase();
+    let mut query = query.chars().peekable();
+    let mut score = 0;
+    let mut previous_matched = false;
+    let mut previous_char = None;
+
+    for c in candidate.chars() {
+        let matched = query.peek() == Some(&c);
+        if matched {
+            query.next();
+            score += 1;
+            if previous_matched {
+                score += 2;
+            }
+            if matches!(previous_char, None | Some('/' | '.' | ' ')) {
+                score += 3;
+            }
+        }
+        previous_matched = matched;
+        previous_char = Some(c);
+    }
+
+    query.peek().is_none().then_some(score)
+}
+
+// the best score among the names of the pair
+fn pair_score(pair: &AssetPair, query: &str) -> Option<i64> {
+    [
+        pair.wsname.as_deref(),
+        pair.alt_name.as_deref(),
+        Some(pair.base.as_str()),
+        Some(pair.quote.as_str()),
+    ]
+    .into_iter()
+    .flatten()
+    .filter_map(|name| fuzzy_score(name, query))
+    .max()
+}
+
+// Finder State, a popup over the previous state listing the asset pairs matching the query
+#[derive(Default)]
+pub struct FinderState {
+    query: String,
+    // wsname of the listed pairs, in the order of finder_stateful
+    wsnames: Vec<String>,
+    status: Option<String>,
+    // wsname of the pair shown instead of the matches, it is not a favorite
+    details: Option<String>,
+}
+
+impl FinderState {
+    fn show_matches(&mut self, ctx: &mut Context) {
+        let mut matches = ctx
+            .model
+            .asset_pairs
+            .values()
+            .filter_map(|pair| {
+                let wsname = pair.wsname.as_ref()?;
+                let score = pair_score(pair, &self.query)?;
+                Some((score, wsname, pair))
+            })
+            .collect::<Vec<_>>();
+        matches.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+
+        self.wsnames.clear();
+        ctx.model.finder_stateful.clear();
+        for (_, wsname, pair) in matches {
+            self.wsnames.push(wsname.clone());
+            ctx.model.finder_stateful.push(format!(
+                "{wsname:<12} {:<12} {}/{}",
+                pair.alt_name.as_deref().unwrap_or_default(),
+                pair.base,
+                pair.quote
+            ));
+        }
+        ctx.model.finder_stateful.next();
+    }
+
+    fn selected(&self, ctx: &Context) -> Option<String> {
+        let index = ctx.model.finder_stateful.state.selected()?;
+        self.wsnames.get(index).cloned()
+    }
+
+    fn favorite_index(ctx: &Context, wsname: &str) -> Option<usize> {
+        ctx.model
+            .favorites_asset_pairs_stateful
+            .items
+            .iter()
+            .position(|favorite| favorite == wsname)
+    }
+
+    fn add_favorite(ctx: &mut Context, wsname: &str) {
+        if FinderState::favorite_index(ctx, wsname).is_none() {
+            ctx.model
+                .favorites_asset_pairs_stateful
+                .push(wsname.to_owned());
+            ctx.save_favorites();
+        }
+    }
+
+    fn details_text(ctx: &Context, wsname: &str) -> String {
+        let Some(pair) = ctx
+            .model
+            .asset_pair_key(wsname)
+            .and_then(|key| ctx.model.asset_pairs.get(&key))
+        else {
+            return String::new();
+        };
+
+        [
+            format!("alt_name: {}", pair.alt_name.as_deref().unwrap_or("-")),
+            format!("base: {} ({})", pair.base, pair.aclass_base),
+            format!("quote: {} ({})", pair.quote, pair.aclass_quote),
+            format!("pair_decimals: {}", pair.pair_decimals),
+            format!("lot_decimals: {}", pair.lot_decimals),
+            format!(
+                "ordermin: {}",
+                pair.ordermin
+                    .as_ref()
+                    .map_or_else(|| "-".to_owned(), ToString::to_string)
+            ),
+        ]
+        .join("\n")
+    }
+}
+
+impl State for FinderState {
+    fn on_enter(&mut self, ctx: &mut Context) {
+        self.query.clear();
+        self.status = None;
+        self.details = None;
+        self.show_matches(ctx);
+    }
+
+    fn on_event(&mut self, event: Event, ctx: &mut Context) -> Option<States> {
+        let Event::Key { key_code } = event else {
+            ctx.debug(format!("[FinderS] on_event {event:?} not match"));
+            return None;
+        };
+
+        match key_code {
+            // back to the matches from the details
+            KeyCode::Esc if self.details.is_some() => self.details = None,
+            KeyCode::Esc => return Some(States::PreviousOne),
+            KeyCode::Down => ctx.model.finder_stateful.next(),
+            KeyCode::Up => ctx.model.finder_stateful.previous(),
+            // the details of the favorites are shown by the Home state, the ones of the
+            // other pairs in the popup, the favorites are left unchanged
+            KeyCode::Enter => {
+                let wsname = self.selected(ctx)?;
+                if let Some(index) = FinderState::favorite_index(ctx, &wsname) {
+                    ctx.model
+                        .favorites_asset_pairs_stateful
+                        .state
+                        .select(Some(index));
+                    return Some(States::Home);
+                }
+                self.details = Some(wsname);
+            }
+            KeyCode::Tab => {
+                if let Some(wsname) = self.selected(ctx) {
+                    FinderState::add_favorite(ctx, &wsname);
+                    self.status = Some(format!("{wsname} in favorites"));
+                }
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.status = None;
+                self.details = None;
+                self.show_matches(ctx);
+            }
+            KeyCode::Char(c) => {
+                self.query.push(c);
+                self.status = None;
+                self.details = None;
+                self.show_matches(ctx);
+            }
+            _ => {}
+        }
+        None
+    }
+
+    // drawn over the ui of the previous state
+    fn ui<B: Backend>(&self, f: &mut Frame<B>, ctx: &mut Context) {
+        let bbox = centered_rect(60, 60, f.size());
+        let title = match &self.status {
+            Some(status) => format!(" Find pair /{}_ - {status} ", self.query),
+            None => format!(" Find pair /{}_ ", self.query),
+        };
+
+        if let Some(wsname) = &self.details {
+            let text = FinderState::details_text(ctx, wsname);
+            draw_popup(f, bbox, &format!(" {wsname} - {title}"), &text);
+            return;
+        }
+
+        clear_box(f, bbox);
+        draw_stateful_list(f, bbox, &title, &mut ctx.model.finder_stateful, false);
+    }
+
+    fn help_text(&self) -> &str {
+        r##"
+        ESC    -> close, back to the list from the details
+        a-z    -> fuzzy match wsname, altname, base or quote
+        UP     -> previous
+        DOWN   -> next
+        ENTER  -> show the details, in home for a favorite
+        TAB    -> add to favorites
+        "##
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::KeyCode;
+    use tui::{backend::TestBackend, buffer::Buffer, Terminal};
+
+    use crate::{kraken::client::MockRestAPI, stm::events::Event};
+
+    use super::*;
+
+    const ASSET_PAIRS_JSON: &str = r#"{
+      "XXBTZUSD": {"alt_name": "XBTUSD", "wsname": "XBT/USD", "aclass_base": "currency",
+        "base": "XXBT", "aclass_quote": "currency", "quote": "ZUSD", "pair_decimals": 1,
+        "lot_decimals": 8, "lot_multiplier": 1, "fees": [], "ordermin": "0.0001"},
+      "XXBTZEUR": {"alt_name": "XBTEUR", "wsname": "XBT/EUR", "aclass_base": "currency",
+        "base": "XXBT", "aclass_quote": "currency", "quote": "ZEUR", "pair_decimals": 1,
+        "lot_decimals": 8, "lot_multiplier": 1, "fees": [], "ordermin": "0.0001"},
+      "XETHZUSD": {"alt_name": "ETHUSD", "wsname": "ETH/USD", "aclass_base": "currency",
+        "base": "XETH", "aclass_quote": "currency", "quote": "ZUSD", "pair_decimals": 2,
+        "lot_decimals": 8, "lot_multiplier": 1, "fees": [], "ordermin": "0.01"}
+    }"#;
+
+    fn key(key_code: KeyCode) -> Event {
+        Event::Key { key_code }
+    }
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert_eq!(fuzzy_score("XBT/USD", "xu"), Some(8));
+        assert_eq!(fuzzy_score("XBT/USD", "ux"), None);
+        assert_eq!(fuzzy_score("XBT/USD", ""), Some(0));
+        // consecutive characters score more than scattered ones
+        assert!(fuzzy_score("XBT/USD", "usd") > fuzzy_score("XXBTZUSD", "xbd"));
+    }
+
+    #[test]
+    fn test_find_and_jump() {
+        let mut ctx = Context::new_for_testing(Box::new(MockRestAPI::new()));
+        ctx.model.asset_pairs = serde_json::from_str(ASSET_PAIRS_JSON).unwrap();
+        ctx.model
+            .favorites_asset_pairs_stateful
+            .push("ETH/USD".to_owned());
+
+        let mut state = FinderState::default();
+        state.on_enter(&mut ctx);
+        assert_eq!(ctx.model.finder_stateful.items.len(), 3);
+
+        for c in "xbeu".chars() {
+            assert_eq!(state.on_event(key(KeyCode::Char(c)), &mut ctx), None);
+        }
+        assert_eq!(state.wsnames, vec!["XBT/EUR".to_owned()]);
+        assert_eq!(
+            ctx.model.finder_stateful.items,
+            vec!["XBT/EUR      XBTEUR       XXBT/ZEUR".to_owned()]
+        );
+
+        // added to the favorites without leaving the finder
+        assert_eq!(state.on_event(key(KeyCode::Tab), &mut ctx), None);
+        assert_eq!(state.status, Some("XBT/EUR in favorites".to_owned()));
+        assert_eq!(state.on_event(key(KeyCode::Tab), &mut ctx), None);
+        assert_eq!(ctx.model.favorites_asset_pairs_stateful.items.len(), 2);
+
+        // matched by the base asset
+        for _ in 0..4 {
+            state.on_event(key(KeyCode::Backspace), &mut ctx);
+        }
+        for c in "xeth".chars() {
+            state.on_event(key(KeyCode::Char(c)), &mut ctx);
+        }
+        assert_eq!(state.wsnames, vec!["ETH/USD".to_owned()]);
+
+        // a favorite is shown in home
+        assert_eq!(
+            state.on_event(key(KeyCode::Enter), &mut ctx),
+            Some(States::Home)
+        );
+        assert_eq!(
+            ctx.model.selected_favorite_key(),
+            Some("XETHZUSD".to_owned())
+        );
+        assert_eq!(ctx.model.favorites_asset_pairs_stateful.items.len(), 2);
+
+        // the other pairs are shown in the popup without being added to the favorites
+        for _ in 0..4 {
+            state.on_event(key(KeyCode::Backspace), &mut ctx);
+        }
+        for c in "xbtusd".chars() {
+            state.on_event(key(KeyCode::Char(c)), &mut ctx);
+        }
+        assert_eq!(state.on_event(key(KeyCode::Enter), &mut ctx), None);
+        assert_eq!(state.details, Some("XBT/USD".to_owned()));
+        assert!(FinderState::details_text(&ctx, "XBT/USD").starts_with("alt_name: XBTUSD\n"));
+        assert_eq!(ctx.model.favorites_asset_pairs_stateful.items.len(), 2);
+        assert_eq!(state.on_event(key(KeyCode::Esc), &mut ctx), None);
+        assert_eq!(state.details, None);
+
+        assert_eq!(
+            state.on_event(key(KeyCode::Esc), &mut ctx),
+            Some(States::PreviousOne)
+        );
+    }
+
+    #[test]
+    fn test_ui() {
+        let backend = TestBackend::new(7, 4);
+        let mut terminal = Terminal::new(backend).unwrap();
+        let mut ctx = Context::new_for_testing(Box::new(MockRestAPI::new()));
+
+        let state = FinderState::default();
+
+        terminal
+            .draw(|f| {
+                state.ui(f, &mut ctx);
+            })
+            .unwrap();
+
+        #[rustfmt::skip]
+        let expected = Buffer::with_lines(vec![
+            " ┌ F┐  ",
+            " └──┘  ",
+            "       ",
+            "       "
+            ]);
+
+        terminal.backend().assert_buffer(&expected);
+    }
+
+    #[test]
+    fn test_state_help() {
+        let state = FinderState::default();
+        assert_eq!(state.help_text().len(), 273);
+    }
+}
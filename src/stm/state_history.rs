@@ -0,0 +1,634 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crossterm::event::KeyCode;
+use tui::{backend::Backend, Frame};
+
+use crate::app::{Context, Model};
+use crate::kraken::messages::{History, OrderInfo, TradeInfo};
+use crate::kraken::worker::Request;
+use crate::stm::{events::Event, State, States};
+use crate::ui::{
+    core::{centered_rect, draw_box},
+    format::format_datetime_utc,
+    list_stateful_widget::draw_stateful_list,
+};
+
+// date ranges cycled with 'd', ending now
+const RANGES: [(&str, Option<u64>); 5] = [
+    ("all", None),
+    ("24h", Some(86_400)),
+    ("7d", Some(7 * 86_400)),
+    ("30d", Some(30 * 86_400)),
+    ("1y", Some(365 * 86_400)),
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+enum Tab {
+    #[default]
+    ClosedOrders,
+    Trades,
+}
+
+// requests of the pages of one tab
+#[derive(Default)]
+struct Pages {
+    // the first page has been requested
+    requested: bool,
+    // a page is in flight, the next one is requested once it arrives
+    loading: bool,
+}
+
+// Closed Orders and Trades History State
+#[derive(Default)]
+pub struct HistoryState {
+    tab: Tab,
+    // only the lines of this pair are shown, the pairs are filtered locally
+    pair: Option<String>,
+    range_index: usize,
+    // start of the date range, fixed when the range is selected so that every page
+    // is requested with the same criteria
+    start: Option<u64>,
+    // incremented with the date range, the replies of a previous range are dropped
+    generation: u64,
+    closed_orders_pages: Pages,
+    trades_pages: Pages,
+}
+
+impl HistoryState {
+    fn load_page(&mut self, ctx: &mut Context, offset: usize) {
+        if !ctx.model.authenticated {
+            return;
+        }
+
+        let (generation, start, end) = (self.generation, self.start, None);
+        match self.tab {
+            Tab::ClosedOrders => {
+                self.closed_orders_pages = Pages {
+                    requested: true,
+                    loading: true,
+                };
+                ctx.request(Request::ClosedOrders {
+                    generation,
+                    offset,
+                    start,
+                    end,
+                });
+            }
+            Tab::Trades => {
+                self.trades_pages = Pages {
+                    requested: true,
+                    loading: true,
+                };
+                ctx.request(Request::TradesHistory {
+                    generation,
+                    offset,
+                    start,
+                    end,
+                });
+            }
+        }
+    }
+
+    fn pages(&self) -> &Pages {
+        match self.tab {
+            Tab::ClosedOrders => &self.closed_orders_pages,
+            Tab::Trades => &self.trades_pages,
+        }
+    }
+
+    // the first page of the current tab, unless already loaded
+    fn load_first_page(&mut self, ctx: &mut Context) {
+        if !self.pages().requested {
+            self.load_page(ctx, 0);
+        }
+    }
+
+    fn load_next_page(&mut self, ctx: &mut Context) {
+        let (has_more, offset) = match self.tab {
+            Tab::ClosedOrders => (
+                ctx.model.closed_orders.has_more(),
+                ctx.model.closed_orders.items.len(),
+            ),
+            Tab::Trades => (
+                ctx.model.trades_history.has_more(),
+                ctx.model.trades_history.items.len(),
+            ),
+        };
+        if has_more && !self.pages().loading {
+            self.load_page(ctx, offset);
+        }
+    }
+
+    fn change_range(&mut self, ctx: &mut Context) {
+        self.range_index = (self.range_index + 1) % RANGES.len();
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |now| now.as_secs());
+        self.start = RANGES[self.range_index]
+            .1
+            .map(|seconds| now.saturating_sub(seconds));
+
+        self.generation += 1;
+        ctx.model.closed_orders = History {
+            generation: self.generation,
+            ..History::default()
+        };
+        ctx.model.trades_history = History {
+            generation: self.generation,
+            ..History::default()
+        };
+        self.closed_orders_pages = Pages::default();
+        self.trades_pages = Pages::default();
+        self.load_first_page(ctx);
+    }
+
+    // the distinct pairs of the loaded lines, cycled with 'p'
+    fn pairs(&self, model: &Model) -> Vec<String> {
+        let mut pairs = match self.tab {
+            Tab::ClosedOrders => model
+                .closed_orders
+                .items
+                .iter()
+                .map(|(_, order)| order.descr.pair.clone())
+                .collect::<Vec<_>>(),
+            Tab::Trades => model
+                .trades_history
+                .items
+                .iter()
+                .map(|(_, trade)| trade.pair.clone())
+                .collect::<Vec<_>>(),
+        };
+        pairs.sort();
+        pairs.dedup();
+        pairs
+    }
+
+    fn change_pair(&mut self, ctx: &Context) {
+        let pairs = self.pairs(&ctx.model);
+        self.pair = match &self.pair {
+            None => pairs.first().cloned(),
+            Some(pair) => pairs
+                .iter()
+                .position(|p| p == pair)
+                .and_then(|index| pairs.get(index + 1))
+                .cloned(),
+        };
+    }
+
+    fn format_order(order: &OrderInfo) -> String {
+        format!(
+            "{}  {:<10} {:<4} {:<10} {:>14} {:>14}  {}",
+            format_datetime_utc(order.closetm),
+            order.descr.pair,
+            order.descr.side,
+            order.descr.ordertype,
+            order.price,
+            order.vol_exec,
+            order.status
+        )
+    }
+
+    fn format_trade(trade: &TradeInfo) -> String {
+        format!(
+            "{}  {:<10} {:<4} {:<10} {:>14} {:>14} {:>10}",
+            format_datetime_utc(trade.time),
+            trade.pair,
+            trade.side,
+            trade.ordertype,
+            trade.price,
+            trade.vol,
+            trade.fee
+        )
+    }
+
+    // keeps the selection, the list is rebuilt every time a page is loaded
+    fn show_history(&self, ctx: &mut Context) {
+        let selected = ctx.model.history_stateful.state.selected();
+        ctx.model.history_stateful.clear();
+
+        if !ctx.model.authenticated {
+            ctx.model
+                .history_stateful
+                .push("API credentials required, see the README".to_owned());
+            return;
+        }
+
+        let pair = self.pair.as_deref();
+        let lines = match self.tab {
+            Tab::ClosedOrders => ctx
+                .model
+                .closed_orders
+                .items
+                .iter()
+                .filter(|(_, order)| pair.is_none() || pair == Some(order.descr.pair.as_str()))
+                .map(|(_, order)| HistoryState::format_order(order))
+                .collect::<Vec<_>>(),
+            Tab::Trades => ctx
+                .model
+                .trades_history
+                .items
+                .iter()
+                .filter(|(_, trade)| pair.is_none() || pair == Some(trade.pair.as_str()))
+                .map(|(_, trade)| HistoryState::format_trade(trade))
+                .collect::<Vec<_>>(),
+        };
+
+        let len = lines.len();
+        for line in lines {
+            ctx.model.history_stateful.push(line);
+        }
+        if len > 0 {
+            ctx.model
+                .history_stateful
+                .state
+                .select(selected.map(|index| index.min(len - 1)));
+        }
+    }
+
+    fn on_page(&self, ctx: &mut Context, offset: usize, loaded: bool) {
+        if !loaded {
+            ctx.debug(format!(
+                "[HistoryS] on_response kraken_api history offset {offset} failed."
+            ));
+        }
+        self.show_history(ctx);
+    }
+
+    fn title(&self, ctx: &Context) -> String {
+        let (name, loaded, count) = match self.tab {
+            Tab::ClosedOrders => (
+                "Closed Orders",
+                ctx.model.closed_orders.items.len(),
+                ctx.model.closed_orders.count,
+            ),
+            Tab::Trades => (
+                "Trades History",
+                ctx.model.trades_history.items.len(),
+                ctx.model.trades_history.count,
+            ),
+        };
+
+        format!(
+            " {name} pair:{} range:{} {loaded}/{count}{} ",
+            self.pair.as_deref().unwrap_or("all"),
+            RANGES[self.range_index].0,
+            if self.pages().loading {
+                " (loading...)"
+            } else {
+                ""
+            }
+        )
+    }
+}
+
+impl State for HistoryState {
+    fn on_enter(&mut self, ctx: &mut Context) {
+        self.show_history(ctx);
+        self.load_first_page(ctx);
+    }
+
+    fn on_response(&mut self, event: &Event, ctx: &mut Context) {
+        match event {
+            Event::ClosedOrders {
+                generation,
+                offset,
+                loaded,
+            } if *generation == self.generation => {
+                self.closed_orders_pages.loading = false;
+                self.on_page(ctx, *offset, *loaded);
+            }
+            Event::TradesHistory {
+                generation,
+                offset,
+                loaded,
+            } if *generation == self.generation => {
+                self.trades_pages.loading = false;
+                self.on_page(ctx, *offset, *loaded);
+            }
+            _ => {}
+        }
+    }
+
+    fn on_event(&mut self, event: Event, ctx: &mut Context) -> Option<States> {
+        match event {
+            Event::Key {
+                key_code: KeyCode::Esc,
+            } => Some(States::Home),
+            Event::Key {
+                key_code: KeyCode::Tab,
+            } => {
+                self.tab = match self.tab {
+                    Tab::ClosedOrders => Tab::Trades,
+                    Tab::Trades => Tab::ClosedOrders,
+                };
+                self.pair = None;
+                ctx.model.history_stateful.unselect();
+                self.show_history(ctx);
+                self.load_first_page(ctx);
+                None
+            }
+            Event::Key {
+                key_code: KeyCode::Char('p'),
+            } => {
+                self.change_pair(ctx);
+                ctx.model.history_stateful.unselect();
+                self.show_history(ctx);
+                None
+            }
+            Event::Key {
+                key_code: KeyCode::Char('d'),
+            } => {
+                self.change_range(ctx);
+                ctx.model.history_stateful.unselect();
+                self.show_history(ctx);
+                None
+            }
+            Event::Key {
+                key_code: KeyCode::Down,
+            } => {
+                // scrolling past the last line loads the next page instead of wrapping
+                let list = &ctx.model.history_stateful;
+                let at_end =
+                    list.items.is_empty() || list.state.selected() == Some(list.items.len() - 1);
+                let has_more = match self.tab {
+                    Tab::ClosedOrders => ctx.model.closed_orders.has_more(),
+                    Tab::Trades => ctx.model.trades_history.has_more(),
+                };
+                if at_end && has_more {
+                    self.load_next_page(ctx);
+                } else {
+                    ctx.model.history_stateful.next();
+                }
+                None
+            }
+            Event::Key {
+                key_code: KeyCode::Up,
+            } => {
+                ctx.model.history_stateful.previous();
+                None
+            }
+            Event::Key {
+                key_code: KeyCode::Left,
+            } => {
+                ctx.model.history_stateful.unselect();
+                None
+            }
+            _ => {
+                ctx.debug(format!("[HistoryS] on_event {event:?} not match"));
+                None
+            }
+        }
+    }
+
+    fn ui<B: Backend>(&self, f: &mut Frame<B>, ctx: &mut Context) {
+        let size = f.size();
+        draw_box(f, size, &self.title(ctx));
+
+        let header = match self.tab {
+            Tab::ClosedOrders => " closed pair side type price filled status ",
+            Tab::Trades => " time pair side type price volume fee ",
+        };
+        let bbox = centered_rect(95, 90, size);
+        draw_stateful_list(f, bbox, header, &mut ctx.model.history_stateful, false);
+    }
+
+    fn help_text(&self) -> &str {
+        r##"
+        ESC    -> home
+        UP     -> previous
+        DOWN   -> next, loads more at the end
+        LEFT   -> unselect
+        TAB    -> closed orders/trades
+        p      -> filter by pair
+        d      -> filter by date range
+        D      -> show Debug
+        "##
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::KeyCode;
+    use mockall::predicate::{always, eq};
+    use tui::{backend::TestBackend, buffer::Buffer, Terminal};
+
+    use crate::kraken::client::MockRestAPI;
+    use crate::kraken::error::ClientError;
+    use crate::kraken::messages::{ClosedOrdersResponse, OrderDescription, TradesHistoryResponse};
+    use crate::stm::events::Event;
+
+    use super::*;
+
+    fn closed_order(pair: &str, closetm: f64) -> OrderInfo {
+        OrderInfo {
+            status: "closed".to_owned(),
+            closetm,
+            descr: OrderDescription {
+                pair: pair.to_owned(),
+                side: "sell".to_owned(),
+                ordertype: "market".to_owned(),
+                ..OrderDescription::default()
+            },
+            vol_exec: "1.0".to_owned(),
+            price: "30000.0".to_owned(),
+            ..OrderInfo::default()
+        }
+    }
+
+    // 3 orders matching, 2 in the first page and 1 in the second one
+    fn closed_orders_page(offset: usize) -> ClosedOrdersResponse {
+        let mut response = ClosedOrdersResponse {
+            count: 3,
+            ..ClosedOrdersResponse::default()
+        };
+        if offset == 0 {
+            let order = closed_order("XBTUSD", 172_800.0);
+            response.closed.insert("O1".to_owned(), order);
+            let order = closed_order("ETHUSD", 86_400.0);
+            response.closed.insert("O2".to_owned(), order);
+        } else {
+            let order = closed_order("XBTUSD", 0.0);
+            response.closed.insert("O3".to_owned(), order);
+        }
+        response
+    }
+
+    fn key(key_code: KeyCode) -> Event {
+        Event::Key { key_code }
+    }
+
+    fn poll(state: &mut HistoryState, ctx: &mut Context) {
+        for event in ctx.poll_responses() {
+            state.on_response(&event, ctx);
+        }
+    }
+
+    #[test]
+    fn test_lazy_pagination() {
+        let mut mock_client = Box::new(MockRestAPI::new());
+        mock_client
+            .expect_closed_orders()
+            .with(eq(0), eq(None), eq(None))
+            .once()
+            .returning(|offset, _, _| Ok(closed_orders_page(offset)));
+        mock_client
+            .expect_closed_orders()
+            .with(eq(2), eq(None), eq(None))
+            .once()
+            .returning(|offset, _, _| Ok(closed_orders_page(offset)));
+        let mut ctx = Context::new_for_testing(mock_client);
+        ctx.model.authenticated = true;
+
+        let mut state = HistoryState::default();
+        state.on_enter(&mut ctx);
+        poll(&mut state, &mut ctx);
+        assert_eq!(
+            ctx.model.history_stateful.items,
+            vec![
+                "1970-01-03 00:00:00  XBTUSD     sell market            30000.0            1.0  closed",
+                "1970-01-02 00:00:00  ETHUSD     sell market            30000.0            1.0  closed",
+            ]
+        );
+        assert_eq!(state.title(&ctx), " Closed Orders pair:all range:all 2/3 ");
+
+        // entering again does not reload the first page
+        state.on_enter(&mut ctx);
+
+        // past the end of the list the second page is loaded, the selection is kept
+        for _ in 0..3 {
+            state.on_event(key(KeyCode::Down), &mut ctx);
+        }
+        assert!(state.closed_orders_pages.loading);
+        poll(&mut state, &mut ctx);
+        assert_eq!(ctx.model.history_stateful.items.len(), 3);
+        assert_eq!(ctx.model.history_stateful.state.selected(), Some(1));
+
+        // the history is complete, the selection wraps around
+        state.on_event(key(KeyCode::Down), &mut ctx);
+        state.on_event(key(KeyCode::Down), &mut ctx);
+        assert_eq!(ctx.model.history_stateful.state.selected(), Some(0));
+
+        // ETHUSD, XBTUSD and back to all the pairs
+        state.on_event(key(KeyCode::Char('p')), &mut ctx);
+        assert_eq!(ctx.model.history_stateful.items.len(), 1);
+        state.on_event(key(KeyCode::Char('p')), &mut ctx);
+        assert_eq!(ctx.model.history_stateful.items.len(), 2);
+        state.on_event(key(KeyCode::Char('p')), &mut ctx);
+        assert_eq!(ctx.model.history_stateful.items.len(), 3);
+
+        assert_eq!(
+            state.on_event(key(KeyCode::Esc), &mut ctx),
+            Some(States::Home)
+        );
+    }
+
+    #[test]
+    fn test_date_range_and_tabs() {
+        let mut mock_client = Box::new(MockRestAPI::new());
+        mock_client
+            .expect_closed_orders()
+            .with(eq(0), always(), eq(None))
+            .times(2)
+            .returning(|_, _, _| Ok(ClosedOrdersResponse::default()));
+        mock_client
+            .expect_trades_history()
+            .with(eq(0), always(), eq(None))
+            .once()
+            .returning(|_, _, _| Err(ClientError::NotConnected));
+        let mut ctx = Context::new_for_testing(mock_client);
+        ctx.model.authenticated = true;
+
+        let mut state = HistoryState::default();
+        state.on_enter(&mut ctx);
+        poll(&mut state, &mut ctx);
+
+        state.on_event(key(KeyCode::Char('d')), &mut ctx);
+        assert!(state.start.is_some());
+        poll(&mut state, &mut ctx);
+
+        state.on_event(key(KeyCode::Tab), &mut ctx);
+        poll(&mut state, &mut ctx);
+        assert_eq!(state.title(&ctx), " Trades History pair:all range:24h 0/0 ");
+    }
+
+    #[test]
+    fn test_stale_range() {
+        let mut mock_client = Box::new(MockRestAPI::new());
+        mock_client
+            .expect_closed_orders()
+            .with(eq(0), eq(None), eq(None))
+            .once()
+            .returning(|offset, _, _| Ok(closed_orders_page(offset)));
+        mock_client
+            .expect_closed_orders()
+            .with(eq(0), always(), eq(None))
+            .once()
+            .returning(|_, _, _| Ok(ClosedOrdersResponse::default()));
+        mock_client
+            .expect_trades_history()
+            .with(eq(0), always(), eq(None))
+            .once()
+            .returning(|_, _, _| Ok(TradesHistoryResponse::default()));
+        let mut ctx = Context::new_for_testing(mock_client);
+        ctx.model.authenticated = true;
+
+        // the range changes and the tab too before the first page arrives
+        let mut state = HistoryState::default();
+        state.on_enter(&mut ctx);
+        state.on_event(key(KeyCode::Char('d')), &mut ctx);
+        state.on_event(key(KeyCode::Tab), &mut ctx);
+        assert!(state.closed_orders_pages.loading);
+        assert!(state.trades_pages.loading);
+
+        // the page of the range "all" is dropped
+        poll(&mut state, &mut ctx);
+        assert!(!state.closed_orders_pages.loading);
+        assert!(!state.trades_pages.loading);
+        assert!(ctx.model.closed_orders.items.is_empty());
+        state.on_event(key(KeyCode::Tab), &mut ctx);
+        assert_eq!(state.title(&ctx), " Closed Orders pair:all range:24h 0/0 ");
+    }
+
+    #[test]
+    fn test_not_authenticated() {
+        let mut ctx = Context::new_for_testing(Box::new(MockRestAPI::new()));
+
+        let mut state = HistoryState::default();
+        state.on_enter(&mut ctx);
+
+        assert_eq!(ctx.model.pending_requests, 0);
+        assert_eq!(ctx.model.history_stateful.items.len(), 1);
+    }
+
+    #[test]
+    fn test_ui() {
+        let backend = TestBackend::new(7, 4);
+        let mut terminal = Terminal::new(backend).unwrap();
+        let mut ctx = Context::new_for_testing(Box::new(MockRestAPI::new()));
+
+        let state = HistoryState::default();
+
+        terminal
+            .draw(|f| {
+                state.ui(f, &mut ctx);
+            })
+            .unwrap();
+
+        #[rustfmt::skip]
+        let expected = Buffer::with_lines(vec![
+            "┌ clo┐╮",
+            "│    ││",
+            "└────┘│",
+            "╰─────╯"
+            ]);
+
+        terminal.backend().assert_buffer(&expected);
+    }
+
+    #[test]
+    fn test_state_help() {
+        let state = HistoryState::default();
+        assert_eq!(state.help_text().len(), 272);
+    }
+}
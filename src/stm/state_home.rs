@@ -1,25 +1,224 @@
-use crossterm::event::KeyCode;
-use tui::{backend::Backend, Frame};
+use std::time::{Duration, Instant};
 
+use crossterm::event::KeyCode;
+use tui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout},
+    Frame,
+};
+
+use crate::app::unix_now;
+use crate::kraken::worker::Request;
+use crate::kraken::ws_messages::{Feed, Subscription};
 use crate::stm::{events::Event, State, States};
-use crate::ui::core::draw_box;
+use crate::ui::core::{draw_box, draw_tabs};
+use crate::ui::format::format_age;
 use crate::ui::list_stateful_widget::draw_stateful_list;
 use crate::{app::Context, ui::core::split_columns};
 
+const TICKER_REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+// while offline the reference data is downloaded again at this pace
+const OFFLINE_RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+// what the name typed in the watchlists box is for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NameInput {
+    Create,
+    Rename,
+}
+
 // Home State
 pub struct HomeState {
     pub on_enter_first: bool,
+    ticker_refreshed_at: Option<Instant>,
+    reference_requested_at: Option<Instant>,
+    // favorites whose ticker is pushed by the websocket
+    ticker_subscriptions: Vec<String>,
+    name_input: Option<(NameInput, String)>,
+    confirm_delete: bool,
 }
 
 impl Default for HomeState {
     fn default() -> Self {
         Self {
             on_enter_first: true,
+            ticker_refreshed_at: None,
+            reference_requested_at: None,
+            ticker_subscriptions: vec![],
+            name_input: None,
+            confirm_delete: false,
         }
     }
 }
 
 impl HomeState {
+    // typing a watchlist name or confirming its deletion
+    pub fn is_editing(&self) -> bool {
+        self.name_input.is_some() || self.confirm_delete
+    }
+
+    // the favorites shown are another list, saved and subscribed to again
+    fn on_watchlist_changed(&mut self, ctx: &mut Context) {
+        ctx.save_favorites();
+        self.sync_ticker_subscriptions(ctx);
+        ctx.model.favorites_asset_pairs_info_stateful.clear();
+        ctx.model.favorites_asset_pairs_ticker_stateful.clear();
+        self.ticker_refreshed_at = None;
+    }
+
+    fn switch_watchlist(&mut self, ctx: &mut Context, forward: bool) {
+        let count = ctx.model.watchlists.len();
+        let index = if forward {
+            (ctx.model.active_watchlist + 1) % count
+        } else {
+            (ctx.model.active_watchlist + count - 1) % count
+        };
+        ctx.model.switch_watchlist(index);
+        self.on_watchlist_changed(ctx);
+    }
+
+    fn on_name_input(&mut self, key_code: KeyCode, ctx: &mut Context) {
+        let Some((input, name)) = &mut self.name_input else {
+            return;
+        };
+        match key_code {
+            KeyCode::Esc => self.name_input = None,
+            KeyCode::Backspace => {
+                name.pop();
+            }
+            KeyCode::Char(c) => name.push(c),
+            KeyCode::Enter => {
+                let done = match input {
+                    NameInput::Create => ctx.model.add_watchlist(name),
+                    NameInput::Rename => ctx.model.rename_watchlist(name),
+                };
+                // an empty or already used name is kept to be fixed
+                if done {
+                    self.name_input = None;
+                    self.on_watchlist_changed(ctx);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn on_confirm_delete(&mut self, key_code: KeyCode, ctx: &mut Context) {
+        self.confirm_delete = false;
+        if key_code == KeyCode::Char('y') && ctx.model.delete_watchlist() {
+            self.on_watchlist_changed(ctx);
+        }
+    }
+
+    // the keys managing the watchlists, false for the other keys
+    fn on_watchlist_key(&mut self, key_code: KeyCode, ctx: &mut Context) -> bool {
+        match key_code {
+            KeyCode::Tab => self.switch_watchlist(ctx, true),
+            KeyCode::BackTab => self.switch_watchlist(ctx, false),
+            KeyCode::Char('w') => self.name_input = Some((NameInput::Create, String::new())),
+            KeyCode::Char('e') => {
+                let name = ctx.model.active_watchlist_name().to_owned();
+                self.name_input = Some((NameInput::Rename, name));
+            }
+            KeyCode::Char('x') => self.confirm_delete = ctx.model.watchlists.len() > 1,
+            KeyCode::Char('m') => {
+                let to = (ctx.model.active_watchlist + 1) % ctx.model.watchlists.len();
+                if let Some(pair) = ctx.model.move_selected_favorite(to) {
+                    ctx.debug(format!("[HomeS] {pair} moved to watchlist {to}"));
+                    self.on_watchlist_changed(ctx);
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    // the cached assets and asset pairs are shown meanwhile
+    fn refresh_reference_data(&mut self, ctx: &mut Context) {
+        self.reference_requested_at = Some(Instant::now());
+        ctx.request(Request::ListAssets);
+        ctx.request(Request::ListAssetPairs);
+    }
+
+    // follows the favorites added by the Search state or removed by the user
+    fn sync_ticker_subscriptions(&mut self, ctx: &mut Context) {
+        let favorites = ctx.model.favorites_asset_pairs_stateful.items.clone();
+
+        for pair in &self.ticker_subscriptions {
+            if !favorites.contains(pair) {
+                ctx.unsubscribe(Subscription::new(pair, Feed::Ticker));
+            }
+        }
+        for pair in &favorites {
+            if !self.ticker_subscriptions.contains(pair) {
+                ctx.subscribe(Subscription::new(pair, Feed::Ticker));
+            }
+        }
+
+        self.ticker_subscriptions = favorites;
+    }
+
+    fn refresh_ticker(&mut self, ctx: &mut Context) {
+        if let Some(key) = ctx.model.selected_favorite_key() {
+            ctx.request(Request::Ticker { asset_pair: key });
+            self.ticker_refreshed_at = Some(Instant::now());
+        } else {
+            self.ticker_refreshed_at = None;
+        }
+
+        HomeState::show_asset_pair_ticker(ctx);
+    }
+
+    fn show_asset_pair_ticker(ctx: &mut Context) {
+        ctx.model.favorites_asset_pairs_ticker_stateful.clear();
+
+        if let Some(key) = ctx.model.selected_favorite_key() {
+            // the prices use the precision of the pair, the volumes the one of the lots
+            let (decimals, lot_decimals) = match ctx.model.asset_pairs.get(&key) {
+                Some(pair) => (
+                    usize::try_from(pair.pair_decimals).unwrap_or(2),
+                    usize::try_from(pair.lot_decimals).unwrap_or(8),
+                ),
+                None => (2, 8),
+            };
+
+            if let Some(ticker) = ctx.model.tickers.get(&key) {
+                let price = |value: Option<f64>| match value {
+                    Some(value) => format!("{value:.decimals$}"),
+                    None => "-".to_owned(),
+                };
+                let volume = match ticker.volume_24h() {
+                    Some(value) => format!("{value:.lot_decimals$}"),
+                    None => "-".to_owned(),
+                };
+                let spread = match (ticker.ask(), ticker.bid()) {
+                    (Some(ask), Some(bid)) => Some(ask - bid),
+                    _ => None,
+                };
+                let change = match ticker.change_pct() {
+                    Some(change) => format!("{change:+.2}%"),
+                    None => "-".to_owned(),
+                };
+
+                let lines = vec![
+                    format!("        last:{}", price(ticker.last())),
+                    format!("         bid:{}", price(ticker.bid())),
+                    format!("         ask:{}", price(ticker.ask())),
+                    format!("      spread:{}", price(spread)),
+                    format!("  volume 24h:{volume}"),
+                    format!("    vwap 24h:{}", price(ticker.vwap_24h())),
+                    format!("    high 24h:{}", price(ticker.high_24h())),
+                    format!("     low 24h:{}", price(ticker.low_24h())),
+                    format!("  24h change:{change}"),
+                ];
+
+                let stateful_list = &mut ctx.model.favorites_asset_pairs_ticker_stateful;
+                for line in lines {
+                    stateful_list.push(line);
+                }
+            }
+        }
+    }
+
     fn show_asset_pair_info(ctx: &mut Context) {
         ctx.model.favorites_asset_pairs_info_stateful.clear();
 
@@ -63,38 +262,117 @@ impl HomeState {
 impl State for HomeState {
     fn on_enter_once(&mut self, ctx: &mut Context) {
         self.on_enter_first = false;
-        let result = ctx.kraken_api.connect();
-        match result {
-            Ok(_) => {
-                ctx.debug("[HomeS] on_enter_once kraken_api connected.".to_string());
-
-                let result = ctx.kraken_api.list_assets();
-                match result {
-                    Some(assets) => {
-                        ctx.debug("[HomeS] on_enter_once kraken_api list_assets.".to_string());
-                        ctx.model.assets = assets;
-                    }
-                    None => {
-                        ctx.debug(
-                            "[HomeS] on_enter_once kraken_api list_assets failed.".to_string(),
-                        );
-                    }
+        ctx.debug("[HomeS] on_enter_once kraken_api connecting.".to_string());
+
+        ctx.request(Request::Connect);
+        self.refresh_reference_data(ctx);
+    }
+
+    fn on_enter(&mut self, ctx: &mut Context) {
+        self.sync_ticker_subscriptions(ctx);
+        // the favorite can be selected by the Finder state
+        if ctx.model.selected_favorite_key().is_some() {
+            HomeState::show_asset_pair_info(ctx);
+            self.refresh_ticker(ctx);
+        }
+    }
+
+    // the ticker is refreshed only while it is visible
+    fn on_exit(&mut self, _ctx: &mut Context) {
+        self.ticker_refreshed_at = None;
+    }
+
+    fn on_tick(&mut self, ctx: &mut Context) {
+        if let Some(requested_at) = self.reference_requested_at {
+            if ctx.model.offline
+                && ctx.model.pending_requests == 0
+                && requested_at.elapsed() >= OFFLINE_RETRY_INTERVAL
+            {
+                self.refresh_reference_data(ctx);
+            }
+        }
+        if let Some(refreshed_at) = self.ticker_refreshed_at {
+            if !ctx.model.streaming && refreshed_at.elapsed() >= TICKER_REFRESH_INTERVAL {
+                self.refresh_ticker(ctx);
+            }
+        }
+    }
+
+    fn on_response(&mut self, event: &Event, ctx: &mut Context) {
+        match event {
+            Event::Connected { result: Ok(()) } => {
+                ctx.debug("[HomeS] on_response kraken_api connected.".to_string());
+            }
+            Event::Connected { result: Err(_) } => {
+                ctx.debug("[HomeS] on_response kraken_api error connection!!!".to_string());
+            }
+            Event::Assets { loaded: true } => {
+                ctx.debug("[HomeS] on_response kraken_api list_assets.".to_string());
+            }
+            Event::Assets { loaded: false } => {
+                ctx.debug("[HomeS] on_response kraken_api list_assets failed.".to_string());
+            }
+            Event::Ticker { asset_pair, loaded } => {
+                if !loaded {
+                    ctx.debug(format!(
+                        "[HomeS] on_response kraken_api ticker {asset_pair} failed."
+                    ));
                 }
+                HomeState::show_asset_pair_ticker(ctx);
             }
-            Err(_) => ctx.debug("[HomeS] on_enter_once kraken_api error connection!!!".to_string()),
+            _ => {}
         }
     }
 
     fn on_event(&mut self, event: Event, ctx: &mut Context) -> Option<States> {
+        if let Event::Key { key_code } = event {
+            if self.name_input.is_some() {
+                self.on_name_input(key_code, ctx);
+                return None;
+            }
+            if self.confirm_delete {
+                self.on_confirm_delete(key_code, ctx);
+                return None;
+            }
+            if self.on_watchlist_key(key_code, ctx) {
+                return None;
+            }
+        }
+
         match event {
             Event::Key {
                 key_code: KeyCode::Char('f'),
             } => Some(States::Search),
+            Event::Key {
+                key_code: KeyCode::Char('o'),
+            } if ctx.model.selected_favorite_key().is_some() => Some(States::OrderBook),
+            Event::Key {
+                key_code: KeyCode::Char('t'),
+            } if ctx.model.selected_favorite_key().is_some() => Some(States::Trades),
+            Event::Key {
+                key_code: KeyCode::Char('c'),
+            } if ctx.model.selected_favorite_key().is_some() => Some(States::Chart),
+            Event::Key {
+                key_code: KeyCode::Char('s'),
+            } if ctx.model.selected_favorite_key().is_some() => Some(States::Spreads),
+            Event::Key {
+                key_code: KeyCode::Char('n'),
+            } if ctx.model.selected_favorite_key().is_some() => Some(States::OrderEntry),
+            Event::Key {
+                key_code: KeyCode::Char('b'),
+            } => Some(States::Balances),
+            Event::Key {
+                key_code: KeyCode::Char('O'),
+            } => Some(States::OpenOrders),
+            Event::Key {
+                key_code: KeyCode::Char('h'),
+            } => Some(States::History),
             Event::Key {
                 key_code: KeyCode::Down,
             } => {
                 ctx.model.favorites_asset_pairs_stateful.next();
                 HomeState::show_asset_pair_info(ctx);
+                self.refresh_ticker(ctx);
 
                 None
             }
@@ -103,6 +381,7 @@ impl State for HomeState {
             } => {
                 ctx.model.favorites_asset_pairs_stateful.previous();
                 HomeState::show_asset_pair_info(ctx);
+                self.refresh_ticker(ctx);
 
                 None
             }
@@ -111,6 +390,14 @@ impl State for HomeState {
             } => {
                 ctx.model.favorites_asset_pairs_stateful.unselect();
                 ctx.model.favorites_asset_pairs_info_stateful.clear();
+                ctx.model.favorites_asset_pairs_ticker_stateful.clear();
+
+                None
+            }
+            Event::Key {
+                key_code: KeyCode::Char('r'),
+            } => {
+                self.refresh_ticker(ctx);
 
                 None
             }
@@ -122,8 +409,12 @@ impl State for HomeState {
                     ctx.model.favorites_asset_pairs_stateful.unselect();
                     if index < ctx.model.favorites_asset_pairs_stateful.items.len() {
                         ctx.model.favorites_asset_pairs_stateful.items.remove(index);
+                        ctx.save_favorites();
+                        self.sync_ticker_subscriptions(ctx);
                     }
                     ctx.model.favorites_asset_pairs_info_stateful.clear();
+                    ctx.model.favorites_asset_pairs_ticker_stateful.clear();
+                    self.ticker_refreshed_at = None;
                 }
 
                 None
@@ -137,20 +428,78 @@ impl State for HomeState {
 
     fn ui<B: Backend>(&self, f: &mut Frame<B>, ctx: &mut Context) {
         let size = f.size();
-        draw_box(f, size, " Home State ");
+        let auth = if ctx.model.authenticated {
+            "authenticated"
+        } else {
+            "public-only"
+        };
+        let offline = match (ctx.model.offline, ctx.model.data_age(unix_now())) {
+            (true, Some(age)) => format!(
+                "[offline, data {} old] ",
+                format_age(Duration::from_secs(age).as_secs_f64())
+            ),
+            (true, None) => "[offline] ".to_owned(),
+            (false, _) => String::new(),
+        };
+        if ctx.model.pending_requests > 0 {
+            draw_box(
+                f,
+                size,
+                &format!(" Home State [{auth}] {offline}(loading...) "),
+            );
+        } else {
+            draw_box(f, size, &format!(" Home State [{auth}] {offline}"));
+        }
 
         let bboxs = split_columns(2, size);
+        let watchlist_bboxs = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
+            .split(bboxs[0]);
+
+        let tabs_title = match (&self.name_input, self.confirm_delete) {
+            (Some((NameInput::Create, name)), _) => format!(" new watchlist: {name}_ "),
+            (Some((NameInput::Rename, name)), _) => format!(" rename watchlist: {name}_ "),
+            (None, true) => format!(" delete {}? (y/n) ", ctx.model.active_watchlist_name()),
+            (None, false) => " watchlists ".to_owned(),
+        };
+        let names = ctx
+            .model
+            .watchlists
+            .iter()
+            .map(|watchlist| watchlist.name.clone())
+            .collect::<Vec<_>>();
+        draw_tabs(
+            f,
+            watchlist_bboxs[0],
+            &tabs_title,
+            &names,
+            ctx.model.active_watchlist,
+        );
 
+        let list_title = format!(" {} ", ctx.model.active_watchlist_name());
         draw_stateful_list(
             f,
-            bboxs[0],
-            " favorites ",
+            watchlist_bboxs[1],
+            &list_title,
             &mut ctx.model.favorites_asset_pairs_stateful,
             false,
         );
+        let details_bboxs = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(11), Constraint::Min(0)].as_ref())
+            .split(bboxs[1]);
+
         draw_stateful_list(
             f,
-            bboxs[1],
+            details_bboxs[0],
+            " ticker ",
+            &mut ctx.model.favorites_asset_pairs_ticker_stateful,
+            false,
+        );
+        draw_stateful_list(
+            f,
+            details_bboxs[1],
             " details ",
             &mut ctx.model.favorites_asset_pairs_info_stateful,
             false,
@@ -164,7 +513,23 @@ impl State for HomeState {
         DOWN   -> next
         LEFT   -> previous
         d      -> remove from favorites
+        TAB    -> next watchlist
+        BTAB   -> previous watchlist
+        w      -> new watchlist
+        e      -> rename watchlist
+        x      -> delete watchlist
+        m      -> move pair to the next watchlist
+        r      -> refresh ticker
+        o      -> order book
+        t      -> recent trades
+        c      -> price chart
+        s      -> recent spreads
+        n      -> new order
+        b      -> balances
+        O      -> open orders
+        h      -> closed orders and trades history
         f      -> search
+        /      -> find a pair
         D      -> show Debug
         "##
     }
@@ -173,10 +538,14 @@ impl State for HomeState {
 #[cfg(test)]
 mod tests {
     use crossterm::event::KeyCode;
-    use krakenrs::AssetsResponse;
+    use krakenrs::AssetPairsResponse;
+
+    use crate::kraken::messages::AssetsResponse;
     use tui::{backend::TestBackend, buffer::Buffer, Terminal};
 
+    use crate::kraken::messages::{TickerInfo, TickerResponse};
     use crate::{kraken::client::MockRestAPI, stm::events::Event};
+    use mockall::predicate::eq;
 
     use super::*;
 
@@ -232,14 +601,24 @@ mod tests {
         mock_client
             .expect_list_assets()
             .once()
-            .returning(|| Some(AssetsResponse::new()));
+            .returning(|| Ok(AssetsResponse::new()));
+        mock_client
+            .expect_list_asset_pairs()
+            .once()
+            .returning(|| Ok(AssetPairsResponse::new()));
         let mut ctx = Context::new_for_testing(mock_client);
 
         let mut state = HomeState::default();
         state.on_enter_once(&mut ctx);
 
         assert_eq!(state.on_enter_first, false);
+        assert_eq!(ctx.model.pending_requests, 3);
 
+        for event in ctx.poll_responses() {
+            state.on_response(&event, &mut ctx);
+        }
+
+        assert_eq!(ctx.model.pending_requests, 0);
         assert_eq!(ctx.model.assets.len(), 0);
         assert_eq!(ctx.model.assets_stateful.items.len(), 0);
         assert_eq!(ctx.model.assets_stateful.state.selected(), None);
@@ -249,7 +628,22 @@ mod tests {
 
     #[test]
     fn test_asset_pair_select() -> Result<(), String> {
-        let mut ctx = Context::new_for_testing(Box::new(MockRestAPI::new()));
+        let mut mock_client = Box::new(MockRestAPI::new());
+        mock_client
+            .expect_ticker()
+            .with(eq("ETHUSDC"))
+            .times(3)
+            .returning(|_| {
+                let mut ticker = TickerResponse::new();
+                let info = TickerInfo {
+                    c: vec!["1210.5".to_owned(), "0.01".to_owned()],
+                    v: vec!["1000".to_owned(), "2500.5".to_owned()],
+                    ..TickerInfo::default()
+                };
+                ticker.insert("ETHUSDC".to_owned(), info);
+                Ok(ticker)
+            });
+        let mut ctx = Context::new_for_testing(mock_client);
 
         ctx.model
             .favorites_asset_pairs_stateful
@@ -304,10 +698,45 @@ mod tests {
         let to_state = state.on_event(event, &mut ctx);
         assert_eq!(to_state, None);
 
+        assert_eq!(ctx.model.pending_requests, 1);
+        for event in ctx.poll_responses() {
+            state.on_response(&event, &mut ctx);
+        }
+
         assert_eq!(
             ctx.model.favorites_asset_pairs_info_stateful.items.len(),
             13
         );
+        assert_eq!(
+            ctx.model.favorites_asset_pairs_ticker_stateful.items.len(),
+            9
+        );
+        assert!(ctx.model.tickers.contains_key("ETHUSDC"));
+        // the prices with pair_decimals, the volumes with lot_decimals
+        let ticker_lines = &ctx.model.favorites_asset_pairs_ticker_stateful.items;
+        assert_eq!(ticker_lines[0], "        last:1210.50");
+        assert_eq!(ticker_lines[4], "  volume 24h:2500.50000000");
+
+        // the ticker is not refreshed before TICKER_REFRESH_INTERVAL is elapsed
+        state.on_tick(&mut ctx);
+        assert_eq!(ctx.model.pending_requests, 0);
+
+        state.ticker_refreshed_at = Instant::now().checked_sub(TICKER_REFRESH_INTERVAL);
+        state.on_tick(&mut ctx);
+        assert_eq!(ctx.model.pending_requests, 1);
+
+        // nor once Home is left for another state
+        state.ticker_refreshed_at = Instant::now().checked_sub(TICKER_REFRESH_INTERVAL);
+        state.on_exit(&mut ctx);
+        state.on_tick(&mut ctx);
+        assert_eq!(ctx.model.pending_requests, 1);
+
+        let event = Event::Key {
+            key_code: KeyCode::Char('r'),
+        };
+        let to_state = state.on_event(event, &mut ctx);
+        assert_eq!(to_state, None);
+        assert_eq!(ctx.model.pending_requests, 2);
         assert_eq!(
             ctx.model
                 .favorites_asset_pairs_info_stateful
@@ -319,6 +748,52 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_watchlist_keys() {
+        let mut ctx = Context::new_for_testing(Box::new(MockRestAPI::new()));
+        ctx.model
+            .favorites_asset_pairs_stateful
+            .push("XBT/EUR".to_owned());
+
+        let mut state = HomeState::default();
+        let mut send = |state: &mut HomeState, key_code| {
+            assert_eq!(state.on_event(Event::Key { key_code }, &mut ctx), None);
+        };
+
+        // new watchlist, the characters are not shortcuts while typing
+        send(&mut state, KeyCode::Char('w'));
+        assert!(state.is_editing());
+        for c in "fx".chars() {
+            send(&mut state, KeyCode::Char(c));
+        }
+        send(&mut state, KeyCode::Enter);
+        assert!(!state.is_editing());
+
+        // renamed
+        send(&mut state, KeyCode::Char('e'));
+        send(&mut state, KeyCode::Backspace);
+        send(&mut state, KeyCode::Char('t'));
+        send(&mut state, KeyCode::Enter);
+
+        // back to the first one to move its pair to the new one
+        send(&mut state, KeyCode::BackTab);
+        send(&mut state, KeyCode::Down);
+        send(&mut state, KeyCode::Char('m'));
+        send(&mut state, KeyCode::Tab);
+
+        // deleted only once confirmed
+        send(&mut state, KeyCode::Char('x'));
+        send(&mut state, KeyCode::Char('n'));
+        send(&mut state, KeyCode::Char('x'));
+        assert!(state.is_editing());
+        send(&mut state, KeyCode::Char('y'));
+        assert!(!state.is_editing());
+
+        assert_eq!(ctx.model.watchlists.len(), 1);
+        assert_eq!(ctx.model.active_watchlist_name(), "favorites");
+        assert!(ctx.model.favorites_asset_pairs_stateful.items.is_empty());
+    }
+
     #[test]
     fn test_ui() {
         let backend = TestBackend::new(7, 4);
@@ -344,10 +819,36 @@ mod tests {
         terminal.backend().assert_buffer(&expected);
     }
 
+    #[test]
+    fn test_ui_offline() {
+        let backend = TestBackend::new(60, 4);
+        let mut terminal = Terminal::new(backend).unwrap();
+        let mut ctx = Context::new_for_testing(Box::new(MockRestAPI::new()));
+        ctx.model.offline = true;
+        ctx.model.assets_saved_at = Some(unix_now() - 7200);
+
+        let state = HomeState::default();
+
+        terminal
+            .draw(|f| {
+                state.ui(f, &mut ctx);
+            })
+            .unwrap();
+
+        let buffer = terminal.backend().buffer();
+        let title = (0..60)
+            .map(|x| buffer.get(x, 0).symbol.clone())
+            .collect::<String>();
+        assert_eq!(
+            title,
+            "╭──── Home State [public-only] [offline, data 2h old] ─────╮"
+        );
+    }
+
     #[test]
     fn test_state_help() -> Result<(), String> {
         let state = HomeState::default();
-        assert_eq!(state.help_text().len(), 203);
+        assert_eq!(state.help_text().len(), 748);
 
         Ok(())
     }
@@ -0,0 +1,362 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crossterm::event::KeyCode;
+use tui::{backend::Backend, Frame};
+
+use crate::app::Context;
+use crate::kraken::messages::OrderInfo;
+use crate::kraken::worker::Request;
+use crate::stm::{events::Event, State, States};
+use crate::ui::{
+    core::{centered_rect, draw_box, draw_popup},
+    format::format_age,
+    list_stateful_widget::draw_stateful_list,
+};
+
+// Open Orders State
+#[derive(Default)]
+pub struct OpenOrdersState {
+    // transaction ids in the same order as the lines of the list
+    order_ids: Vec<String>,
+    // order waiting for the user confirmation before being cancelled
+    confirm_cancel: Option<String>,
+    // outcome of the last cancel request
+    status: Option<String>,
+}
+
+impl OpenOrdersState {
+    fn refresh(ctx: &mut Context) {
+        if ctx.model.authenticated {
+            ctx.request(Request::OpenOrders);
+        }
+    }
+
+    fn selected_order_id(&self, ctx: &Context) -> Option<String> {
+        let index = ctx.model.open_orders_stateful.state.selected()?;
+        self.order_ids.get(index).cloned()
+    }
+
+    // newest order first
+    fn show_open_orders(&mut self, ctx: &mut Context) {
+        ctx.model.open_orders_stateful.clear();
+        self.order_ids.clear();
+
+        if !ctx.model.authenticated {
+            ctx.model
+                .open_orders_stateful
+                .push("API credentials required, see the README".to_owned());
+            return;
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0.0, |now| now.as_secs_f64());
+
+        let mut orders = ctx.model.open_orders.iter().collect::<Vec<_>>();
+        orders.sort_by(|(_, a), (_, b)| b.opentm.total_cmp(&a.opentm));
+
+        let lines = orders
+            .iter()
+            .map(|(_, order)| OpenOrdersState::format_order(order, now))
+            .collect::<Vec<_>>();
+        self.order_ids = orders.into_iter().map(|(txid, _)| txid.clone()).collect();
+
+        for line in lines {
+            ctx.model.open_orders_stateful.push(line);
+        }
+    }
+
+    fn format_order(order: &OrderInfo, now: f64) -> String {
+        format!(
+            "{:<10} {:<4} {:<10} {:>14} {:>14} {:>14} {:>4}",
+            order.descr.pair,
+            order.descr.side,
+            order.descr.ordertype,
+            order.descr.price,
+            order.vol,
+            order.vol_exec,
+            format_age(now - order.opentm)
+        )
+    }
+
+    fn title(&self, ctx: &Context) -> String {
+        match &self.status {
+            Some(status) => format!(" Open Orders {} - {status} ", ctx.model.open_orders.len()),
+            None => format!(" Open Orders {} ", ctx.model.open_orders.len()),
+        }
+    }
+}
+
+impl State for OpenOrdersState {
+    fn on_enter(&mut self, ctx: &mut Context) {
+        self.confirm_cancel = None;
+        self.status = None;
+        self.show_open_orders(ctx);
+        OpenOrdersState::refresh(ctx);
+    }
+
+    fn on_response(&mut self, event: &Event, ctx: &mut Context) {
+        match event {
+            Event::OpenOrders { loaded } => {
+                if !loaded {
+                    ctx.debug(
+                        "[OpenOrdersS] on_response kraken_api open_orders failed.".to_owned(),
+                    );
+                }
+                self.show_open_orders(ctx);
+            }
+            Event::OrderCancelled { txid, result } => {
+                self.status = Some(match result {
+                    Ok(()) => format!("order {txid} cancelled"),
+                    Err(e) => format!("cancel of order {txid} failed, {e}"),
+                });
+                self.show_open_orders(ctx);
+                OpenOrdersState::refresh(ctx);
+            }
+            _ => {}
+        }
+    }
+
+    fn on_event(&mut self, event: Event, ctx: &mut Context) -> Option<States> {
+        // the confirmation popup captures the keys until it is answered
+        if let Some(txid) = self.confirm_cancel.take() {
+            if let Event::Key {
+                key_code: KeyCode::Char('y'),
+            } = event
+            {
+                self.status = Some(format!("cancelling order {txid}"));
+                ctx.request(Request::CancelOrder { txid });
+            }
+            return None;
+        }
+
+        match event {
+            Event::Key {
+                key_code: KeyCode::Esc,
+            } => Some(States::Home),
+            Event::Key {
+                key_code: KeyCode::Char('r'),
+            } => {
+                OpenOrdersState::refresh(ctx);
+                None
+            }
+            Event::Key {
+                key_code: KeyCode::Char('x'),
+            } => {
+                self.confirm_cancel = self.selected_order_id(ctx);
+                None
+            }
+            Event::Key {
+                key_code: KeyCode::Down,
+            } => {
+                ctx.model.open_orders_stateful.next();
+                None
+            }
+            Event::Key {
+                key_code: KeyCode::Up,
+            } => {
+                ctx.model.open_orders_stateful.previous();
+                None
+            }
+            Event::Key {
+                key_code: KeyCode::Left,
+            } => {
+                ctx.model.open_orders_stateful.unselect();
+                None
+            }
+            _ => {
+                ctx.debug(format!("[OpenOrdersS] on_event {event:?} not match"));
+                None
+            }
+        }
+    }
+
+    fn ui<B: Backend>(&self, f: &mut Frame<B>, ctx: &mut Context) {
+        let size = f.size();
+        draw_box(f, size, &self.title(ctx));
+
+        let bbox = centered_rect(95, 90, size);
+        draw_stateful_list(
+            f,
+            bbox,
+            " pair side type price volume filled age ",
+            &mut ctx.model.open_orders_stateful,
+            false,
+        );
+
+        if let Some(txid) = &self.confirm_cancel {
+            let description = ctx
+                .model
+                .open_orders
+                .get(txid)
+                .map(|order| order.descr.order.clone())
+                .unwrap_or_default();
+            draw_popup(
+                f,
+                centered_rect(50, 30, size),
+                " Cancel Order ",
+                &format!("cancel the order {txid} {description} ? (y/n)"),
+            );
+        }
+    }
+
+    fn help_text(&self) -> &str {
+        r##"
+        ESC    -> home
+        UP     -> previous
+        DOWN   -> next
+        LEFT   -> unselect
+        r      -> refresh
+        x      -> cancel the selected order
+        D      -> show Debug
+        "##
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::KeyCode;
+    use mockall::predicate::eq;
+    use tui::{backend::TestBackend, buffer::Buffer, Terminal};
+
+    use crate::kraken::messages::{CancelOrderResponse, OpenOrdersResponse, OrderDescription};
+    use crate::{kraken::client::MockRestAPI, stm::events::Event};
+
+    use super::*;
+
+    fn order(pair: &str, opentm: f64) -> OrderInfo {
+        OrderInfo {
+            status: "open".to_owned(),
+            opentm,
+            descr: OrderDescription {
+                pair: pair.to_owned(),
+                side: "buy".to_owned(),
+                ordertype: "limit".to_owned(),
+                price: "30000.0".to_owned(),
+                ..OrderDescription::default()
+            },
+            vol: "1.0".to_owned(),
+            vol_exec: "0.5".to_owned(),
+            ..OrderInfo::default()
+        }
+    }
+
+    fn key(key_code: KeyCode) -> Event {
+        Event::Key { key_code }
+    }
+
+    #[test]
+    fn test_cancel_order() {
+        let mut mock_client = Box::new(MockRestAPI::new());
+        mock_client.expect_open_orders().times(3).returning(|| {
+            let mut response = OpenOrdersResponse::default();
+            response
+                .open
+                .insert("OLD".to_owned(), order("ETHUSD", 10.0));
+            response
+                .open
+                .insert("NEW".to_owned(), order("XBTUSD", 20.0));
+            Ok(response)
+        });
+        mock_client
+            .expect_cancel_order()
+            .with(eq("NEW"))
+            .once()
+            .returning(|_| {
+                Ok(CancelOrderResponse {
+                    count: 1,
+                    pending: false,
+                })
+            });
+        let mut ctx = Context::new_for_testing(mock_client);
+        ctx.model.authenticated = true;
+
+        let mut state = OpenOrdersState::default();
+        state.on_enter(&mut ctx);
+        for event in ctx.poll_responses() {
+            state.on_response(&event, &mut ctx);
+        }
+        assert_eq!(state.order_ids, vec!["NEW", "OLD"]);
+        assert!(ctx.model.open_orders_stateful.items[0].starts_with(
+            "XBTUSD     buy  limit             30000.0            1.0            0.5"
+        ));
+
+        // nothing selected, nothing to confirm
+        assert_eq!(state.on_event(key(KeyCode::Char('x')), &mut ctx), None);
+        assert_eq!(state.confirm_cancel, None);
+
+        // the answer 'n' discards the cancellation
+        state.on_event(key(KeyCode::Down), &mut ctx);
+        state.on_event(key(KeyCode::Char('x')), &mut ctx);
+        assert_eq!(state.confirm_cancel, Some("NEW".to_owned()));
+        assert_eq!(state.on_event(key(KeyCode::Char('n')), &mut ctx), None);
+        assert_eq!(state.confirm_cancel, None);
+        assert_eq!(ctx.model.pending_requests, 0);
+
+        state.on_event(key(KeyCode::Char('x')), &mut ctx);
+        assert_eq!(state.on_event(key(KeyCode::Char('y')), &mut ctx), None);
+        for _ in 0..2 {
+            for event in ctx.poll_responses() {
+                state.on_response(&event, &mut ctx);
+            }
+        }
+        assert_eq!(state.title(&ctx), " Open Orders 2 - order NEW cancelled ");
+
+        let event = Event::OrderCancelled {
+            txid: "OLD".to_owned(),
+            result: Err("kraken error EOrder:Unknown order".to_owned()),
+        };
+        state.on_response(&event, &mut ctx);
+        assert_eq!(
+            state.title(&ctx),
+            " Open Orders 2 - cancel of order OLD failed, kraken error EOrder:Unknown order "
+        );
+
+        assert_eq!(
+            state.on_event(key(KeyCode::Esc), &mut ctx),
+            Some(States::Home)
+        );
+    }
+
+    #[test]
+    fn test_not_authenticated() {
+        let mut ctx = Context::new_for_testing(Box::new(MockRestAPI::new()));
+
+        let mut state = OpenOrdersState::default();
+        state.on_enter(&mut ctx);
+
+        assert_eq!(ctx.model.pending_requests, 0);
+        assert_eq!(ctx.model.open_orders_stateful.items.len(), 1);
+    }
+
+    #[test]
+    fn test_ui() {
+        let backend = TestBackend::new(7, 4);
+        let mut terminal = Terminal::new(backend).unwrap();
+        let mut ctx = Context::new_for_testing(Box::new(MockRestAPI::new()));
+
+        let state = OpenOrdersState::default();
+
+        terminal
+            .draw(|f| {
+                state.ui(f, &mut ctx);
+            })
+            .unwrap();
+
+        #[rustfmt::skip]
+        let expected = Buffer::with_lines(vec![
+            "┌ pai┐╮",
+            "│    ││",
+            "└────┘│",
+            "╰─────╯"
+            ]);
+
+        terminal.backend().assert_buffer(&expected);
+    }
+
+    #[test]
+    fn test_state_help() {
+        let state = OpenOrdersState::default();
+        assert_eq!(state.help_text().len(), 208);
+    }
+}
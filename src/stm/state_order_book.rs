@@ -0,0 +1,420 @@
+use crossterm::event::KeyCode;
+use tui::{backend::Backend, Frame};
+
+use crate::app::Context;
+use crate::kraken::messages::{cumulative_volumes, OrderBookEntry};
+use crate::kraken::worker::Request;
+use crate::kraken::ws_messages::{Feed, Subscription};
+use crate::stm::{events::Event, selected_pair_name, State, States};
+use crate::ui::{
+    core::{draw_box, split_columns},
+    list_stateful_widget::draw_stateful_list,
+};
+
+const DEFAULT_DEPTH: u32 = 25;
+const MAX_DEPTH: u32 = 500;
+// depths accepted by the websocket book channel
+const STREAM_DEPTHS: [u32; 5] = [10, 25, 100, 500, 1000];
+
+// Order Book State
+pub struct OrderBookState {
+    asset_pair: Option<String>,
+    depth: u32,
+}
+
+impl Default for OrderBookState {
+    fn default() -> Self {
+        Self {
+            asset_pair: None,
+            depth: DEFAULT_DEPTH,
+        }
+    }
+}
+
+impl OrderBookState {
+    fn refresh(&self, ctx: &mut Context) {
+        // while streaming a new subscription brings a fresh snapshot instead
+        if ctx.model.streaming {
+            if let Some(subscription) = self.subscription(ctx) {
+                ctx.unsubscribe(subscription.clone());
+                ctx.subscribe(subscription);
+            }
+            return;
+        }
+        if let Some(asset_pair) = &self.asset_pair {
+            ctx.request(Request::OrderBook {
+                asset_pair: asset_pair.clone(),
+                depth: self.depth,
+            });
+        }
+    }
+
+    // the book is kept up to date by the websocket while it is visible
+    fn subscription(&self, ctx: &Context) -> Option<Subscription> {
+        let pair = ctx.model.wsname(self.asset_pair.as_ref()?)?;
+        let depth = STREAM_DEPTHS
+            .iter()
+            .copied()
+            .find(|depth| *depth >= self.depth)
+            .unwrap_or(MAX_DEPTH);
+        Some(Subscription::new(&pair, Feed::Book(depth)))
+    }
+
+    fn change_depth(&mut self, depth: u32, ctx: &mut Context) {
+        if let Some(subscription) = self.subscription(ctx) {
+            ctx.unsubscribe(subscription);
+        }
+        self.depth = depth;
+        if let Some(subscription) = self.subscription(ctx) {
+            ctx.subscribe(subscription);
+        }
+        if !ctx.model.streaming {
+            self.refresh(ctx);
+        }
+    }
+
+    fn show_order_book(&self, ctx: &mut Context) {
+        ctx.model.order_book_bids_stateful.clear();
+        ctx.model.order_book_asks_stateful.clear();
+
+        if let Some(asset_pair) = &self.asset_pair {
+            let lot_decimals = ctx
+                .model
+                .asset_pairs
+                .get(asset_pair)
+                .map_or(8, |pair| usize::try_from(pair.lot_decimals).unwrap_or(8));
+
+            // a streamed book has the levels of the subscribed depth, which can be deeper
+            let depth = usize::try_from(self.depth).unwrap_or(usize::MAX);
+            if let Some(order_book) = ctx.model.order_books.get(asset_pair) {
+                let bids = OrderBookState::format_entries(&order_book.bids, depth, lot_decimals);
+                let asks = OrderBookState::format_entries(&order_book.asks, depth, lot_decimals);
+
+                for line in bids {
+                    ctx.model.order_book_bids_stateful.push(line);
+                }
+                for line in asks {
+                    ctx.model.order_book_asks_stateful.push(line);
+                }
+            }
+        }
+    }
+
+    // price, volume and cumulative volume, one line for each of the depth first price levels
+    fn format_entries(
+        entries: &[OrderBookEntry],
+        depth: usize,
+        lot_decimals: usize,
+    ) -> Vec<String> {
+        let entries = &entries[..entries.len().min(depth)];
+        entries
+            .iter()
+            .zip(cumulative_volumes(entries))
+            .map(|(entry, cumulative)| {
+                format!(
+                    "{:>14} {:>16.lot_decimals$} {:>16.lot_decimals$}",
+                    entry.0,
+                    entry.volume().unwrap_or(0.0),
+                    cumulative
+                )
+            })
+            .collect()
+    }
+
+    fn title(&self, ctx: &Context) -> String {
+        let order_book = self
+            .asset_pair
+            .as_ref()
+            .and_then(|asset_pair| ctx.model.order_books.get(asset_pair));
+
+        let name = selected_pair_name(ctx, self.asset_pair.as_ref());
+
+        let resyncing = self
+            .asset_pair
+            .as_ref()
+            .filter(|asset_pair| ctx.model.resyncing_books.contains(*asset_pair))
+            .map_or("", |_| "(resyncing...) ");
+
+        match order_book.and_then(|ob| ob.spread().zip(ob.spread_pct())) {
+            Some((spread, spread_pct)) => format!(
+                " Order Book {name} depth:{} spread:{spread:.8} ({spread_pct:.3}%) {resyncing}",
+                self.depth
+            ),
+            _ => format!(" Order Book {name} depth:{} {resyncing}", self.depth),
+        }
+    }
+}
+
+impl State for OrderBookState {
+    fn on_enter(&mut self, ctx: &mut Context) {
+        self.asset_pair = ctx.model.selected_favorite_key();
+        if let Some(subscription) = self.subscription(ctx) {
+            ctx.subscribe(subscription);
+        }
+        self.show_order_book(ctx);
+        if !ctx.model.streaming {
+            self.refresh(ctx);
+        }
+    }
+
+    // the book is not streamed anymore once it is not visible
+    fn on_exit(&mut self, ctx: &mut Context) {
+        if let Some(subscription) = self.subscription(ctx) {
+            ctx.unsubscribe(subscription);
+        }
+    }
+
+    fn on_response(&mut self, event: &Event, ctx: &mut Context) {
+        if let Event::OrderBook { asset_pair, loaded } = event {
+            if self.asset_pair.as_ref() == Some(asset_pair) {
+                if !loaded {
+                    ctx.debug(format!(
+                        "[OrderBookS] on_response kraken_api order_book {asset_pair} failed."
+                    ));
+                }
+                self.show_order_book(ctx);
+            }
+        }
+    }
+
+    fn on_event(&mut self, event: Event, ctx: &mut Context) -> Option<States> {
+        match event {
+            Event::Key {
+                key_code: KeyCode::Esc,
+            } => Some(States::Home),
+            Event::Key {
+                key_code: KeyCode::Char('r'),
+            } => {
+                self.refresh(ctx);
+                None
+            }
+            Event::Key {
+                key_code: KeyCode::Char('+'),
+            } => {
+                self.change_depth((self.depth * 2).min(MAX_DEPTH), ctx);
+                None
+            }
+            Event::Key {
+                key_code: KeyCode::Char('-'),
+            } => {
+                self.change_depth((self.depth / 2).max(1), ctx);
+                None
+            }
+            Event::Key {
+                key_code: KeyCode::Down,
+            } => {
+                ctx.model.order_book_bids_stateful.next();
+                ctx.model.order_book_asks_stateful.next();
+                None
+            }
+            Event::Key {
+                key_code: KeyCode::Up,
+            } => {
+                ctx.model.order_book_bids_stateful.previous();
+                ctx.model.order_book_asks_stateful.previous();
+                None
+            }
+            Event::Key {
+                key_code: KeyCode::Left,
+            } => {
+                ctx.model.order_book_bids_stateful.unselect();
+                ctx.model.order_book_asks_stateful.unselect();
+                None
+            }
+            _ => {
+                ctx.debug(format!("[OrderBookS] on_event {event:?} not match"));
+                None
+            }
+        }
+    }
+
+    fn ui<B: Backend>(&self, f: &mut Frame<B>, ctx: &mut Context) {
+        let size = f.size();
+        draw_box(f, size, &self.title(ctx));
+
+        let bboxs = split_columns(2, size);
+
+        draw_stateful_list(
+            f,
+            bboxs[0],
+            " bids: price volume cumulative ",
+            &mut ctx.model.order_book_bids_stateful,
+            false,
+        );
+        draw_stateful_list(
+            f,
+            bboxs[1],
+            " asks: price volume cumulative ",
+            &mut ctx.model.order_book_asks_stateful,
+            false,
+        );
+    }
+
+    fn help_text(&self) -> &str {
+        r##"
+        ESC    -> home
+        UP     -> previous
+        DOWN   -> next
+        LEFT   -> unselect
+        r      -> refresh
+        +      -> double the depth
+        -      -> halve the depth
+        D      -> show Debug
+        "##
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::KeyCode;
+    use mockall::predicate::eq;
+    use tui::{backend::TestBackend, buffer::Buffer, Terminal};
+
+    use crate::kraken::messages::{OrderBook, OrderBookResponse};
+    use crate::{kraken::client::MockRestAPI, stm::events::Event};
+
+    use super::*;
+
+    fn order_book() -> OrderBook {
+        OrderBook {
+            asks: vec![
+                OrderBookEntry("30010.0".to_owned(), "1.5".to_owned(), 0),
+                OrderBookEntry("30020.0".to_owned(), "2.0".to_owned(), 0),
+            ],
+            bids: vec![OrderBookEntry("30000.0".to_owned(), "0.5".to_owned(), 0)],
+        }
+    }
+
+    #[test]
+    fn test_on_event() {
+        let mut mock_client = Box::new(MockRestAPI::new());
+        mock_client
+            .expect_order_book()
+            .with(eq("XXBTZUSD"), eq(50))
+            .once()
+            .returning(|_, _| {
+                let mut order_books = OrderBookResponse::new();
+                order_books.insert("XXBTZUSD".to_owned(), order_book());
+                Ok(order_books)
+            });
+        let mut ctx = Context::new_for_testing(mock_client);
+
+        let mut state = OrderBookState {
+            asset_pair: Some("XXBTZUSD".to_owned()),
+            ..OrderBookState::default()
+        };
+
+        let event = Event::Key {
+            key_code: KeyCode::Char('+'),
+        };
+        assert_eq!(state.on_event(event, &mut ctx), None);
+        assert_eq!(state.depth, 50);
+
+        for event in ctx.poll_responses() {
+            state.on_response(&event, &mut ctx);
+        }
+
+        assert_eq!(ctx.model.order_book_asks_stateful.items.len(), 2);
+        assert_eq!(ctx.model.order_book_bids_stateful.items.len(), 1);
+        assert_eq!(
+            ctx.model.order_book_asks_stateful.items[1],
+            "       30020.0       2.00000000       3.50000000"
+        );
+        assert_eq!(
+            state.title(&ctx),
+            " Order Book XXBTZUSD depth:50 spread:10.00000000 (0.033%) "
+        );
+
+        ctx.model.resyncing_books.insert("XXBTZUSD".to_owned());
+        assert_eq!(
+            state.title(&ctx),
+            " Order Book XXBTZUSD depth:50 spread:10.00000000 (0.033%) (resyncing...) "
+        );
+
+        // the levels beyond the depth are not shown
+        state.depth = 1;
+        state.show_order_book(&mut ctx);
+        assert_eq!(
+            ctx.model.order_book_asks_stateful.items,
+            vec!["       30010.0       1.50000000       1.50000000"]
+        );
+
+        let event = Event::Key {
+            key_code: KeyCode::Down,
+        };
+        assert_eq!(state.on_event(event, &mut ctx), None);
+        assert_eq!(ctx.model.order_book_asks_stateful.state.selected(), Some(0));
+
+        let event = Event::Key {
+            key_code: KeyCode::Esc,
+        };
+        assert_eq!(state.on_event(event, &mut ctx), Some(States::Home));
+    }
+
+    #[test]
+    fn test_on_enter_without_selection() {
+        let mut ctx = Context::new_for_testing(Box::new(MockRestAPI::new()));
+
+        let mut state = OrderBookState::default();
+        state.on_enter(&mut ctx);
+
+        assert_eq!(state.asset_pair, None);
+        assert_eq!(ctx.model.pending_requests, 0);
+    }
+
+    #[test]
+    fn test_ui() {
+        let backend = TestBackend::new(7, 4);
+        let mut terminal = Terminal::new(backend).unwrap();
+        let mut ctx = Context::new_for_testing(Box::new(MockRestAPI::new()));
+
+        let state = OrderBookState::default();
+
+        terminal
+            .draw(|f| {
+                state.ui(f, &mut ctx);
+            })
+            .unwrap();
+
+        #[rustfmt::skip]
+        let expected = Buffer::with_lines(vec![
+            " Orde─╮",
+            "│ ┌┌┐ │",
+            "│ └└┘ │",
+            "╰─────╯"
+            ]);
+
+        terminal.backend().assert_buffer(&expected);
+    }
+
+    #[test]
+    fn test_change_depth() {
+        let mut ctx = Context::new_for_testing(Box::new(MockRestAPI::new()));
+
+        let mut state = OrderBookState {
+            depth: 2,
+            ..OrderBookState::default()
+        };
+
+        // halved down to 1, doubled up to MAX_DEPTH
+        for _ in 0..2 {
+            let event = Event::Key {
+                key_code: KeyCode::Char('-'),
+            };
+            assert_eq!(state.on_event(event, &mut ctx), None);
+        }
+        assert_eq!(state.depth, 1);
+
+        state.depth = 400;
+        for _ in 0..2 {
+            let event = Event::Key {
+                key_code: KeyCode::Char('+'),
+            };
+            assert_eq!(state.on_event(event, &mut ctx), None);
+        }
+        assert_eq!(state.depth, MAX_DEPTH);
+
+        // nothing to request without a selected pair
+        assert_eq!(ctx.model.pending_requests, 0);
+    }
+}
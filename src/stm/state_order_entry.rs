@@ -0,0 +1,673 @@
+use crossterm::event::KeyCode;
+use krakenrs::AssetPair;
+use thiserror::Error;
+use tui::{backend::Backend, Frame};
+
+use crate::app::Context;
+use crate::kraken::messages::{AddOrderRequest, TickerInfo};
+use crate::kraken::worker::Request;
+use crate::stm::{events::Event, State, States};
+use crate::ui::core::{centered_rect, draw_box, draw_popup};
+
+#[derive(Error, Debug, PartialEq)]
+pub enum OrderError {
+    #[error("invalid price {0:?}")]
+    InvalidPrice(String),
+    #[error("invalid volume {0:?}")]
+    InvalidVolume(String),
+    #[error("the price allows at most {0} decimals")]
+    PriceDecimals(u64),
+    #[error("the volume allows at most {0} decimals")]
+    VolumeDecimals(u64),
+    #[error("the volume is below the minimum order of {0}")]
+    BelowOrderMin(String),
+}
+
+// fields of the form, in the order they are visited with TAB
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum Field {
+    #[default]
+    Side,
+    OrderType,
+    Price,
+    Volume,
+    Mode,
+}
+
+impl Field {
+    const ALL: [Field; 5] = [
+        Field::Side,
+        Field::OrderType,
+        Field::Price,
+        Field::Volume,
+        Field::Mode,
+    ];
+
+    fn index(self) -> usize {
+        Field::ALL
+            .iter()
+            .position(|field| *field == self)
+            .unwrap_or(0)
+    }
+
+    fn next(self) -> Field {
+        Field::ALL[(self.index() + 1) % Field::ALL.len()]
+    }
+
+    fn previous(self) -> Field {
+        Field::ALL[(self.index() + Field::ALL.len() - 1) % Field::ALL.len()]
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct OrderForm {
+    sell: bool,
+    limit: bool,
+    price: String,
+    volume: String,
+    // the order is placed on the exchange, by default it is only validated
+    live: bool,
+}
+
+impl OrderForm {
+    fn side(&self) -> &'static str {
+        if self.sell {
+            "sell"
+        } else {
+            "buy"
+        }
+    }
+
+    fn ordertype(&self) -> &'static str {
+        if self.limit {
+            "limit"
+        } else {
+            "market"
+        }
+    }
+
+    // the limits are the ones shown in the Home details of the pair
+    pub fn to_request(
+        &self,
+        pair_key: &str,
+        pair: &AssetPair,
+    ) -> Result<AddOrderRequest, OrderError> {
+        if self.limit {
+            parse_positive(&self.price)
+                .ok_or_else(|| OrderError::InvalidPrice(self.price.clone()))?;
+            if decimals(&self.price) > pair.pair_decimals {
+                return Err(OrderError::PriceDecimals(pair.pair_decimals));
+            }
+        }
+
+        let volume = parse_positive(&self.volume)
+            .ok_or_else(|| OrderError::InvalidVolume(self.volume.clone()))?;
+        if decimals(&self.volume) > pair.lot_decimals {
+            return Err(OrderError::VolumeDecimals(pair.lot_decimals));
+        }
+        if let Some(ordermin) = &pair.ordermin {
+            let ordermin = ordermin.to_string();
+            if matches!(ordermin.parse::<f64>(), Ok(ordermin) if volume < ordermin) {
+                return Err(OrderError::BelowOrderMin(ordermin));
+            }
+        }
+
+        Ok(AddOrderRequest {
+            pair: pair_key.to_owned(),
+            side: self.side().to_owned(),
+            ordertype: self.ordertype().to_owned(),
+            price: if self.limit {
+                self.price.clone()
+            } else {
+                String::new()
+            },
+            volume: self.volume.clone(),
+            validate: !self.live,
+        })
+    }
+
+    // estimated cost and fee in the quote currency, a market order is priced at the best
+    // ask or bid of the last ticker.
+    pub fn estimate(&self, pair: &AssetPair, ticker: Option<&TickerInfo>) -> Option<(f64, f64)> {
+        let price = if self.limit {
+            parse_positive(&self.price)
+        } else {
+            ticker.and_then(|ticker| {
+                let best = if self.sell {
+                    ticker.bid()
+                } else {
+                    ticker.ask()
+                };
+                best.or_else(|| ticker.last())
+            })
+        }?;
+        let cost = price * parse_positive(&self.volume)?;
+
+        Some((cost, cost * taker_fee(pair).unwrap_or(0.0) / 100.0))
+    }
+}
+
+fn parse_positive(value: &str) -> Option<f64> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|value| value.is_finite() && *value > 0.0)
+}
+
+fn decimals(value: &str) -> u64 {
+    value
+        .split_once('.')
+        .map_or(0, |(_, decimals)| decimals.len() as u64)
+}
+
+// fees are [volume, percent] tiers, without the 30 days volume the first tier applies
+fn taker_fee(pair: &AssetPair) -> Option<f64> {
+    pair.fees
+        .first()
+        .and_then(|tier| tier.get(1))
+        .and_then(|percent| percent.to_string().parse().ok())
+}
+
+// Order Entry State
+#[derive(Default)]
+pub struct OrderEntryState {
+    asset_pair: Option<String>,
+    form: OrderForm,
+    focus: Field,
+    // order waiting for the user confirmation before being sent
+    confirm: Option<AddOrderRequest>,
+    // outcome of the last validation or submission
+    status: Option<String>,
+}
+
+impl OrderEntryState {
+    fn pair<'a>(&self, ctx: &'a Context) -> Option<&'a AssetPair> {
+        self.asset_pair
+            .as_ref()
+            .and_then(|asset_pair| ctx.model.asset_pairs.get(asset_pair))
+    }
+
+    fn submit(&mut self, ctx: &Context) {
+        if !ctx.model.authenticated {
+            self.status = Some("API credentials required, see the README".to_owned());
+            return;
+        }
+
+        let (Some(asset_pair), Some(pair)) = (&self.asset_pair, self.pair(ctx)) else {
+            self.status = Some("no asset pair selected".to_owned());
+            return;
+        };
+
+        match self.form.to_request(asset_pair, pair) {
+            Ok(order) => {
+                self.confirm = Some(order);
+                self.status = None;
+            }
+            Err(e) => self.status = Some(e.to_string()),
+        }
+    }
+
+    fn on_field_key(&mut self, key_code: KeyCode) {
+        let form = &mut self.form;
+        match (self.focus, key_code) {
+            (Field::Side, KeyCode::Left | KeyCode::Right | KeyCode::Char(' ')) => {
+                form.sell = !form.sell;
+            }
+            (Field::OrderType, KeyCode::Left | KeyCode::Right | KeyCode::Char(' ')) => {
+                form.limit = !form.limit;
+            }
+            (Field::Mode, KeyCode::Left | KeyCode::Right | KeyCode::Char(' ')) => {
+                form.live = !form.live;
+            }
+            (Field::Price, KeyCode::Char(c)) if c.is_ascii_digit() || c == '.' => {
+                form.price.push(c);
+            }
+            (Field::Volume, KeyCode::Char(c)) if c.is_ascii_digit() || c == '.' => {
+                form.volume.push(c);
+            }
+            (Field::Price, KeyCode::Backspace) => {
+                form.price.pop();
+            }
+            (Field::Volume, KeyCode::Backspace) => {
+                form.volume.pop();
+            }
+            _ => {}
+        }
+    }
+
+    fn form_text(&self, ctx: &Context) -> String {
+        let form = &self.form;
+        let pair = self.pair(ctx);
+        let marker = |field: Field| if self.focus == field { '>' } else { ' ' };
+        let price = if form.limit {
+            form.price.as_str()
+        } else {
+            "market"
+        };
+        let mode = if form.live {
+            "submit live"
+        } else {
+            "validate only"
+        };
+
+        let mut lines = vec![
+            format!("{}   side: {}", marker(Field::Side), form.side()),
+            format!("{}   type: {}", marker(Field::OrderType), form.ordertype()),
+            format!("{}  price: {price}", marker(Field::Price)),
+            format!("{} volume: {}", marker(Field::Volume), form.volume),
+            format!("{}   mode: {mode}", marker(Field::Mode)),
+            String::new(),
+        ];
+
+        if let Some(pair) = pair {
+            let ticker = self
+                .asset_pair
+                .as_ref()
+                .and_then(|asset_pair| ctx.model.tickers.get(asset_pair));
+            let decimals = usize::try_from(pair.pair_decimals).unwrap_or(8);
+            match form.estimate(pair, ticker) {
+                Some((cost, fee)) => lines.push(format!(
+                    "    est: cost {cost:.decimals$} fee {fee:.decimals$} {}",
+                    pair.quote
+                )),
+                None => lines.push("    est: -".to_owned()),
+            }
+            lines.push(format!(
+                " limits: ordermin {} pair_decimals {} lot_decimals {}",
+                pair.ordermin
+                    .as_ref()
+                    .map_or_else(|| "-".to_owned(), ToString::to_string),
+                pair.pair_decimals,
+                pair.lot_decimals
+            ));
+        }
+
+        if let Some(status) = &self.status {
+            lines.push(String::new());
+            lines.push(status.clone());
+        }
+
+        lines.join("\n")
+    }
+}
+
+impl State for OrderEntryState {
+    fn on_enter(&mut self, ctx: &mut Context) {
+        self.asset_pair = ctx.model.selected_favorite_key();
+        self.form = OrderForm::default();
+        self.focus = Field::default();
+        self.confirm = None;
+        self.status = None;
+
+        if let Some(asset_pair) = &self.asset_pair {
+            ctx.request(Request::Ticker {
+                asset_pair: asset_pair.clone(),
+            });
+        }
+    }
+
+    fn on_response(&mut self, event: &Event, ctx: &mut Context) {
+        if let Event::OrderAdded { validate, result } = event {
+            let description = ctx
+                .model
+                .added_order
+                .as_ref()
+                .map(|order| format!("{} {}", order.descr.order, order.txid.join(",")))
+                .unwrap_or_default();
+
+            self.status = Some(match (validate, result) {
+                (true, Ok(())) => format!("order validated: {}", description.trim_end()),
+                (false, Ok(())) => format!("order placed: {}", description.trim_end()),
+                (_, Err(e)) => format!("order rejected: {e}"),
+            });
+            if result.is_err() {
+                ctx.debug("[OrderEntryS] on_response kraken_api add_order failed.".to_owned());
+            }
+        }
+    }
+
+    fn on_event(&mut self, event: Event, ctx: &mut Context) -> Option<States> {
+        // the confirmation popup captures the keys until it is answered
+        if let Some(order) = self.confirm.take() {
+            if let Event::Key {
+                key_code: KeyCode::Char('y'),
+            } = event
+            {
+                self.status = Some(if order.validate {
+                    "validating...".to_owned()
+                } else {
+                    "submitting...".to_owned()
+                });
+                ctx.request(Request::AddOrder { order });
+            }
+            return None;
+        }
+
+        match event {
+            Event::Key {
+                key_code: KeyCode::Esc,
+            } => Some(States::Home),
+            Event::Key {
+                key_code: KeyCode::Enter,
+            } => {
+                self.submit(ctx);
+                None
+            }
+            Event::Key {
+                key_code: KeyCode::Tab | KeyCode::Down,
+            } => {
+                self.focus = self.focus.next();
+                None
+            }
+            Event::Key {
+                key_code: KeyCode::BackTab | KeyCode::Up,
+            } => {
+                self.focus = self.focus.previous();
+                None
+            }
+            Event::Key { key_code } => {
+                self.on_field_key(key_code);
+                None
+            }
+            _ => {
+                ctx.debug(format!("[OrderEntryS] on_event {event:?} not match"));
+                None
+            }
+        }
+    }
+
+    fn ui<B: Backend>(&self, f: &mut Frame<B>, ctx: &mut Context) {
+        let size = f.size();
+        let name = self
+            .pair(ctx)
+            .and_then(|pair| pair.wsname.clone())
+            .or_else(|| self.asset_pair.clone())
+            .unwrap_or_default();
+        draw_box(f, size, &format!(" Order Entry {name} "));
+
+        draw_popup(
+            f,
+            centered_rect(70, 60, size),
+            " New Order ",
+            &self.form_text(ctx),
+        );
+
+        if let Some(order) = &self.confirm {
+            let price = if order.price.is_empty() {
+                "market"
+            } else {
+                order.price.as_str()
+            };
+            let action = if order.validate {
+                "validate"
+            } else {
+                "submit LIVE"
+            };
+            draw_popup(
+                f,
+                centered_rect(50, 30, size),
+                " Confirm Order ",
+                &format!(
+                    "{action} {} {} {name} @ {price} ? (y/n)",
+                    order.side, order.volume
+                ),
+            );
+        }
+    }
+
+    fn help_text(&self) -> &str {
+        r##"
+        ESC    -> home
+        TAB    -> next field
+        UP     -> previous field
+        DOWN   -> next field
+        LEFT   -> toggle side, type or mode
+        0-9 .  -> edit price or volume
+        ENTER  -> check and confirm the order
+        D      -> show Debug
+        "##
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::KeyCode;
+    use mockall::predicate::eq;
+    use tui::{backend::TestBackend, buffer::Buffer, Terminal};
+
+    use crate::kraken::messages::{AddOrderDescription, AddOrderResponse};
+    use crate::{
+        kraken::{client::MockRestAPI, error::ClientError},
+        stm::events::Event,
+    };
+
+    use super::*;
+
+    const ASSET_PAIRS_JSON: &str = r#"{
+      "XXBTZUSD": {
+        "alt_name": "XBTUSD", "wsname": "XBT/USD", "aclass_base": "currency", "base": "XXBT",
+        "aclass_quote": "currency", "quote": "ZUSD", "pair_decimals": 1, "lot_decimals": 8,
+        "lot_multiplier": 1, "fees": [[0, 0.26], [50000, 0.24]], "ordermin": "0.0001"
+      }
+    }"#;
+
+    fn key(key_code: KeyCode) -> Event {
+        Event::Key { key_code }
+    }
+
+    fn type_text(state: &mut OrderEntryState, ctx: &mut Context, text: &str) {
+        for c in text.chars() {
+            state.on_event(key(KeyCode::Char(c)), ctx);
+        }
+    }
+
+    fn state_for_testing(ctx: &mut Context) -> OrderEntryState {
+        ctx.model.asset_pairs = serde_json::from_str(ASSET_PAIRS_JSON).unwrap();
+        OrderEntryState {
+            asset_pair: Some("XXBTZUSD".to_owned()),
+            ..OrderEntryState::default()
+        }
+    }
+
+    #[test]
+    fn test_validation() {
+        let pairs: krakenrs::AssetPairsResponse = serde_json::from_str(ASSET_PAIRS_JSON).unwrap();
+        let pair = &pairs["XXBTZUSD"];
+
+        let mut form = OrderForm {
+            limit: true,
+            price: "30000.55".to_owned(),
+            volume: "0.5".to_owned(),
+            ..OrderForm::default()
+        };
+        assert_eq!(
+            form.to_request("XXBTZUSD", pair),
+            Err(OrderError::PriceDecimals(1))
+        );
+
+        form.price = "30000.5".to_owned();
+        let request = form.to_request("XXBTZUSD", pair).unwrap();
+        assert_eq!(request.ordertype, "limit");
+        assert_eq!(request.price, "30000.5");
+
+        form.volume = "0.00001".to_owned();
+        assert_eq!(
+            form.to_request("XXBTZUSD", pair),
+            Err(OrderError::BelowOrderMin("0.0001".to_owned()))
+        );
+        form.volume = "0.123456789".to_owned();
+        assert_eq!(
+            form.to_request("XXBTZUSD", pair),
+            Err(OrderError::VolumeDecimals(8))
+        );
+        form.volume = "1..".to_owned();
+        assert_eq!(
+            form.to_request("XXBTZUSD", pair),
+            Err(OrderError::InvalidVolume("1..".to_owned()))
+        );
+
+        // the price of a market order is ignored
+        form.limit = false;
+        form.price = String::new();
+        form.volume = "2".to_owned();
+        assert_eq!(form.to_request("XXBTZUSD", pair).unwrap().price, "");
+
+        let ticker = TickerInfo {
+            a: vec!["30000.0".to_owned()],
+            b: vec!["29990.0".to_owned()],
+            ..TickerInfo::default()
+        };
+        let (cost, fee) = form.estimate(pair, Some(&ticker)).unwrap();
+        assert!((cost - 60000.0).abs() < 1e-6);
+        assert!((fee - 156.0).abs() < 1e-6);
+        assert_eq!(form.estimate(pair, None), None);
+    }
+
+    #[test]
+    fn test_submit_after_confirmation() {
+        let mut mock_client = Box::new(MockRestAPI::new());
+        mock_client
+            .expect_add_order()
+            .with(eq(AddOrderRequest {
+                pair: "XXBTZUSD".to_owned(),
+                side: "sell".to_owned(),
+                ordertype: "limit".to_owned(),
+                price: "31000".to_owned(),
+                volume: "0.25".to_owned(),
+                validate: true,
+            }))
+            .once()
+            .returning(|_| {
+                Ok(AddOrderResponse {
+                    descr: AddOrderDescription {
+                        order: "sell 0.25000000 XBTUSD @ limit 31000.0".to_owned(),
+                    },
+                    txid: vec![],
+                })
+            });
+        let mut ctx = Context::new_for_testing(mock_client);
+        ctx.model.authenticated = true;
+        let mut state = state_for_testing(&mut ctx);
+
+        state.on_event(key(KeyCode::Right), &mut ctx);
+        state.on_event(key(KeyCode::Tab), &mut ctx);
+        state.on_event(key(KeyCode::Char(' ')), &mut ctx);
+        state.on_event(key(KeyCode::Tab), &mut ctx);
+        type_text(&mut state, &mut ctx, "31000");
+        state.on_event(key(KeyCode::Down), &mut ctx);
+        type_text(&mut state, &mut ctx, "0.25x5");
+        state.on_event(key(KeyCode::Backspace), &mut ctx);
+        assert_eq!(state.form.volume, "0.25");
+        // nothing is placed unless the mode is switched to live
+        assert!(!state.form.live);
+
+        // the answer 'n' discards the order
+        state.on_event(key(KeyCode::Enter), &mut ctx);
+        assert!(state.confirm.is_some());
+        state.on_event(key(KeyCode::Char('n')), &mut ctx);
+        assert_eq!(state.confirm, None);
+        assert_eq!(ctx.model.pending_requests, 0);
+
+        state.on_event(key(KeyCode::Enter), &mut ctx);
+        assert_eq!(state.on_event(key(KeyCode::Char('y')), &mut ctx), None);
+        for event in ctx.poll_responses() {
+            state.on_response(&event, &mut ctx);
+        }
+        assert_eq!(
+            state.status,
+            Some("order validated: sell 0.25000000 XBTUSD @ limit 31000.0".to_owned())
+        );
+
+        assert_eq!(
+            state.on_event(key(KeyCode::Esc), &mut ctx),
+            Some(States::Home)
+        );
+    }
+
+    #[test]
+    fn test_invalid_order_is_not_confirmed() {
+        let mut ctx = Context::new_for_testing(Box::new(MockRestAPI::new()));
+        let mut state = state_for_testing(&mut ctx);
+
+        state.on_event(key(KeyCode::Enter), &mut ctx);
+        assert_eq!(
+            state.status,
+            Some("API credentials required, see the README".to_owned())
+        );
+
+        ctx.model.authenticated = true;
+        state.on_event(key(KeyCode::Enter), &mut ctx);
+        assert_eq!(state.confirm, None);
+        assert_eq!(state.status, Some(r#"invalid volume """#.to_owned()));
+        assert_eq!(ctx.model.pending_requests, 0);
+    }
+
+    #[test]
+    fn test_ui() {
+        let backend = TestBackend::new(7, 4);
+        let mut terminal = Terminal::new(backend).unwrap();
+        let mut ctx = Context::new_for_testing(Box::new(MockRestAPI::new()));
+
+        let state = OrderEntryState::default();
+
+        terminal
+            .draw(|f| {
+                state.ui(f, &mut ctx);
+            })
+            .unwrap();
+
+        #[rustfmt::skip]
+        let expected = Buffer::with_lines(vec![
+            "  N─╮─╮",
+            "│╰──╯ │",
+            "│     │",
+            "╰─────╯"
+            ]);
+
+        terminal.backend().assert_buffer(&expected);
+    }
+
+    #[test]
+    fn test_state_help() {
+        let state = OrderEntryState::default();
+        assert_eq!(state.help_text().len(), 281);
+    }
+
+    #[test]
+    fn test_rejected_order() {
+        let mut mock_client = Box::new(MockRestAPI::new());
+        mock_client.expect_add_order().once().returning(|_| {
+            Err(ClientError::Api(vec![
+                "EOrder:Insufficient funds".to_owned()
+            ]))
+        });
+        let mut ctx = Context::new_for_testing(mock_client);
+        ctx.model.authenticated = true;
+        let mut state = state_for_testing(&mut ctx);
+
+        for _ in 0..3 {
+            state.on_event(key(KeyCode::Down), &mut ctx);
+        }
+        type_text(&mut state, &mut ctx, "1");
+        state.on_event(key(KeyCode::Down), &mut ctx);
+        state.on_event(key(KeyCode::Left), &mut ctx);
+        assert!(state.form.live);
+
+        state.on_event(key(KeyCode::Enter), &mut ctx);
+        assert_eq!(
+            state.confirm.as_ref().map(|order| order.validate),
+            Some(false)
+        );
+        state.on_event(key(KeyCode::Char('y')), &mut ctx);
+        for event in ctx.poll_responses() {
+            state.on_response(&event, &mut ctx);
+        }
+        assert_eq!(
+            state.status,
+            Some("order rejected: kraken error EOrder:Insufficient funds".to_owned())
+        );
+        assert!(state
+            .form_text(&ctx)
+            .ends_with("order rejected: kraken error EOrder:Insufficient funds"));
+    }
+}
@@ -1,11 +1,19 @@
 use crossterm::event::KeyCode;
-use tui::{backend::Backend, Frame};
+use krakenrs::AssetPair;
+use std::collections::HashMap;
+
+use tui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout},
+    Frame,
+};
 
 use crate::app::Context;
+use crate::kraken::worker::Request;
 use crate::stm::{events::Event, State, States};
 use crate::ui::{
     core::{draw_box, split_columns},
-    list_stateful_widget::draw_stateful_list,
+    list_stateful_widget::{draw_highlighted_list, draw_stateful_list},
 };
 
 enum ActiveColumn {
@@ -13,10 +21,23 @@ enum ActiveColumn {
     AssetPairs,
 }
 
+// the side of the pairs listed for the selected asset
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BrowseBy {
+    Base,
+    Quote,
+}
+
 // Search State
 pub struct SearchState {
     pub on_enter_first: bool,
     avtive_column: ActiveColumn,
+    // typed after '/', filters the assets and the asset pairs
+    query: String,
+    editing: bool,
+    browse_by: BrowseBy,
+    // aclass_base or aclass_quote of the listed pairs, depending on browse_by
+    asset_class: Option<String>,
 }
 
 impl Default for SearchState {
@@ -24,12 +45,206 @@ impl Default for SearchState {
         Self {
             on_enter_first: true,
             avtive_column: ActiveColumn::Assets,
+            query: String::new(),
+            editing: false,
+            browse_by: BrowseBy::Base,
+            asset_class: None,
         }
     }
 }
 
+// case-insensitive, an empty query matches everything
+fn matches_query(value: Option<&str>, query: &str) -> bool {
+    query.is_empty()
+        || matches!(value, Some(value) if value.to_ascii_lowercase().contains(&query.to_ascii_lowercase()))
+}
+
 impl SearchState {
-    fn filter_asset_pairs(ctx: &mut Context) {
+    // the keys are captured by the query input, also the global shortcuts
+    pub fn is_editing(&self) -> bool {
+        self.editing
+    }
+
+    fn asset_matches(&self, ctx: &Context, asset: &str) -> bool {
+        let altname = ctx
+            .model
+            .assets
+            .get(asset)
+            .map(|info| info.altname.as_str());
+        matches_query(Some(asset), &self.query) || matches_query(altname, &self.query)
+    }
+
+    fn pair_matches(&self, pair: &AssetPair) -> bool {
+        matches_query(pair.alt_name.as_deref(), &self.query)
+            || matches_query(pair.wsname.as_deref(), &self.query)
+    }
+
+    fn pair_asset<'a>(&self, pair: &'a AssetPair) -> &'a str {
+        match self.browse_by {
+            BrowseBy::Base => &pair.base,
+            BrowseBy::Quote => &pair.quote,
+        }
+    }
+
+    fn pair_in_class(&self, pair: &AssetPair) -> bool {
+        let aclass = match self.browse_by {
+            BrowseBy::Base => &pair.aclass_base,
+            BrowseBy::Quote => &pair.aclass_quote,
+        };
+        match &self.asset_class {
+            Some(class) => class == aclass,
+            None => true,
+        }
+    }
+
+    // the pairs of the asset on the browsed side, in the asset class
+    fn is_listed(&self, pair: &AssetPair, asset: &str) -> bool {
+        self.pair_asset(pair) == asset && self.pair_in_class(pair)
+    }
+
+    // all the assets when browsing by base, otherwise only the ones of the listed pairs
+    fn candidate_assets(&self, ctx: &Context) -> Vec<String> {
+        if self.browse_by == BrowseBy::Base && self.asset_class.is_none() {
+            return ctx.model.assets.keys().cloned().collect();
+        }
+
+        let mut assets = ctx
+            .model
+            .asset_pairs
+            .values()
+            .filter(|pair| self.pair_in_class(pair))
+            .map(|pair| self.pair_asset(pair).to_owned())
+            .collect::<Vec<_>>();
+        assets.sort();
+        assets.dedup();
+        assets
+    }
+
+    // the asset class is the one of the other side, it is reset
+    fn toggle_browse_by(&mut self, ctx: &mut Context) {
+        self.browse_by = match self.browse_by {
+            BrowseBy::Base => BrowseBy::Quote,
+            BrowseBy::Quote => BrowseBy::Base,
+        };
+        self.asset_class = None;
+        self.on_query_changed(ctx);
+    }
+
+    // 'c' cycles through the classes of the pairs and back to all of them
+    fn next_asset_class(&mut self, ctx: &Context) {
+        let mut classes = ctx
+            .model
+            .asset_pairs
+            .values()
+            .map(|pair| match self.browse_by {
+                BrowseBy::Base => pair.aclass_base.clone(),
+                BrowseBy::Quote => pair.aclass_quote.clone(),
+            })
+            .collect::<Vec<_>>();
+        classes.sort();
+        classes.dedup();
+
+        self.asset_class = match &self.asset_class {
+            None => classes.into_iter().next(),
+            Some(class) => classes.into_iter().find(|next| next > class),
+        };
+    }
+
+    // an asset is listed when its code or altname match, or when one of its pairs matches
+    fn show_assets(&self, ctx: &mut Context) {
+        let assets = self
+            .candidate_assets(ctx)
+            .into_iter()
+            .filter(|asset| {
+                self.asset_matches(ctx, asset)
+                    || ctx
+                        .model
+                        .asset_pairs
+                        .values()
+                        .any(|pair| self.is_listed(pair, asset) && self.pair_matches(pair))
+            })
+            .collect::<Vec<_>>();
+
+        ctx.model.assets_stateful.clear();
+        ctx.model.asset_pairs_stateful.clear();
+        for key in assets {
+            ctx.model.assets_stateful.push(key);
+        }
+    }
+
+    fn on_query_changed(&mut self, ctx: &mut Context) {
+        self.avtive_column = ActiveColumn::Assets;
+        self.show_assets(ctx);
+        ctx.model.assets_stateful.next();
+        self.filter_asset_pairs(ctx);
+    }
+
+    fn on_query_key(&mut self, key_code: KeyCode, ctx: &mut Context) {
+        match key_code {
+            KeyCode::Char(c) => {
+                self.query.push(c);
+                self.on_query_changed(ctx);
+            }
+            KeyCode::Backspace => {
+                if self.query.pop().is_none() {
+                    self.editing = false;
+                }
+                self.on_query_changed(ctx);
+            }
+            KeyCode::Esc => {
+                self.editing = false;
+                self.query.clear();
+                self.on_query_changed(ctx);
+            }
+            // the filter is kept, the lists can be browsed
+            KeyCode::Enter | KeyCode::Down | KeyCode::Up => self.editing = false,
+            _ => {}
+        }
+    }
+
+    // the keys changing the filter of the lists, false for the other keys
+    fn on_filter_key(&mut self, key_code: KeyCode, ctx: &mut Context) -> bool {
+        match key_code {
+            KeyCode::Char('/') => self.editing = true,
+            KeyCode::Char('b') => self.toggle_browse_by(ctx),
+            KeyCode::Char('c') => {
+                self.next_asset_class(ctx);
+                self.on_query_changed(ctx);
+            }
+            KeyCode::Esc if !self.query.is_empty() => {
+                self.query.clear();
+                self.on_query_changed(ctx);
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn add_favorite(ctx: &mut Context) {
+        if let Some(index) = ctx.model.asset_pairs_stateful.state.selected() {
+            let asset_pair_opt = ctx.model.asset_pairs_stateful.items.get(index);
+
+            if let Some(asset_pair) = asset_pair_opt {
+                let pairs = ctx
+                    .model
+                    .asset_pairs
+                    .iter()
+                    .filter(|(_, pair)| pair.wsname == Some(asset_pair.clone()))
+                    .filter_map(|(_, pair)| {
+                        pair.wsname.as_ref().map(std::borrow::ToOwned::to_owned)
+                    })
+                    .collect::<Vec<_>>();
+
+                if !pairs.is_empty() {
+                    let key = &pairs[0];
+                    ctx.model.favorites_asset_pairs_stateful.push(key.clone());
+                    ctx.save_favorites();
+                }
+            }
+        }
+    }
+
+    fn filter_asset_pairs(&self, ctx: &mut Context) {
         ctx.model.asset_pairs_stateful.clear();
 
         let selected = ctx.model.assets_stateful.state.selected();
@@ -37,11 +252,14 @@ impl SearchState {
             let asset_opt = ctx.model.assets_stateful.items.get(index);
 
             if let Some(asset) = asset_opt {
+                // all the pairs of an asset matching the query
+                let asset_matches = self.asset_matches(ctx, asset);
                 let pairs = ctx
                     .model
                     .asset_pairs
                     .iter()
-                    .filter(|(_, pair)| pair.base == *asset)
+                    .filter(|(_, pair)| self.is_listed(pair, asset))
+                    .filter(|(_, pair)| asset_matches || self.pair_matches(pair))
                     .filter_map(|(_, pair)| {
                         pair.wsname.as_ref().map(std::borrow::ToOwned::to_owned)
                     })
@@ -52,6 +270,65 @@ impl SearchState {
                 }
             }
         }
+
+        SearchState::show_asset_info(ctx);
+    }
+
+    // the number of listed pairs of each asset
+    fn pair_counts(&self, ctx: &Context) -> HashMap<String, usize> {
+        let mut counts = HashMap::new();
+        for pair in ctx.model.asset_pairs.values() {
+            if self.pair_in_class(pair) {
+                *counts.entry(self.pair_asset(pair).to_owned()).or_default() += 1;
+            }
+        }
+        counts
+    }
+
+    fn show_asset_info(ctx: &mut Context) {
+        ctx.model.asset_info_stateful.clear();
+
+        let Some(index) = ctx.model.assets_stateful.state.selected() else {
+            return;
+        };
+        let Some(asset) = ctx.model.assets_stateful.items.get(index) else {
+            return;
+        };
+        let as_base = ctx
+            .model
+            .asset_pairs
+            .values()
+            .filter(|pair| pair.base == *asset)
+            .count();
+        let as_quote = ctx
+            .model
+            .asset_pairs
+            .values()
+            .filter(|pair| pair.quote == *asset)
+            .count();
+
+        let mut lines = vec![format!("           asset:{asset}")];
+        if let Some(info) = ctx.model.assets.get(asset) {
+            let collateral_value = match info.collateral_value {
+                Some(value) => format!("{value}"),
+                None => "-".to_owned(),
+            };
+            lines.push(format!("         altname:{}", info.altname));
+            lines.push(format!("          aclass:{}", info.aclass));
+            lines.push(format!("        decimals:{}", info.decimals));
+            lines.push(format!("display_decimals:{}", info.display_decimals));
+            lines.push(format!("collateral_value:{collateral_value}"));
+            lines.push(format!(
+                "          status:{}",
+                info.status.as_deref().unwrap_or("-")
+            ));
+        }
+        lines.push(format!("   pairs as base:{as_base}"));
+        lines.push(format!("  pairs as quote:{as_quote}"));
+
+        for line in lines {
+            ctx.model.asset_info_stateful.push(line);
+        }
     }
 }
 
@@ -64,21 +341,43 @@ impl State for SearchState {
             ctx.model.assets_stateful.push(key.clone());
         }
 
-        let result = ctx.kraken_api.list_asset_pairs();
-        match result {
-            Some(asset_pairs) => {
-                ctx.debug("[SearchS] on_enter_once kraken_api list_asset_pairs.".to_string());
-                ctx.model.asset_pairs = asset_pairs;
+        ctx.request(Request::ListAssetPairs);
+    }
+
+    fn on_response(&mut self, event: &Event, ctx: &mut Context) {
+        match event {
+            // the assets received before entering the first time are listed by on_enter_once
+            Event::Assets { loaded: true } if !self.on_enter_first => {
+                self.show_assets(ctx);
+            }
+            Event::AssetPairs { loaded: true } => {
+                ctx.debug("[SearchS] on_response kraken_api list_asset_pairs.".to_string());
+                // the listed assets come from the pairs
+                if self.browse_by == BrowseBy::Quote || self.asset_class.is_some() {
+                    self.show_assets(ctx);
+                }
+                self.filter_asset_pairs(ctx);
             }
-            None => {
-                ctx.debug(
-                    "[SearchS] on_enter_once kraken_api list_asset_pairs failed.".to_string(),
-                );
+            Event::AssetPairs { loaded: false } => {
+                ctx.debug("[SearchS] on_response kraken_api list_asset_pairs failed.".to_string());
             }
+            _ => {}
         }
     }
 
     fn on_event(&mut self, event: Event, ctx: &mut Context) -> Option<States> {
+        if let Event::Key { key_code } = event {
+            if self.editing {
+                self.on_query_key(key_code, ctx);
+                return None;
+            }
+            if self.on_filter_key(key_code, ctx) {
+                return None;
+            }
+        } else if self.editing {
+            return None;
+        }
+
         match event {
             Event::Key {
                 key_code: KeyCode::Down,
@@ -86,7 +385,7 @@ impl State for SearchState {
                 match self.avtive_column {
                     ActiveColumn::Assets => {
                         ctx.model.assets_stateful.next();
-                        SearchState::filter_asset_pairs(ctx);
+                        self.filter_asset_pairs(ctx);
                     }
                     ActiveColumn::AssetPairs => {
                         ctx.model.asset_pairs_stateful.next();
@@ -100,7 +399,7 @@ impl State for SearchState {
                 match self.avtive_column {
                     ActiveColumn::Assets => {
                         ctx.model.assets_stateful.previous();
-                        SearchState::filter_asset_pairs(ctx);
+                        self.filter_asset_pairs(ctx);
                     }
                     ActiveColumn::AssetPairs => {
                         ctx.model.asset_pairs_stateful.previous();
@@ -114,7 +413,7 @@ impl State for SearchState {
                 match self.avtive_column {
                     ActiveColumn::Assets => {
                         ctx.model.assets_stateful.unselect();
-                        ctx.model.asset_pairs_stateful.clear();
+                        self.filter_asset_pairs(ctx);
                     }
                     ActiveColumn::AssetPairs => {
                         self.avtive_column = ActiveColumn::Assets;
@@ -142,28 +441,7 @@ impl State for SearchState {
             } => {
                 match self.avtive_column {
                     ActiveColumn::Assets => {}
-                    ActiveColumn::AssetPairs => {
-                        if let Some(index) = ctx.model.asset_pairs_stateful.state.selected() {
-                            let asset_pair_opt = ctx.model.asset_pairs_stateful.items.get(index);
-
-                            if let Some(asset_pair) = asset_pair_opt {
-                                let pairs = ctx
-                                    .model
-                                    .asset_pairs
-                                    .iter()
-                                    .filter(|(_, pair)| pair.wsname == Some(asset_pair.clone()))
-                                    .filter_map(|(_, pair)| {
-                                        pair.wsname.as_ref().map(std::borrow::ToOwned::to_owned)
-                                    })
-                                    .collect::<Vec<_>>();
-
-                                if !pairs.is_empty() {
-                                    let key = &pairs[0];
-                                    ctx.model.favorites_asset_pairs_stateful.push(key.clone());
-                                }
-                            }
-                        }
-                    }
+                    ActiveColumn::AssetPairs => SearchState::add_favorite(ctx),
                 }
                 None
             }
@@ -179,29 +457,61 @@ impl State for SearchState {
 
     fn ui<B: Backend>(&self, f: &mut Frame<B>, ctx: &mut Context) {
         let size = f.size();
-        draw_box(f, size, " Search State ");
+        let browsed = match self.browse_by {
+            BrowseBy::Base => "base",
+            BrowseBy::Quote => "quote",
+        };
+        let class = match &self.asset_class {
+            Some(class) => format!("[{browsed} {class}] "),
+            None => String::new(),
+        };
+        let query = if self.editing {
+            format!("/{}_ ", self.query)
+        } else if self.query.is_empty() {
+            String::new()
+        } else {
+            format!("/{} ", self.query)
+        };
+        draw_box(f, size, &format!(" Search State {class}{query}"));
 
         let bboxs = split_columns(2, size);
+        let assets_bboxs = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(11)].as_ref())
+            .split(bboxs[0]);
 
-        draw_stateful_list(
+        let counts = self.pair_counts(ctx);
+        draw_highlighted_list(
             f,
-            bboxs[0],
-            " assets ",
+            assets_bboxs[0],
+            &format!(" {browsed} assets "),
             &mut ctx.model.assets_stateful,
-            false,
+            &self.query,
+            |asset| format!("{asset} ({})", counts.get(asset).unwrap_or(&0)),
         );
         draw_stateful_list(
+            f,
+            assets_bboxs[1],
+            " asset details ",
+            &mut ctx.model.asset_info_stateful,
+            false,
+        );
+        draw_highlighted_list(
             f,
             bboxs[1],
             " asset pairs ",
             &mut ctx.model.asset_pairs_stateful,
-            false,
+            &self.query,
+            ToOwned::to_owned,
         );
     }
 
     fn help_text(&self) -> &str {
         r##"
-        ESC    -> back
+        ESC    -> clear the filter
+        /      -> filter by code or name
+        b      -> browse by base or quote
+        c      -> filter by asset class
         UP     -> previous
         DOWN   -> next
         LEFT   -> previous
@@ -280,7 +590,7 @@ mod tests {
         mock_client
             .expect_list_asset_pairs()
             .once()
-            .returning(|| Some(AssetPairsResponse::new()));
+            .returning(|| Ok(AssetPairsResponse::new()));
         let mut ctx = Context::new_for_testing(mock_client);
 
         ctx.model.assets_stateful.push("key_0".to_owned());
@@ -296,6 +606,10 @@ mod tests {
 
         assert_eq!(state.on_enter_first, false);
 
+        for event in ctx.poll_responses() {
+            state.on_response(&event, &mut ctx);
+        }
+
         let to_state = state.on_event(event, &mut ctx);
 
         assert_eq!(to_state, None);
@@ -371,6 +685,216 @@ mod tests {
         Ok(())
     }
 
+    fn type_keys(state: &mut SearchState, ctx: &mut Context, keys: &[KeyCode]) {
+        for key_code in keys {
+            assert_eq!(
+                state.on_event(
+                    Event::Key {
+                        key_code: *key_code
+                    },
+                    ctx
+                ),
+                None
+            );
+        }
+    }
+
+    fn sorted(items: &[String]) -> Vec<String> {
+        let mut items = items.to_vec();
+        items.sort();
+        items
+    }
+
+    #[test]
+    fn test_filter() {
+        let mut ctx = Context::new_for_testing(Box::new(MockRestAPI::new()));
+        ctx.model.assets = serde_json::from_str(
+            r#"{
+              "XXBT": {"altname": "XBT", "aclass": "currency", "decimals": 10, "display_decimals": 5},
+              "XETH": {"altname": "ETH", "aclass": "currency", "decimals": 10, "display_decimals": 5},
+              "ZUSD": {"altname": "USD", "aclass": "currency", "decimals": 4, "display_decimals": 2}
+            }"#,
+        )
+        .unwrap();
+        ctx.model.asset_pairs = serde_json::from_str(
+            r#"{
+              "XXBTZUSD": {"alt_name": "XBTUSD", "wsname": "XBT/USD", "aclass_base": "currency",
+                "base": "XXBT", "aclass_quote": "currency", "quote": "ZUSD", "pair_decimals": 1,
+                "lot_decimals": 8, "lot_multiplier": 1, "fees": [], "ordermin": "0.0001"},
+              "XETHXXBT": {"alt_name": "ETHXBT", "wsname": "ETH/XBT", "aclass_base": "currency",
+                "base": "XETH", "aclass_quote": "currency", "quote": "XXBT", "pair_decimals": 5,
+                "lot_decimals": 8, "lot_multiplier": 1, "fees": [], "ordermin": "0.01"}
+            }"#,
+        )
+        .unwrap();
+
+        let mut state = SearchState::default();
+        type_keys(
+            &mut state,
+            &mut ctx,
+            &[KeyCode::Char('/'), KeyCode::Char('x'), KeyCode::Char('B')],
+        );
+        assert!(state.is_editing());
+        // XETH is listed for its ETH/XBT pair
+        assert_eq!(
+            sorted(&ctx.model.assets_stateful.items),
+            vec!["XETH".to_owned(), "XXBT".to_owned()]
+        );
+        assert_eq!(ctx.model.assets_stateful.state.selected(), Some(0));
+
+        type_keys(
+            &mut state,
+            &mut ctx,
+            &[KeyCode::Char('t'), KeyCode::Char('/')],
+        );
+        assert_eq!(ctx.model.assets_stateful.items, vec!["XXBT".to_owned()]);
+        assert_eq!(
+            ctx.model.asset_pairs_stateful.items,
+            vec!["XBT/USD".to_owned()]
+        );
+
+        // matched by the altname
+        type_keys(
+            &mut state,
+            &mut ctx,
+            &[
+                KeyCode::Backspace,
+                KeyCode::Backspace,
+                KeyCode::Backspace,
+                KeyCode::Backspace,
+                KeyCode::Char('u'),
+                KeyCode::Char('s'),
+            ],
+        );
+        assert_eq!(
+            sorted(&ctx.model.assets_stateful.items),
+            vec!["XXBT".to_owned(), "ZUSD".to_owned()]
+        );
+
+        // the filter is kept while browsing
+        type_keys(&mut state, &mut ctx, &[KeyCode::Enter, KeyCode::Down]);
+        assert!(!state.is_editing());
+        assert_eq!(ctx.model.assets_stateful.items.len(), 2);
+
+        type_keys(&mut state, &mut ctx, &[KeyCode::Esc]);
+        assert_eq!(ctx.model.assets_stateful.items.len(), 3);
+        assert_eq!(
+            state.on_event(
+                Event::Key {
+                    key_code: KeyCode::Char('h')
+                },
+                &mut ctx
+            ),
+            Some(States::Home)
+        );
+    }
+
+    #[test]
+    fn test_asset_details() {
+        let mut ctx = Context::new_for_testing(Box::new(MockRestAPI::new()));
+        ctx.model.assets = serde_json::from_str(
+            r#"{
+              "XXBT": {"altname": "XBT", "aclass": "currency", "decimals": 10,
+                "display_decimals": 5, "collateral_value": 0.95, "status": "enabled"}
+            }"#,
+        )
+        .unwrap();
+        ctx.model.asset_pairs = serde_json::from_str(
+            r#"{
+              "XXBTZUSD": {"alt_name": "XBTUSD", "wsname": "XBT/USD", "aclass_base": "currency",
+                "base": "XXBT", "aclass_quote": "currency", "quote": "ZUSD", "pair_decimals": 1,
+                "lot_decimals": 8, "lot_multiplier": 1, "fees": [], "ordermin": "0.0001"},
+              "XETHXXBT": {"alt_name": "ETHXBT", "wsname": "ETH/XBT", "aclass_base": "currency",
+                "base": "XETH", "aclass_quote": "currency", "quote": "XXBT", "pair_decimals": 5,
+                "lot_decimals": 8, "lot_multiplier": 1, "fees": [], "ordermin": "0.01"}
+            }"#,
+        )
+        .unwrap();
+
+        let mut state = SearchState::default();
+        state.show_assets(&mut ctx);
+        type_keys(&mut state, &mut ctx, &[KeyCode::Down]);
+        assert_eq!(
+            ctx.model.asset_info_stateful.items,
+            vec![
+                "           asset:XXBT".to_owned(),
+                "         altname:XBT".to_owned(),
+                "          aclass:currency".to_owned(),
+                "        decimals:10".to_owned(),
+                "display_decimals:5".to_owned(),
+                "collateral_value:0.95".to_owned(),
+                "          status:enabled".to_owned(),
+                "   pairs as base:1".to_owned(),
+                "  pairs as quote:1".to_owned(),
+            ]
+        );
+        assert_eq!(state.pair_counts(&ctx).get("XXBT"), Some(&1));
+
+        type_keys(&mut state, &mut ctx, &[KeyCode::Left]);
+        assert!(ctx.model.asset_info_stateful.items.is_empty());
+    }
+
+    #[test]
+    fn test_browse_by_quote_and_class() {
+        let mut ctx = Context::new_for_testing(Box::new(MockRestAPI::new()));
+        ctx.model.asset_pairs = serde_json::from_str(
+            r#"{
+              "XXBTZUSD": {"alt_name": "XBTUSD", "wsname": "XBT/USD", "aclass_base": "currency",
+                "base": "XXBT", "aclass_quote": "currency", "quote": "ZUSD", "pair_decimals": 1,
+                "lot_decimals": 8, "lot_multiplier": 1, "fees": [], "ordermin": "0.0001"},
+              "XETHZUSD": {"alt_name": "ETHUSD", "wsname": "ETH/USD", "aclass_base": "currency",
+                "base": "XETH", "aclass_quote": "currency", "quote": "ZUSD", "pair_decimals": 2,
+                "lot_decimals": 8, "lot_multiplier": 1, "fees": [], "ordermin": "0.01"},
+              "XXBTZEUR": {"alt_name": "XBTEUR", "wsname": "XBT/EUR", "aclass_base": "currency",
+                "base": "XXBT", "aclass_quote": "currency", "quote": "ZEUR", "pair_decimals": 1,
+                "lot_decimals": 8, "lot_multiplier": 1, "fees": [], "ordermin": "0.0001"},
+              "AAPLXZUSD": {"alt_name": "AAPLXUSD", "wsname": "AAPLx/USD",
+                "aclass_base": "tokenized_asset", "base": "AAPLx", "aclass_quote": "currency",
+                "quote": "ZUSD", "pair_decimals": 2, "lot_decimals": 8, "lot_multiplier": 1,
+                "fees": [], "ordermin": "0.1"}
+            }"#,
+        )
+        .unwrap();
+
+        let mut state = SearchState::default();
+        type_keys(&mut state, &mut ctx, &[KeyCode::Char('b')]);
+        assert_eq!(
+            ctx.model.assets_stateful.items,
+            vec!["ZEUR".to_owned(), "ZUSD".to_owned()]
+        );
+
+        // every market quoted in USD
+        type_keys(&mut state, &mut ctx, &[KeyCode::Down]);
+        assert_eq!(
+            sorted(&ctx.model.asset_pairs_stateful.items),
+            vec![
+                "AAPLx/USD".to_owned(),
+                "ETH/USD".to_owned(),
+                "XBT/USD".to_owned()
+            ]
+        );
+
+        // back to the base assets, one asset class after the other
+        type_keys(
+            &mut state,
+            &mut ctx,
+            &[KeyCode::Char('b'), KeyCode::Char('c')],
+        );
+        assert_eq!(state.asset_class, Some("currency".to_owned()));
+        assert_eq!(
+            ctx.model.assets_stateful.items,
+            vec!["XETH".to_owned(), "XXBT".to_owned()]
+        );
+        type_keys(&mut state, &mut ctx, &[KeyCode::Char('c')]);
+        assert_eq!(ctx.model.assets_stateful.items, vec!["AAPLx".to_owned()]);
+        assert_eq!(
+            ctx.model.asset_pairs_stateful.items,
+            vec!["AAPLx/USD".to_owned()]
+        );
+        type_keys(&mut state, &mut ctx, &[KeyCode::Char('c')]);
+        assert_eq!(state.asset_class, None);
+    }
+
     #[test]
     fn test_ui() {
         let backend = TestBackend::new(7, 4);
@@ -399,7 +923,7 @@ mod tests {
     #[test]
     fn test_state_help() -> Result<(), String> {
         let state = SearchState::default();
-        assert_eq!(state.help_text().len(), 221);
+        assert_eq!(state.help_text().len(), 356);
 
         Ok(())
     }
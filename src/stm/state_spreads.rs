@@ -0,0 +1,374 @@
+use std::time::{Duration, Instant};
+
+use crossterm::event::KeyCode;
+use tui::{backend::Backend, Frame};
+
+use crate::app::Context;
+use crate::kraken::messages::{RecentSpreads, Spread};
+use crate::kraken::worker::Request;
+use crate::kraken::ws_messages::{Feed, Subscription};
+use crate::stm::{events::Event, selected_pair_name, State, States};
+use crate::ui::{
+    core::{centered_rect, draw_box},
+    format::format_time_utc,
+    list_stateful_widget::draw_stateful_list,
+};
+
+const SPREADS_REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+// Recent Spreads State
+#[derive(Default)]
+pub struct SpreadsState {
+    asset_pair: Option<String>,
+    refreshed_at: Option<Instant>,
+    // pair of the request in flight, the same since cursor must not be sent twice
+    loading: Option<String>,
+}
+
+impl SpreadsState {
+    // fetch only the spreads newer than the last one already loaded
+    fn refresh(&mut self, ctx: &mut Context) {
+        if let Some(asset_pair) = &self.asset_pair {
+            if self.loading.as_ref() == Some(asset_pair) {
+                return;
+            }
+            let since = ctx
+                .model
+                .recent_spreads
+                .get(asset_pair)
+                .and_then(|recent_spreads| recent_spreads.last);
+
+            ctx.request(Request::Spreads {
+                asset_pair: asset_pair.clone(),
+                since,
+            });
+            self.loading = Some(asset_pair.clone());
+            self.refreshed_at = Some(Instant::now());
+        }
+    }
+
+    fn pair_decimals(&self, ctx: &Context) -> usize {
+        self.asset_pair
+            .as_ref()
+            .and_then(|asset_pair| ctx.model.asset_pairs.get(asset_pair))
+            .map_or(8, |pair| usize::try_from(pair.pair_decimals).unwrap_or(8))
+    }
+
+    // the new spreads are pushed by the websocket while they are visible
+    fn subscription(&self, ctx: &Context) -> Option<Subscription> {
+        let pair = ctx.model.wsname(self.asset_pair.as_ref()?)?;
+        Some(Subscription::new(&pair, Feed::Spread))
+    }
+
+    // newest spread first, the selection follows the selected spread when newer ones arrive
+    fn show_spreads(&self, ctx: &mut Context) {
+        let selected = ctx.model.spreads_stateful.state.selected();
+        let previous_len = ctx.model.spreads_stateful.items.len();
+        ctx.model.spreads_stateful.clear();
+
+        let pair_decimals = self.pair_decimals(ctx);
+        if let Some(asset_pair) = &self.asset_pair {
+            if let Some(recent_spreads) = ctx.model.recent_spreads.get(asset_pair) {
+                let lines = recent_spreads
+                    .spreads
+                    .iter()
+                    .rev()
+                    .map(|spread| SpreadsState::format_spread(spread, pair_decimals))
+                    .collect::<Vec<_>>();
+
+                let len = lines.len();
+                for line in lines {
+                    ctx.model.spreads_stateful.push(line);
+                }
+                if len > 0 {
+                    let added = len.saturating_sub(previous_len);
+                    ctx.model
+                        .spreads_stateful
+                        .state
+                        .select(selected.map(|index| (index + added).min(len - 1)));
+                }
+            }
+        }
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn format_spread(spread: &Spread, pair_decimals: usize) -> String {
+        format!(
+            "{}  {:>14} {:>14} {:>14.pair_decimals$}",
+            format_time_utc(spread.time as f64),
+            spread.bid,
+            spread.ask,
+            spread.spread().unwrap_or(0.0)
+        )
+    }
+
+    fn title(&self, ctx: &Context) -> String {
+        let name = selected_pair_name(ctx, self.asset_pair.as_ref());
+
+        let stats = self
+            .asset_pair
+            .as_ref()
+            .and_then(|asset_pair| ctx.model.recent_spreads.get(asset_pair))
+            .and_then(RecentSpreads::stats);
+
+        let decimals = self.pair_decimals(ctx);
+        match stats {
+            Some(stats) => format!(
+                " Recent Spreads {name} min:{:.decimals$} avg:{:.decimals$} max:{:.decimals$} ",
+                stats.min, stats.avg, stats.max
+            ),
+            None => format!(" Recent Spreads {name} "),
+        }
+    }
+}
+
+impl State for SpreadsState {
+    fn on_enter(&mut self, ctx: &mut Context) {
+        self.asset_pair = ctx.model.selected_favorite_key();
+        if let Some(subscription) = self.subscription(ctx) {
+            ctx.subscribe(subscription);
+        }
+        self.show_spreads(ctx);
+        self.refresh(ctx);
+    }
+
+    // stop polling and streaming once the spreads are not visible anymore
+    fn on_exit(&mut self, ctx: &mut Context) {
+        self.refreshed_at = None;
+        if let Some(subscription) = self.subscription(ctx) {
+            ctx.unsubscribe(subscription);
+        }
+    }
+
+    fn on_tick(&mut self, ctx: &mut Context) {
+        if let Some(refreshed_at) = self.refreshed_at {
+            if !ctx.model.streaming && refreshed_at.elapsed() >= SPREADS_REFRESH_INTERVAL {
+                self.refresh(ctx);
+            }
+        }
+    }
+
+    fn on_response(&mut self, event: &Event, ctx: &mut Context) {
+        if let Event::Spreads { asset_pair, loaded } = event {
+            if self.loading.as_ref() == Some(asset_pair) {
+                self.loading = None;
+            }
+            if self.asset_pair.as_ref() == Some(asset_pair) {
+                if !loaded {
+                    ctx.debug(format!(
+                        "[SpreadsS] on_response kraken_api spreads {asset_pair} failed."
+                    ));
+                }
+                self.show_spreads(ctx);
+            }
+        }
+    }
+
+    fn on_event(&mut self, event: Event, ctx: &mut Context) -> Option<States> {
+        match event {
+            Event::Key {
+                key_code: KeyCode::Esc,
+            } => Some(States::Home),
+            Event::Key {
+                key_code: KeyCode::Char('r'),
+            } => {
+                self.refresh(ctx);
+                None
+            }
+            Event::Key {
+                key_code: KeyCode::Down,
+            } => {
+                ctx.model.spreads_stateful.next();
+                None
+            }
+            Event::Key {
+                key_code: KeyCode::Up,
+            } => {
+                ctx.model.spreads_stateful.previous();
+                None
+            }
+            Event::Key {
+                key_code: KeyCode::Left,
+            } => {
+                ctx.model.spreads_stateful.unselect();
+                None
+            }
+            _ => {
+                ctx.debug(format!("[SpreadsS] on_event {event:?} not match"));
+                None
+            }
+        }
+    }
+
+    fn ui<B: Backend>(&self, f: &mut Frame<B>, ctx: &mut Context) {
+        let size = f.size();
+        draw_box(f, size, &self.title(ctx));
+
+        let bbox = centered_rect(95, 90, size);
+        draw_stateful_list(
+            f,
+            bbox,
+            " time bid ask spread ",
+            &mut ctx.model.spreads_stateful,
+            false,
+        );
+    }
+
+    fn help_text(&self) -> &str {
+        r##"
+        ESC    -> home
+        UP     -> previous
+        DOWN   -> next
+        LEFT   -> unselect
+        r      -> refresh
+        D      -> show Debug
+        "##
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::KeyCode;
+    use mockall::predicate::eq;
+    use tui::{backend::TestBackend, buffer::Buffer, Terminal};
+
+    use crate::kraken::messages::SpreadsResponse;
+    use crate::{kraken::client::MockRestAPI, stm::events::Event};
+
+    use super::*;
+
+    fn spreads_response(last: u64, ask: &str) -> SpreadsResponse {
+        let json = format!(r#"{{ "last": {last}, "XXBTZUSD": [[{last}, "30000.0", "{ask}"]] }}"#);
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn test_incremental_refresh() {
+        let mut mock_client = Box::new(MockRestAPI::new());
+        mock_client
+            .expect_spreads()
+            .with(eq("XXBTZUSD"), eq(None))
+            .once()
+            .returning(|_, _| Ok(spreads_response(60, "30001.0")));
+        mock_client
+            .expect_spreads()
+            .with(eq("XXBTZUSD"), eq(Some(60)))
+            .once()
+            .returning(|_, _| Ok(spreads_response(120, "30003.0")));
+        let mut ctx = Context::new_for_testing(mock_client);
+
+        let mut state = SpreadsState {
+            asset_pair: Some("XXBTZUSD".to_owned()),
+            ..SpreadsState::default()
+        };
+
+        for _ in 0..2 {
+            // not requested again while the previous request is in flight
+            for _ in 0..2 {
+                let event = Event::Key {
+                    key_code: KeyCode::Char('r'),
+                };
+                assert_eq!(state.on_event(event, &mut ctx), None);
+            }
+
+            for event in ctx.poll_responses() {
+                state.on_response(&event, &mut ctx);
+            }
+        }
+
+        assert_eq!(ctx.model.spreads_stateful.items.len(), 2);
+        assert_eq!(
+            ctx.model.spreads_stateful.items[0],
+            "00:02:00         30000.0        30003.0     3.00000000"
+        );
+        assert_eq!(
+            state.title(&ctx),
+            " Recent Spreads XXBTZUSD min:1.00000000 avg:2.00000000 max:3.00000000 "
+        );
+
+        let event = Event::Key {
+            key_code: KeyCode::Esc,
+        };
+        assert_eq!(state.on_event(event, &mut ctx), Some(States::Home));
+        state.on_exit(&mut ctx);
+        assert_eq!(state.refreshed_at, None);
+    }
+
+    #[test]
+    fn test_on_enter_without_selection() {
+        let mut ctx = Context::new_for_testing(Box::new(MockRestAPI::new()));
+
+        let mut state = SpreadsState::default();
+        state.on_enter(&mut ctx);
+        state.on_tick(&mut ctx);
+
+        assert_eq!(state.asset_pair, None);
+        assert_eq!(ctx.model.pending_requests, 0);
+    }
+
+    #[test]
+    fn test_ui() {
+        let backend = TestBackend::new(7, 4);
+        let mut terminal = Terminal::new(backend).unwrap();
+        let mut ctx = Context::new_for_testing(Box::new(MockRestAPI::new()));
+
+        let state = SpreadsState::default();
+
+        terminal
+            .draw(|f| {
+                state.ui(f, &mut ctx);
+            })
+            .unwrap();
+
+        #[rustfmt::skip]
+        let expected = Buffer::with_lines(vec![
+            "┌ tim┐╮",
+            "│    ││",
+            "└────┘│",
+            "╰─────╯"
+            ]);
+
+        terminal.backend().assert_buffer(&expected);
+    }
+
+    #[test]
+    fn test_navigation() {
+        let mut mock_client = Box::new(MockRestAPI::new());
+        mock_client
+            .expect_spreads()
+            .with(eq("XXBTZUSD"), eq(None))
+            .once()
+            .returning(|_, _| Ok(spreads_response(60, "30001.0")));
+        mock_client
+            .expect_spreads()
+            .with(eq("XXBTZUSD"), eq(Some(60)))
+            .once()
+            .returning(|_, _| Ok(spreads_response(120, "30003.0")));
+        let mut ctx = Context::new_for_testing(mock_client);
+
+        let mut state = SpreadsState {
+            asset_pair: Some("XXBTZUSD".to_owned()),
+            ..SpreadsState::default()
+        };
+        state.refresh(&mut ctx);
+        for event in ctx.poll_responses() {
+            state.on_response(&event, &mut ctx);
+        }
+
+        let key = |key_code| Event::Key { key_code };
+        assert_eq!(state.on_event(key(KeyCode::Down), &mut ctx), None);
+        assert_eq!(ctx.model.spreads_stateful.state.selected(), Some(0));
+        assert_eq!(state.on_event(key(KeyCode::Left), &mut ctx), None);
+        assert_eq!(ctx.model.spreads_stateful.state.selected(), None);
+        assert_eq!(state.on_event(key(KeyCode::Up), &mut ctx), None);
+        assert_eq!(ctx.model.spreads_stateful.state.selected(), Some(0));
+
+        // the selected spread stays selected below the newer one
+        state.refresh(&mut ctx);
+        for event in ctx.poll_responses() {
+            state.on_response(&event, &mut ctx);
+        }
+        assert_eq!(ctx.model.spreads_stateful.items.len(), 2);
+        assert_eq!(ctx.model.spreads_stateful.state.selected(), Some(1));
+    }
+}
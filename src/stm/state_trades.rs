@@ -0,0 +1,360 @@
+use std::time::{Duration, Instant};
+
+use crossterm::event::KeyCode;
+use tui::{backend::Backend, Frame};
+
+use crate::app::Context;
+use crate::kraken::messages::Trade;
+use crate::kraken::worker::Request;
+use crate::kraken::ws_messages::{Feed, Subscription};
+use crate::stm::{events::Event, selected_pair_name, State, States};
+use crate::ui::{
+    core::{centered_rect, draw_box},
+    format::format_time_utc,
+    list_stateful_widget::draw_stateful_list,
+};
+
+const TRADES_REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+// Recent Trades State
+#[derive(Default)]
+pub struct TradesState {
+    asset_pair: Option<String>,
+    refreshed_at: Option<Instant>,
+    // pair of the request in flight, the same since cursor must not be sent twice
+    loading: Option<String>,
+}
+
+impl TradesState {
+    // fetch only the trades newer than the last one already loaded
+    fn refresh(&mut self, ctx: &mut Context) {
+        if let Some(asset_pair) = &self.asset_pair {
+            if self.loading.as_ref() == Some(asset_pair) {
+                return;
+            }
+            let since = ctx
+                .model
+                .recent_trades
+                .get(asset_pair)
+                .and_then(|recent_trades| recent_trades.last.clone());
+
+            ctx.request(Request::RecentTrades {
+                asset_pair: asset_pair.clone(),
+                since,
+            });
+            self.loading = Some(asset_pair.clone());
+            self.refreshed_at = Some(Instant::now());
+        }
+    }
+
+    // the new trades are pushed by the websocket while they are visible
+    fn subscription(&self, ctx: &Context) -> Option<Subscription> {
+        let pair = ctx.model.wsname(self.asset_pair.as_ref()?)?;
+        Some(Subscription::new(&pair, Feed::Trade))
+    }
+
+    // newest trade first, the selection follows the selected trade when newer ones arrive
+    fn show_trades(&self, ctx: &mut Context) {
+        let selected = ctx.model.trades_stateful.state.selected();
+        let previous_len = ctx.model.trades_stateful.items.len();
+        ctx.model.trades_stateful.clear();
+
+        if let Some(asset_pair) = &self.asset_pair {
+            if let Some(recent_trades) = ctx.model.recent_trades.get(asset_pair) {
+                let lines = recent_trades
+                    .trades
+                    .iter()
+                    .rev()
+                    .map(TradesState::format_trade)
+                    .collect::<Vec<_>>();
+
+                let len = lines.len();
+                for line in lines {
+                    ctx.model.trades_stateful.push(line);
+                }
+                if len > 0 {
+                    let added = len.saturating_sub(previous_len);
+                    ctx.model
+                        .trades_stateful
+                        .state
+                        .select(selected.map(|index| (index + added).min(len - 1)));
+                }
+            }
+        }
+    }
+
+    fn format_trade(trade: &Trade) -> String {
+        format!(
+            "{}  {:<4} {:>14} {:>16}  {}",
+            format_time_utc(trade.time),
+            if trade.is_buy() { "buy" } else { "sell" },
+            trade.price,
+            trade.volume,
+            if trade.is_market() { "market" } else { "limit" }
+        )
+    }
+
+    #[cfg(test)]
+    pub fn is_polling(&self) -> bool {
+        self.refreshed_at.is_some()
+    }
+
+    fn title(&self, ctx: &Context) -> String {
+        let name = selected_pair_name(ctx, self.asset_pair.as_ref());
+
+        format!(" Recent Trades {name} ")
+    }
+}
+
+impl State for TradesState {
+    fn on_enter(&mut self, ctx: &mut Context) {
+        self.asset_pair = ctx.model.selected_favorite_key();
+        if let Some(subscription) = self.subscription(ctx) {
+            ctx.subscribe(subscription);
+        }
+        self.show_trades(ctx);
+        self.refresh(ctx);
+    }
+
+    // stop polling and streaming once the tape is not visible anymore
+    fn on_exit(&mut self, ctx: &mut Context) {
+        self.refreshed_at = None;
+        if let Some(subscription) = self.subscription(ctx) {
+            ctx.unsubscribe(subscription);
+        }
+    }
+
+    fn on_tick(&mut self, ctx: &mut Context) {
+        if let Some(refreshed_at) = self.refreshed_at {
+            if !ctx.model.streaming && refreshed_at.elapsed() >= TRADES_REFRESH_INTERVAL {
+                self.refresh(ctx);
+            }
+        }
+    }
+
+    fn on_response(&mut self, event: &Event, ctx: &mut Context) {
+        if let Event::RecentTrades { asset_pair, loaded } = event {
+            if self.loading.as_ref() == Some(asset_pair) {
+                self.loading = None;
+            }
+            if self.asset_pair.as_ref() == Some(asset_pair) {
+                if !loaded {
+                    ctx.debug(format!(
+                        "[TradesS] on_response kraken_api recent_trades {asset_pair} failed."
+                    ));
+                }
+                self.show_trades(ctx);
+            }
+        }
+    }
+
+    fn on_event(&mut self, event: Event, ctx: &mut Context) -> Option<States> {
+        match event {
+            Event::Key {
+                key_code: KeyCode::Esc,
+            } => Some(States::Home),
+            Event::Key {
+                key_code: KeyCode::Char('r'),
+            } => {
+                self.refresh(ctx);
+                None
+            }
+            Event::Key {
+                key_code: KeyCode::Down,
+            } => {
+                ctx.model.trades_stateful.next();
+                None
+            }
+            Event::Key {
+                key_code: KeyCode::Up,
+            } => {
+                ctx.model.trades_stateful.previous();
+                None
+            }
+            Event::Key {
+                key_code: KeyCode::Left,
+            } => {
+                ctx.model.trades_stateful.unselect();
+                None
+            }
+            _ => {
+                ctx.debug(format!("[TradesS] on_event {event:?} not match"));
+                None
+            }
+        }
+    }
+
+    fn ui<B: Backend>(&self, f: &mut Frame<B>, ctx: &mut Context) {
+        let size = f.size();
+        draw_box(f, size, &self.title(ctx));
+
+        let bbox = centered_rect(95, 90, size);
+        draw_stateful_list(
+            f,
+            bbox,
+            " time side price volume type ",
+            &mut ctx.model.trades_stateful,
+            false,
+        );
+    }
+
+    fn help_text(&self) -> &str {
+        r##"
+        ESC    -> home
+        UP     -> previous
+        DOWN   -> next
+        LEFT   -> unselect
+        r      -> refresh
+        D      -> show Debug
+        "##
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::KeyCode;
+    use mockall::predicate::eq;
+    use tui::{backend::TestBackend, buffer::Buffer, Terminal};
+
+    use crate::kraken::messages::TradesResponse;
+    use crate::{kraken::client::MockRestAPI, stm::events::Event};
+
+    use super::*;
+
+    fn trades_response(last: &str, price: &str, time: f64) -> TradesResponse {
+        let json = format!(
+            r#"{{ "last": "{last}", "XXBTZUSD": [["{price}", "0.5", {time}, "b", "m", "", 1]] }}"#
+        );
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn test_incremental_refresh() {
+        let mut mock_client = Box::new(MockRestAPI::new());
+        mock_client
+            .expect_recent_trades()
+            .with(eq("XXBTZUSD"), eq(None))
+            .once()
+            .returning(|_, _| Ok(trades_response("100", "30000.0", 60.0)));
+        mock_client
+            .expect_recent_trades()
+            .with(eq("XXBTZUSD"), eq(Some("100".to_owned())))
+            .once()
+            .returning(|_, _| Ok(trades_response("200", "30010.0", 120.0)));
+        let mut ctx = Context::new_for_testing(mock_client);
+
+        let mut state = TradesState {
+            asset_pair: Some("XXBTZUSD".to_owned()),
+            ..TradesState::default()
+        };
+
+        for _ in 0..2 {
+            // not requested again while the previous request is in flight
+            for _ in 0..2 {
+                let event = Event::Key {
+                    key_code: KeyCode::Char('r'),
+                };
+                assert_eq!(state.on_event(event, &mut ctx), None);
+            }
+
+            for event in ctx.poll_responses() {
+                state.on_response(&event, &mut ctx);
+            }
+        }
+
+        let recent_trades = ctx.model.recent_trades.get("XXBTZUSD").unwrap();
+        assert_eq!(recent_trades.trades.len(), 2);
+        assert_eq!(recent_trades.last, Some("200".to_owned()));
+
+        assert_eq!(ctx.model.trades_stateful.items.len(), 2);
+        assert_eq!(
+            ctx.model.trades_stateful.items[0],
+            "00:02:00  buy         30010.0              0.5  market"
+        );
+
+        let event = Event::Key {
+            key_code: KeyCode::Esc,
+        };
+        assert_eq!(state.on_event(event, &mut ctx), Some(States::Home));
+        state.on_exit(&mut ctx);
+        assert_eq!(state.refreshed_at, None);
+    }
+
+    #[test]
+    fn test_on_enter_without_selection() {
+        let mut ctx = Context::new_for_testing(Box::new(MockRestAPI::new()));
+
+        let mut state = TradesState::default();
+        state.on_enter(&mut ctx);
+        state.on_tick(&mut ctx);
+
+        assert_eq!(state.asset_pair, None);
+        assert_eq!(ctx.model.pending_requests, 0);
+    }
+
+    #[test]
+    fn test_ui() {
+        let backend = TestBackend::new(7, 4);
+        let mut terminal = Terminal::new(backend).unwrap();
+        let mut ctx = Context::new_for_testing(Box::new(MockRestAPI::new()));
+
+        let state = TradesState::default();
+
+        terminal
+            .draw(|f| {
+                state.ui(f, &mut ctx);
+            })
+            .unwrap();
+
+        #[rustfmt::skip]
+        let expected = Buffer::with_lines(vec![
+            "┌ tim┐╮",
+            "│    ││",
+            "└────┘│",
+            "╰─────╯"
+            ]);
+
+        terminal.backend().assert_buffer(&expected);
+    }
+
+    #[test]
+    fn test_navigation() {
+        let mut mock_client = Box::new(MockRestAPI::new());
+        mock_client
+            .expect_recent_trades()
+            .with(eq("XXBTZUSD"), eq(None))
+            .once()
+            .returning(|_, _| Ok(trades_response("100", "30000.0", 60.0)));
+        mock_client
+            .expect_recent_trades()
+            .with(eq("XXBTZUSD"), eq(Some("100".to_owned())))
+            .once()
+            .returning(|_, _| Ok(trades_response("200", "30010.0", 120.0)));
+        let mut ctx = Context::new_for_testing(mock_client);
+
+        let mut state = TradesState {
+            asset_pair: Some("XXBTZUSD".to_owned()),
+            ..TradesState::default()
+        };
+        state.refresh(&mut ctx);
+        for event in ctx.poll_responses() {
+            state.on_response(&event, &mut ctx);
+        }
+
+        let key = |key_code| Event::Key { key_code };
+        assert_eq!(state.on_event(key(KeyCode::Down), &mut ctx), None);
+        assert_eq!(ctx.model.trades_stateful.state.selected(), Some(0));
+        assert_eq!(state.on_event(key(KeyCode::Left), &mut ctx), None);
+        assert_eq!(ctx.model.trades_stateful.state.selected(), None);
+        assert_eq!(state.on_event(key(KeyCode::Up), &mut ctx), None);
+        assert_eq!(ctx.model.trades_stateful.state.selected(), Some(0));
+
+        // the selected trade stays selected below the newer one
+        state.refresh(&mut ctx);
+        for event in ctx.poll_responses() {
+            state.on_response(&event, &mut ctx);
+        }
+        assert_eq!(ctx.model.trades_stateful.items.len(), 2);
+        assert_eq!(ctx.model.trades_stateful.state.selected(), Some(1));
+    }
+}
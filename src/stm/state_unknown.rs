@@ -41,13 +41,19 @@ mod tests {
     use crossterm::event::KeyCode;
     use tui::{backend::TestBackend, buffer::Buffer, Terminal};
 
+    use crate::config::Config;
     use crate::{kraken::client::MockRestAPI, stm::events::Event};
 
     use super::*;
 
     #[test]
     fn test_unknown_state() -> Result<(), String> {
-        let mut ctx = Context::new(String::from("APP_ID"), String::from("APP_VERSION"));
+        let mut ctx = Context::new(
+            String::from("APP_ID"),
+            String::from("APP_VERSION"),
+            Config::default(),
+            None,
+        );
 
         let event = Event::Key {
             key_code: KeyCode::Esc,
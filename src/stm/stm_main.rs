@@ -3,13 +3,22 @@ use tui::{backend::Backend, Frame};
 
 use crate::app::Context;
 use crate::stm::events::Event;
+use crate::stm::state_balances::BalancesState;
+use crate::stm::state_chart::ChartState;
 use crate::stm::state_debug::DebugState;
+use crate::stm::state_finder::FinderState;
 use crate::stm::state_help::HelpState;
+use crate::stm::state_history::HistoryState;
 use crate::stm::state_home::HomeState;
+use crate::stm::state_open_orders::OpenOrdersState;
+use crate::stm::state_order_book::OrderBookState;
+use crate::stm::state_order_entry::OrderEntryState;
 use crate::stm::state_search::SearchState;
+use crate::stm::state_spreads::SpreadsState;
+use crate::stm::state_trades::TradesState;
 use crate::stm::state_unknown::UnknownState;
 use crate::stm::{State, States};
-use crate::ui::core::{centered_rect, clear_box, draw_box, draw_paragraph};
+use crate::ui::core::{centered_rect, draw_popup, draw_status_bar};
 
 #[allow(unused)]
 pub struct MainStm<'a> {
@@ -21,8 +30,17 @@ pub struct MainStm<'a> {
     unknow_st: UnknownState,
     debug_st: DebugState,
     help_st: HelpState,
+    finder_st: FinderState,
     home_st: HomeState,
     search_st: SearchState,
+    order_book_st: OrderBookState,
+    trades_st: TradesState,
+    chart_st: ChartState,
+    spreads_st: SpreadsState,
+    balances_st: BalancesState,
+    open_orders_st: OpenOrdersState,
+    history_st: HistoryState,
+    order_entry_st: OrderEntryState,
 
     trace: bool,
 }
@@ -38,8 +56,17 @@ impl MainStm<'_> {
             unknow_st: UnknownState,
             debug_st: DebugState,
             help_st: HelpState,
+            finder_st: FinderState::default(),
             home_st: HomeState::default(),
             search_st: SearchState::default(),
+            order_book_st: OrderBookState::default(),
+            trades_st: TradesState::default(),
+            chart_st: ChartState::default(),
+            spreads_st: SpreadsState::default(),
+            balances_st: BalancesState::default(),
+            open_orders_st: OpenOrdersState::default(),
+            history_st: HistoryState::default(),
+            order_entry_st: OrderEntryState::default(),
 
             trace,
         }
@@ -52,6 +79,19 @@ impl MainStm<'_> {
                 self.previous_st = None;
             }
         } else {
+            // the overlay states are drawn over the current one, which is not left
+            let overlay = matches!(to_state, States::Help | States::Debug | States::Finder);
+            // leaving an overlay, e.g. the Finder jumping to Home, leaves the state below it
+            if !overlay {
+                let left = match self.current_st {
+                    States::Help | States::Debug | States::Finder => self.previous_st,
+                    current => Some(current),
+                };
+                if let Some(left) = left {
+                    self.exit_state(left, ctx);
+                }
+            }
+
             self.previous_st = Some(self.current_st);
             self.current_st = to_state;
 
@@ -60,18 +100,90 @@ impl MainStm<'_> {
                     if self.home_st.on_enter_first {
                         self.home_st.on_enter_once(ctx);
                     }
+                    self.home_st.on_enter(ctx);
                 }
                 States::Search => {
                     if self.search_st.on_enter_first {
                         self.search_st.on_enter_once(ctx);
                     }
                 }
+                States::OrderBook => self.order_book_st.on_enter(ctx),
+                States::Trades => self.trades_st.on_enter(ctx),
+                States::Chart => self.chart_st.on_enter(ctx),
+                States::Spreads => self.spreads_st.on_enter(ctx),
+                States::Balances => self.balances_st.on_enter(ctx),
+                States::OpenOrders => self.open_orders_st.on_enter(ctx),
+                States::History => self.history_st.on_enter(ctx),
+                States::OrderEntry => self.order_entry_st.on_enter(ctx),
+                States::Finder => self.finder_st.on_enter(ctx),
                 _ => {}
             }
         }
     }
 
+    // the states polling on tick or streaming stop once they are left
+    fn exit_state(&mut self, state: States, ctx: &mut Context) {
+        match state {
+            States::Home => self.home_st.on_exit(ctx),
+            States::OrderBook => self.order_book_st.on_exit(ctx),
+            States::Trades => self.trades_st.on_exit(ctx),
+            States::Chart => self.chart_st.on_exit(ctx),
+            States::Spreads => self.spreads_st.on_exit(ctx),
+            States::Balances => self.balances_st.on_exit(ctx),
+            _ => {}
+        }
+    }
+
+    // the ticks are delivered to every state
+    fn on_tick(&mut self, ctx: &mut Context) {
+        self.unknow_st.on_tick(ctx);
+        self.debug_st.on_tick(ctx);
+        self.help_st.on_tick(ctx);
+        self.home_st.on_tick(ctx);
+        self.search_st.on_tick(ctx);
+        self.order_book_st.on_tick(ctx);
+        self.trades_st.on_tick(ctx);
+        self.chart_st.on_tick(ctx);
+        self.spreads_st.on_tick(ctx);
+        self.balances_st.on_tick(ctx);
+        self.open_orders_st.on_tick(ctx);
+        self.history_st.on_tick(ctx);
+        self.order_entry_st.on_tick(ctx);
+    }
+
+    // the kraken worker responses are delivered to every state
+    fn on_response(&mut self, event: &Event, ctx: &mut Context) {
+        self.unknow_st.on_response(event, ctx);
+        self.debug_st.on_response(event, ctx);
+        self.help_st.on_response(event, ctx);
+        self.home_st.on_response(event, ctx);
+        self.search_st.on_response(event, ctx);
+        self.order_book_st.on_response(event, ctx);
+        self.trades_st.on_response(event, ctx);
+        self.chart_st.on_response(event, ctx);
+        self.spreads_st.on_response(event, ctx);
+        self.balances_st.on_response(event, ctx);
+        self.open_orders_st.on_response(event, ctx);
+        self.history_st.on_response(event, ctx);
+        self.order_entry_st.on_response(event, ctx);
+    }
+
+    // a text input of the current state takes all the keys, also 'q', 'D' and '?'
+    pub fn captures_keys(&self) -> bool {
+        match self.current_st {
+            States::Home => self.home_st.is_editing(),
+            States::Search => self.search_st.is_editing(),
+            States::Finder => true,
+            _ => false,
+        }
+    }
+
     pub fn on_event(&mut self, event: Event, ctx: &mut Context) {
+        if let Event::Tick = event {
+            self.on_tick(ctx);
+            return;
+        }
+
         if self.current_st != States::Debug {
             ctx.debug(format!(
                 "[STM] on_event {:?} state current:{:?} prev:{:?}",
@@ -79,104 +191,220 @@ impl MainStm<'_> {
             ));
         }
 
+        if event.is_response() {
+            self.on_response(&event, ctx);
+            return;
+        }
+
+        if self.captures_keys() {
+            if let Some(to_state) = self.dispatch(event, ctx) {
+                self.switch_state(to_state, ctx);
+            }
+            return;
+        }
+
         match (self.current_st, event.clone()) {
             (
-                States::Unknown | States::Home | States::Search,
+                States::Unknown
+                | States::Home
+                | States::Search
+                | States::OrderBook
+                | States::Trades
+                | States::Chart
+                | States::Spreads
+                | States::Balances
+                | States::OpenOrders
+                | States::History
+                | States::OrderEntry,
                 Event::Key {
                     key_code: KeyCode::Char('D'),
                 },
             ) => self.switch_state(States::Debug, ctx),
             (
-                States::Home | States::Search,
+                States::Home
+                | States::Search
+                | States::OrderBook
+                | States::Trades
+                | States::Chart
+                | States::Spreads
+                | States::Balances
+                | States::OpenOrders
+                | States::History
+                | States::OrderEntry,
                 Event::Key {
                     key_code: KeyCode::Char('?'),
                 },
             ) => {
                 self.switch_state(States::Help, ctx);
             }
-            (States::Unknown, _) => {
-                if let Some(to_state) = self.unknow_st.on_event(event, ctx) {
+            // in Search '/' filters the lists of the state itself
+            (
+                States::Home
+                | States::OrderBook
+                | States::Trades
+                | States::Chart
+                | States::Spreads
+                | States::Balances
+                | States::OpenOrders
+                | States::History
+                | States::OrderEntry,
+                Event::Key {
+                    key_code: KeyCode::Char('/'),
+                },
+            )
+            | (
+                States::Home
+                | States::Search
+                | States::OrderBook
+                | States::Trades
+                | States::Chart
+                | States::Spreads
+                | States::Balances
+                | States::OpenOrders
+                | States::History
+                | States::OrderEntry,
+                Event::OpenFinder,
+            ) => self.switch_state(States::Finder, ctx),
+            _ => {
+                if let Some(to_state) = self.dispatch(event, ctx) {
                     self.switch_state(to_state, ctx);
                 }
             }
-            (States::Debug, _) => {
-                if let Some(to_state) = self.debug_st.on_event(event, ctx) {
-                    self.switch_state(to_state, ctx);
-                }
+        }
+    }
+
+    // forwards the event to the current state, returns the state to switch to if any
+    fn dispatch(&mut self, event: Event, ctx: &mut Context) -> Option<States> {
+        match self.current_st {
+            States::Unknown => self.unknow_st.on_event(event, ctx),
+            States::Debug => self.debug_st.on_event(event, ctx),
+            States::Home => self.home_st.on_event(event, ctx),
+            States::Search => self.search_st.on_event(event, ctx),
+            States::OrderBook => self.order_book_st.on_event(event, ctx),
+            States::Trades => self.trades_st.on_event(event, ctx),
+            States::Chart => self.chart_st.on_event(event, ctx),
+            States::Spreads => self.spreads_st.on_event(event, ctx),
+            States::Balances => self.balances_st.on_event(event, ctx),
+            States::OpenOrders => self.open_orders_st.on_event(event, ctx),
+            States::History => self.history_st.on_event(event, ctx),
+            States::OrderEntry => self.order_entry_st.on_event(event, ctx),
+            States::Help => self.help_st.on_event(event, ctx),
+            States::Finder => self.finder_st.on_event(event, ctx),
+            _ => {
+                ctx.debug(format!("[STM] on_event {event:?} not match"));
+                None
             }
-            (States::Home, _) => {
-                if let Some(to_state) = self.home_st.on_event(event, ctx) {
-                    self.switch_state(to_state, ctx);
-                }
+        }
+    }
+
+    // draws the state below an overlay like Help or Finder, returns its help text
+    fn draw_below<B: Backend>(&self, f: &mut Frame<B>, ctx: &mut Context) -> &str {
+        match self.previous_st {
+            Some(States::Unknown) => {
+                self.unknow_st.ui(f, ctx);
+                self.unknow_st.help_text()
             }
-            (States::Search, _) => {
-                if let Some(to_state) = self.search_st.on_event(event, ctx) {
-                    self.switch_state(to_state, ctx);
-                }
+            Some(States::Debug) => {
+                self.debug_st.ui(f, ctx);
+                self.debug_st.help_text()
             }
-            (States::Help, _) => {
-                if let Some(to_state) = self.help_st.on_event(event, ctx) {
-                    self.switch_state(to_state, ctx);
-                }
+            Some(States::Home) => {
+                self.home_st.ui(f, ctx);
+                self.home_st.help_text()
+            }
+            Some(States::Search) => {
+                self.search_st.ui(f, ctx);
+                self.search_st.help_text()
+            }
+            Some(States::OrderBook) => {
+                self.order_book_st.ui(f, ctx);
+                self.order_book_st.help_text()
+            }
+            Some(States::Trades) => {
+                self.trades_st.ui(f, ctx);
+                self.trades_st.help_text()
+            }
+            Some(States::Chart) => {
+                self.chart_st.ui(f, ctx);
+                self.chart_st.help_text()
+            }
+            Some(States::Spreads) => {
+                self.spreads_st.ui(f, ctx);
+                self.spreads_st.help_text()
+            }
+            Some(States::Balances) => {
+                self.balances_st.ui(f, ctx);
+                self.balances_st.help_text()
+            }
+            Some(States::OpenOrders) => {
+                self.open_orders_st.ui(f, ctx);
+                self.open_orders_st.help_text()
+            }
+            Some(States::History) => {
+                self.history_st.ui(f, ctx);
+                self.history_st.help_text()
+            }
+            Some(States::OrderEntry) => {
+                self.order_entry_st.ui(f, ctx);
+                self.order_entry_st.help_text()
+            }
+            _ => {
+                ctx.debug(format!(
+                    "[STM] draw for current:{:?} not match",
+                    self.current_st
+                ));
+                "None"
             }
-            _ => ctx.debug(format!("[STM] on_event {event:?} not match")),
         }
     }
 
     pub fn draw<B: Backend>(&self, f: &mut Frame<B>, ctx: &mut Context) {
         // it Help is the current state, overlay to the previous state ui the help view.
         if self.current_st == States::Help {
-            let text = match self.previous_st {
-                Some(States::Unknown) => {
-                    self.unknow_st.ui(f, ctx);
-                    self.unknow_st.help_text()
-                }
-                Some(States::Debug) => {
-                    self.debug_st.ui(f, ctx);
-                    self.debug_st.help_text()
-                }
-                Some(States::Home) => {
-                    self.home_st.ui(f, ctx);
-                    self.home_st.help_text()
-                }
-                Some(States::Search) => {
-                    self.search_st.ui(f, ctx);
-                    self.search_st.help_text()
-                }
-                _ => {
-                    ctx.debug(format!(
-                        "[STM] draw for current:{:?} not match",
-                        self.current_st
-                    ));
-                    "None"
-                }
-            };
+            let text = self.draw_below(f, ctx);
 
             // overlay the help popup
             let bbox = centered_rect(75, 70, f.size());
-            let bbox_inner = centered_rect(95, 90, bbox);
-            clear_box(f, bbox);
-            draw_box(f, bbox, " Help State ");
-            draw_paragraph(f, bbox_inner, text);
+            draw_popup(f, bbox, " Help State ", text);
+        } else if self.current_st == States::Finder {
+            self.draw_below(f, ctx);
+            self.finder_st.ui(f, ctx);
         } else {
             match self.current_st {
                 States::Unknown => self.unknow_st.ui(f, ctx),
                 States::Debug => self.debug_st.ui(f, ctx),
                 States::Home => self.home_st.ui(f, ctx),
                 States::Search => self.search_st.ui(f, ctx),
+                States::OrderBook => self.order_book_st.ui(f, ctx),
+                States::Trades => self.trades_st.ui(f, ctx),
+                States::Chart => self.chart_st.ui(f, ctx),
+                States::Spreads => self.spreads_st.ui(f, ctx),
+                States::Balances => self.balances_st.ui(f, ctx),
+                States::OpenOrders => self.open_orders_st.ui(f, ctx),
+                States::History => self.history_st.ui(f, ctx),
+                States::OrderEntry => self.order_entry_st.ui(f, ctx),
                 _ => ctx.debug(format!(
                     "[STM] draw for current:{:?} not match",
                     self.current_st
                 )),
             }
         }
+
+        if let Some(error) = ctx.model.status_error() {
+            draw_status_bar(f, f.size(), error);
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crossterm::event::KeyCode;
-    use krakenrs::{AssetPairsResponse, AssetsResponse};
+    use krakenrs::AssetPairsResponse;
+
+    use crate::kraken::messages::{AssetsResponse, TickerResponse, TradesResponse};
+    use crate::kraken::ws_client::MockMarketDataAPI;
+    use crate::kraken::ws_messages::{Feed, Subscription};
+    use mockall::predicate::eq;
 
     use crate::{kraken::client::MockRestAPI, stm::events::Event};
 
@@ -189,11 +417,12 @@ mod tests {
         mock_client
             .expect_list_assets()
             .once()
-            .returning(|| Some(AssetsResponse::new()));
+            .returning(|| Ok(AssetsResponse::new()));
+        // downloaded by the Home state and again by the Search state
         mock_client
             .expect_list_asset_pairs()
-            .once()
-            .returning(|| Some(AssetPairsResponse::new()));
+            .times(2)
+            .returning(|| Ok(AssetPairsResponse::new()));
         let mut ctx = Context::new_for_testing(mock_client);
 
         let mut stm = MainStm::new("my_stm", false);
@@ -256,6 +485,114 @@ mod tests {
         stm.on_event(e, &mut ctx);
         assert!(matches!(stm.current_st, States::Search));
 
+        // the search filter input takes the global shortcuts as text
+        for key_code in [KeyCode::Char('/'), KeyCode::Char('D'), KeyCode::Char('?')] {
+            stm.on_event(Event::Key { key_code }, &mut ctx);
+        }
+        assert!(matches!(stm.current_st, States::Search));
+        assert!(stm.captures_keys());
+
         Ok(())
     }
+
+    #[test]
+    fn test_stm_finder() {
+        let mut ctx = Context::new_for_testing(Box::new(MockRestAPI::new()));
+
+        let mut stm = MainStm::new("my_stm", false);
+        stm.current_st = States::Trades;
+
+        stm.on_event(
+            Event::Key {
+                key_code: KeyCode::Char('/'),
+            },
+            &mut ctx,
+        );
+        assert!(matches!(stm.current_st, States::Finder));
+        // typed in the query
+        stm.on_event(
+            Event::Key {
+                key_code: KeyCode::Char('D'),
+            },
+            &mut ctx,
+        );
+        assert!(matches!(stm.current_st, States::Finder));
+
+        stm.on_event(
+            Event::Key {
+                key_code: KeyCode::Esc,
+            },
+            &mut ctx,
+        );
+        assert!(matches!(stm.current_st, States::Trades));
+
+        // in Search '/' is the filter of the state, Ctrl-P opens the finder
+        stm.current_st = States::Search;
+        stm.on_event(Event::OpenFinder, &mut ctx);
+        assert!(matches!(stm.current_st, States::Finder));
+    }
+
+    #[test]
+    fn test_finder_leaves_the_state_below() {
+        let mut mock_client = Box::new(MockRestAPI::new());
+        mock_client
+            .expect_recent_trades()
+            .once()
+            .returning(|_, _| Ok(TradesResponse::default()));
+        mock_client
+            .expect_ticker()
+            .once()
+            .returning(|_| Ok(TickerResponse::new()));
+        let mut mock_api = Box::new(MockMarketDataAPI::new());
+        mock_api.expect_subscribe().times(2).returning(|_| Ok(()));
+        mock_api
+            .expect_unsubscribe()
+            .with(eq(Subscription::new("XBT/USD", Feed::Trade)))
+            .once()
+            .returning(|_| Ok(()));
+        let mut ctx = Context::new_for_testing_with_stream(mock_client, mock_api);
+        ctx.model.asset_pairs = serde_json::from_str(
+            r#"{ "XXBTZUSD": {
+              "alt_name": "XBTUSD", "wsname": "XBT/USD", "aclass_base": "currency", "base": "XXBT",
+              "aclass_quote": "currency", "quote": "ZUSD", "pair_decimals": 1, "lot_decimals": 8,
+              "lot_multiplier": 1, "fees": [], "ordermin": "0.0001"
+            } }"#,
+        )
+        .unwrap();
+        ctx.model
+            .favorites_asset_pairs_stateful
+            .push("XBT/USD".to_owned());
+        ctx.model.favorites_asset_pairs_stateful.next();
+
+        let mut stm = MainStm::new("my_stm", false);
+        stm.home_st.on_enter_first = false;
+        stm.switch_state(States::Trades, &mut ctx);
+        assert!(stm.trades_st.is_polling());
+
+        // Ctrl-P then Enter on the favorite
+        stm.on_event(Event::OpenFinder, &mut ctx);
+        stm.on_event(
+            Event::Key {
+                key_code: KeyCode::Enter,
+            },
+            &mut ctx,
+        );
+        assert!(matches!(stm.current_st, States::Home));
+
+        assert!(!stm.trades_st.is_polling());
+        stm.on_event(Event::Tick, &mut ctx);
+    }
+
+    #[test]
+    fn test_stm_tick() {
+        let mut ctx = Context::new_for_testing(Box::new(MockRestAPI::new()));
+
+        let mut stm = MainStm::new("my_stm", false);
+        stm.current_st = States::Search;
+
+        stm.on_event(Event::Tick, &mut ctx);
+
+        assert!(matches!(stm.current_st, States::Search));
+        assert_eq!(ctx.model.debug_messages_stateful.items.len(), 0);
+    }
 }
@@ -0,0 +1,218 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum StorageError {
+    #[error("io error {0}")]
+    Io(#[from] io::Error),
+    #[error("json error {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+// name of the list of the files saved before the watchlists, and of the first one
+pub const DEFAULT_WATCHLIST: &str = "favorites";
+
+// a named list of asset pairs, shown as a tab in Home
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Watchlist {
+    pub name: String,
+    pub pairs: Vec<String>,
+}
+
+impl Watchlist {
+    pub fn new(name: &str) -> Watchlist {
+        Watchlist {
+            name: name.to_owned(),
+            pairs: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct FavoritesFile {
+    // the single list saved by the previous versions
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    favorites: Vec<String>,
+    #[serde(default)]
+    watchlists: Vec<Watchlist>,
+    // name of the watchlist shown at startup
+    #[serde(default)]
+    active: String,
+}
+
+// returns the watchlists in the saved order and the index of the active one, a single empty
+// list if the file does not exist yet.
+pub fn load_watchlists(path: &Path) -> Result<(Vec<Watchlist>, usize), StorageError> {
+    let file: FavoritesFile = match fs::read_to_string(path) {
+        Ok(text) => serde_json::from_str(&text)?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => FavoritesFile::default(),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut watchlists = file.watchlists;
+    if watchlists.is_empty() {
+        watchlists.push(Watchlist {
+            name: DEFAULT_WATCHLIST.to_owned(),
+            pairs: file.favorites,
+        });
+    }
+    let active = watchlists
+        .iter()
+        .position(|watchlist| watchlist.name == file.active)
+        .unwrap_or(0);
+
+    Ok((watchlists, active))
+}
+
+pub fn save_watchlists(
+    path: &Path,
+    watchlists: &[Watchlist],
+    active: usize,
+) -> Result<(), StorageError> {
+    let file = FavoritesFile {
+        favorites: vec![],
+        watchlists: watchlists.to_vec(),
+        active: watchlists
+            .get(active)
+            .map(|watchlist| watchlist.name.clone())
+            .unwrap_or_default(),
+    };
+    write_atomically(path, &serde_json::to_string_pretty(&file)?)
+}
+
+// reference data downloaded from kraken, with the unix time of the download
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Cached<T> {
+    pub saved_at: u64,
+    pub data: T,
+}
+
+// None if the data has not been cached yet.
+pub fn load_cache<T: DeserializeOwned>(path: &Path) -> Result<Option<Cached<T>>, StorageError> {
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(Some(serde_json::from_str(&text)?)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn save_cache<T: Serialize>(path: &Path, saved_at: u64, data: &T) -> Result<(), StorageError> {
+    let cached = Cached { saved_at, data };
+    write_atomically(path, &serde_json::to_string(&cached)?)
+}
+
+// moves aside a file which can not be parsed so that it is not overwritten by the next save.
+pub fn backup_corrupted(path: &Path) -> Result<PathBuf, StorageError> {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(".corrupted");
+    let backup = PathBuf::from(backup);
+
+    fs::rename(path, &backup)?;
+    Ok(backup)
+}
+
+fn write_atomically(path: &Path, text: &str) -> Result<(), StorageError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    fs::write(&tmp, text)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+// tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("kraken_tui_{name}_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_watchlists_round_trip() {
+        let dir = temp_dir("favorites");
+        let path = dir.join("favorites.json");
+
+        assert_eq!(
+            load_watchlists(&path).unwrap(),
+            (vec![Watchlist::new(DEFAULT_WATCHLIST)], 0)
+        );
+
+        let watchlists = vec![
+            Watchlist {
+                name: "spot".to_owned(),
+                pairs: vec!["XBT/EUR".to_owned(), "ETH/USDC".to_owned()],
+            },
+            Watchlist {
+                name: "fx".to_owned(),
+                pairs: vec!["EUR/USD".to_owned()],
+            },
+        ];
+        save_watchlists(&path, &watchlists, 1).unwrap();
+
+        assert_eq!(load_watchlists(&path).unwrap(), (watchlists, 1));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_legacy_favorites() {
+        let dir = temp_dir("legacy");
+        let path = dir.join("favorites.json");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&path, r#"{"favorites": ["XBT/EUR", "ETH/USDC"]}"#).unwrap();
+
+        let (watchlists, active) = load_watchlists(&path).unwrap();
+        assert_eq!(watchlists.len(), 1);
+        assert_eq!(watchlists[0].name, DEFAULT_WATCHLIST);
+        assert_eq!(watchlists[0].pairs, vec!["XBT/EUR", "ETH/USDC"]);
+        assert_eq!(active, 0);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_favorites_corrupted() {
+        let dir = temp_dir("corrupted");
+        let path = dir.join("favorites.json");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&path, "{ not json").unwrap();
+
+        assert!(matches!(load_watchlists(&path), Err(StorageError::Json(_))));
+
+        let backup = backup_corrupted(&path).unwrap();
+        assert!(backup.exists());
+        assert!(load_watchlists(&path).unwrap().0[0].pairs.is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_cache_round_trip() {
+        let dir = temp_dir("cache");
+        let path = dir.join("cache").join("assets.json");
+
+        assert!(load_cache::<Vec<String>>(&path).unwrap().is_none());
+
+        let assets = vec!["XXBT".to_owned(), "ZUSD".to_owned()];
+        save_cache(&path, 1_700_000_000, &assets).unwrap();
+
+        let cached = load_cache::<Vec<String>>(&path).unwrap().unwrap();
+        assert_eq!(cached.saved_at, 1_700_000_000);
+        assert_eq!(cached.data, assets);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,2 +1,4 @@
+pub(crate) mod chart;
 pub(crate) mod core;
+pub(crate) mod format;
 pub(crate) mod list_stateful_widget;
@@ -0,0 +1,172 @@
+use tui::{
+    backend::Backend,
+    layout::Rect,
+    style::{Color, Style},
+    symbols::Marker,
+    text::Span,
+    widgets::{
+        canvas::{Canvas, Line},
+        Axis, Block, Borders, Chart, Dataset, GraphType, Sparkline,
+    },
+    Frame,
+};
+
+use crate::kraken::messages::Candle;
+
+// lowest low and highest high of the candles, None if there are no candles
+pub fn price_bounds(candles: &[Candle]) -> Option<(f64, f64)> {
+    candles.iter().fold(None, |bounds, candle| match bounds {
+        None => Some((candle.low, candle.high)),
+        Some((low, high)) => Some((candle.low.min(low), candle.high.max(high))),
+    })
+}
+
+// the latest candles fitting the given width, one column each
+pub fn visible_candles(candles: &[Candle], width: u16) -> &[Candle] {
+    let width = usize::from(width);
+    &candles[candles.len().saturating_sub(width)..]
+}
+
+fn price_labels(low: f64, high: f64, decimals: usize) -> Vec<Span<'static>> {
+    vec![
+        Span::raw(format!("{low:.decimals$}")),
+        Span::raw(format!("{:.decimals$}", low + (high - low) / 2.0)),
+        Span::raw(format!("{high:.decimals$}")),
+    ]
+}
+
+#[allow(clippy::cast_precision_loss)]
+pub fn draw_line_chart<B: Backend>(
+    f: &mut Frame<B>,
+    bbox: Rect,
+    title: &str,
+    candles: &[Candle],
+    decimals: usize,
+) {
+    let (low, high) = price_bounds(candles).unwrap_or((0.0, 1.0));
+    let points = candles
+        .iter()
+        .enumerate()
+        .map(|(index, candle)| (index as f64, candle.close))
+        .collect::<Vec<_>>();
+
+    let datasets = vec![Dataset::default()
+        .marker(Marker::Braille)
+        .graph_type(GraphType::Line)
+        .style(Style::default().fg(Color::Cyan))
+        .data(&points)];
+
+    let chart = Chart::new(datasets)
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .x_axis(Axis::default().bounds([0.0, candles.len().max(1) as f64]))
+        .y_axis(
+            Axis::default()
+                .bounds([low, high])
+                .labels(price_labels(low, high, decimals)),
+        );
+
+    f.render_widget(chart, bbox);
+}
+
+// wick from low to high, body from open to close: green when bullish, red otherwise
+#[allow(clippy::cast_precision_loss)]
+pub fn draw_candlestick_chart<B: Backend>(
+    f: &mut Frame<B>,
+    bbox: Rect,
+    title: &str,
+    candles: &[Candle],
+) {
+    let (low, high) = price_bounds(candles).unwrap_or((0.0, 1.0));
+
+    let canvas = Canvas::default()
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .marker(Marker::Braille)
+        .x_bounds([0.0, candles.len().max(1) as f64])
+        .y_bounds([low, high])
+        .paint(|ctx| {
+            for (index, candle) in candles.iter().enumerate() {
+                let x = index as f64 + 0.5;
+                let color = if candle.is_bullish() {
+                    Color::Green
+                } else {
+                    Color::Red
+                };
+
+                ctx.draw(&Line {
+                    x1: x,
+                    y1: candle.low,
+                    x2: x,
+                    y2: candle.high,
+                    color,
+                });
+                for offset in [-0.3, 0.3] {
+                    ctx.draw(&Line {
+                        x1: x + offset,
+                        y1: candle.open,
+                        x2: x + offset,
+                        y2: candle.close,
+                        color,
+                    });
+                }
+            }
+        });
+
+    f.render_widget(canvas, bbox);
+}
+
+// volumes scaled to the highest one, the sparkline only accepts integers
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub fn draw_volumes<B: Backend>(f: &mut Frame<B>, bbox: Rect, title: &str, candles: &[Candle]) {
+    let max_volume = candles
+        .iter()
+        .map(|candle| candle.volume)
+        .fold(0.0, f64::max);
+    let volumes = candles
+        .iter()
+        .map(|candle| {
+            if max_volume > 0.0 {
+                (candle.volume / max_volume * 100.0).round() as u64
+            } else {
+                0
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let sparkline = Sparkline::default()
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .style(Style::default().fg(Color::Yellow))
+        .max(100)
+        .data(&volumes);
+
+    f.render_widget(sparkline, bbox);
+}
+
+// tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candle(low: f64, high: f64) -> Candle {
+        Candle {
+            high,
+            low,
+            ..Candle::default()
+        }
+    }
+
+    #[test]
+    fn test_price_bounds() {
+        assert_eq!(price_bounds(&[]), None);
+
+        let candles = vec![candle(10.0, 12.0), candle(9.0, 11.0), candle(11.0, 15.0)];
+        assert_eq!(price_bounds(&candles), Some((9.0, 15.0)));
+    }
+
+    #[test]
+    fn test_visible_candles() {
+        let candles = vec![candle(1.0, 2.0), candle(3.0, 4.0), candle(5.0, 6.0)];
+
+        assert_eq!(visible_candles(&candles, 2), &candles[1..]);
+        assert_eq!(visible_candles(&candles, 10), &candles[..]);
+    }
+}
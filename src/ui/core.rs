@@ -1,7 +1,9 @@
 use tui::{
     backend::Backend,
-    layout::{Alignment, Constraint, Direction, Layout, Rect},
-    widgets::{Block, BorderType, Borders, Clear, Paragraph, Wrap},
+    layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
+    style::{Color, Modifier, Style},
+    text::Spans,
+    widgets::{Block, BorderType, Borders, Clear, Paragraph, Tabs, Wrap},
     Frame,
 };
 
@@ -29,6 +31,51 @@ pub fn clear_box<B: Backend>(f: &mut Frame<B>, bbox: Rect) {
     f.render_widget(Clear, bbox);
 }
 
+// clears the area below and draws a bordered box with the text inside
+pub fn draw_popup<B: Backend>(f: &mut Frame<B>, bbox: Rect, title: &str, text: &str) {
+    clear_box(f, bbox);
+    draw_box(f, bbox, title);
+    let bbox_inner = bbox.inner(&Margin {
+        vertical: 1,
+        horizontal: 2,
+    });
+    draw_paragraph(f, bbox_inner, text);
+}
+
+// draws the text over the bottom border of the box, like the title on the top one
+pub fn draw_status_bar<B: Backend>(f: &mut Frame<B>, bbox: Rect, text: &str) {
+    if bbox.height == 0 || bbox.width <= 4 {
+        return;
+    }
+    let bbox_status = Rect::new(bbox.x + 2, bbox.bottom() - 1, bbox.width - 4, 1);
+    let paragraph = Paragraph::new(format!(" {text} ")).style(Style::default().fg(Color::Red));
+    f.render_widget(paragraph, bbox_status);
+}
+
+// one tab per title inside a bordered box, the selected one highlighted
+pub fn draw_tabs<B: Backend>(
+    f: &mut Frame<B>,
+    bbox: Rect,
+    title: &str,
+    titles: &[String],
+    selected: usize,
+) {
+    let titles = titles
+        .iter()
+        .map(|title| Spans::from(title.as_str()))
+        .collect::<Vec<_>>();
+    let widget = Tabs::new(titles)
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .select(selected)
+        .highlight_style(
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        );
+
+    f.render_widget(widget, bbox);
+}
+
 pub fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::default()
         .direction(Direction::Vertical)
@@ -140,6 +187,86 @@ mod tests {
         terminal.backend().assert_buffer(&expected);
     }
 
+    #[test]
+    fn test_draw_popup() {
+        let backend = TestBackend::new(9, 5);
+        let mut terminal = Terminal::new(backend).unwrap();
+
+        terminal
+            .draw(|f| {
+                let size = f.size();
+
+                draw_paragraph(f, size, "#########################################");
+                draw_popup(f, Rect::new(1, 0, 7, 5), " x ", "y/n");
+            })
+            .unwrap();
+
+        #[rustfmt::skip]
+        let expected = Buffer::with_lines(vec![
+            "#╭─ x ─╮#",
+            "#│ y/n │#",
+            "#│     │#",
+            "#│     │#",
+            "#╰─────╯ "
+            ]);
+
+        terminal.backend().assert_buffer(&expected);
+    }
+
+    #[test]
+    fn test_draw_status_bar() {
+        let backend = TestBackend::new(11, 3);
+        let mut terminal = Terminal::new(backend).unwrap();
+
+        terminal
+            .draw(|f| {
+                let size = f.size();
+
+                draw_box(f, size, "");
+                draw_status_bar(f, size, "error");
+            })
+            .unwrap();
+
+        #[rustfmt::skip]
+        let mut expected = Buffer::with_lines(vec![
+            "╭─────────╮",
+            "│         │",
+            "╰─ error ─╯"
+            ]);
+        expected.set_style(Rect::new(2, 2, 7, 1), Style::default().fg(Color::Red));
+
+        terminal.backend().assert_buffer(&expected);
+    }
+
+    #[test]
+    fn test_draw_tabs() {
+        let backend = TestBackend::new(11, 3);
+        let mut terminal = Terminal::new(backend).unwrap();
+
+        terminal
+            .draw(|f| {
+                let size = f.size();
+
+                draw_tabs(f, size, "", &["a".to_owned(), "b".to_owned()], 1);
+            })
+            .unwrap();
+
+        #[rustfmt::skip]
+        let mut expected = Buffer::with_lines(vec![
+            "┌─────────┐",
+            "│ a │ b   │",
+            "└─────────┘"
+            ]);
+        expected.set_style(
+            Rect::new(6, 1, 1, 1),
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        );
+
+        terminal.backend().assert_buffer(&expected);
+    }
+
     #[test]
     fn test_centered_rect() {
         let rect = Rect::new(2, 3, 10, 10);
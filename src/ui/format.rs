@@ -0,0 +1,85 @@
+const SECONDS_PER_DAY: u64 = 86_400;
+
+// HH:MM:SS of a unix timestamp, in UTC
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub fn format_time_utc(unix_time: f64) -> String {
+    let seconds = unix_time.max(0.0) as u64 % SECONDS_PER_DAY;
+
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        (seconds % 3600) / 60,
+        seconds % 60
+    )
+}
+
+// YYYY-MM-DD HH:MM:SS of a unix timestamp, in UTC
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_possible_wrap
+)]
+pub fn format_datetime_utc(unix_time: f64) -> String {
+    let days = (unix_time.max(0.0) as u64 / SECONDS_PER_DAY) as i64;
+
+    // civil from days, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {}",
+        format_time_utc(unix_time)
+    )
+}
+
+// elapsed time in the largest unit, e.g. 45s, 12m, 3h, 2d
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub fn format_age(seconds: f64) -> String {
+    let seconds = seconds.max(0.0) as u64;
+
+    match seconds {
+        0..=59 => format!("{seconds}s"),
+        60..=3599 => format!("{}m", seconds / 60),
+        3600..=86_399 => format!("{}h", seconds / 3600),
+        _ => format!("{}d", seconds / SECONDS_PER_DAY),
+    }
+}
+
+// tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_time_utc() {
+        assert_eq!(format_time_utc(0.0), "00:00:00");
+        assert_eq!(format_time_utc(1_688_671_834.123), "19:30:34");
+        assert_eq!(format_time_utc(-1.0), "00:00:00");
+    }
+
+    #[test]
+    fn test_format_datetime_utc() {
+        assert_eq!(format_datetime_utc(0.0), "1970-01-01 00:00:00");
+        assert_eq!(format_datetime_utc(951_782_400.0), "2000-02-29 00:00:00");
+        assert_eq!(
+            format_datetime_utc(1_688_671_834.123),
+            "2023-07-06 19:30:34"
+        );
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(-5.0), "0s");
+        assert_eq!(format_age(59.9), "59s");
+        assert_eq!(format_age(720.0), "12m");
+        assert_eq!(format_age(3.0 * 3600.0), "3h");
+        assert_eq!(format_age(2.5 * 86_400.0), "2d");
+    }
+}
@@ -2,6 +2,7 @@ use tui::{
     backend::Backend,
     layout::{Corner, Rect},
     style::{Color, Modifier, Style},
+    text::{Span, Spans},
     widgets::{Block, Borders, List, ListItem, ListState},
     Frame,
 };
@@ -106,6 +107,57 @@ pub fn draw_stateful_list<B: Backend>(
     f.render_stateful_widget(widget, bbox, &mut stateful_list.state);
 }
 
+// splits the text around the first case-insensitive match of the query, the match is
+// underlined in yellow.
+pub fn highlight_match<'a>(text: &'a str, query: &str) -> Spans<'a> {
+    let lowercase = text.to_ascii_lowercase();
+    let start = match lowercase.find(&query.to_ascii_lowercase()) {
+        Some(start) if !query.is_empty() => start,
+        _ => return Spans::from(text),
+    };
+    let end = start + query.len();
+
+    Spans::from(vec![
+        Span::raw(&text[..start]),
+        Span::styled(
+            &text[start..end],
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::UNDERLINED),
+        ),
+        Span::raw(&text[end..]),
+    ])
+}
+
+// like draw_stateful_list, with the matches of the query highlighted. The items are shown
+// through label, e.g. with a count next to them.
+pub fn draw_highlighted_list<B: Backend>(
+    f: &mut Frame<B>,
+    bbox: Rect,
+    title: &str,
+    stateful_list: &mut StatefulList<String>,
+    query: &str,
+    label: impl Fn(&str) -> String,
+) {
+    let labels: Vec<String> = stateful_list.items.iter().map(|item| label(item)).collect();
+    let items: Vec<ListItem> = labels
+        .iter()
+        .map(|label| ListItem::new(highlight_match(label, query)))
+        .collect();
+
+    let widget = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(
+            Style::default()
+                .bg(Color::White)
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+        )
+        .start_corner(Corner::TopLeft);
+
+    f.render_stateful_widget(widget, bbox, &mut stateful_list.state);
+}
+
 // tests
 #[cfg(test)]
 mod tests {
@@ -200,6 +252,24 @@ mod tests {
         assert_eq!(stateful_list.state.selected(), None);
     }
 
+    #[test]
+    fn test_highlight_match() {
+        let spans = highlight_match("XBT/USD", "bt/");
+        assert_eq!(
+            spans
+                .0
+                .iter()
+                .map(|span| span.content.as_ref())
+                .collect::<Vec<_>>(),
+            vec!["X", "BT/", "USD"]
+        );
+        assert_eq!(spans.0[1].style.fg, Some(Color::Yellow));
+
+        assert_eq!(highlight_match("XBT/USD", "eth").0.len(), 1);
+        assert_eq!(highlight_match("XBT/USD", "").0.len(), 1);
+        assert_eq!(highlight_match("XBT/USD", "").width(), 7);
+    }
+
     #[test]
     fn test_stateful_list_with_title() {
         let backend = TestBackend::new(7, 4);